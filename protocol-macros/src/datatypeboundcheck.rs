@@ -39,7 +39,7 @@ pub fn datatype_bound_check_derive(input: TokenStream) -> TokenStream {
     // Generate the implementation
     let gen = quote! {
         impl #datatypes_reader_path::DataTypeBoundCheck for #struct_name {
-            fn check_bounds(&self, datareader: &mut #datatypes_reader_path::DataTypeReader) -> #datatypes_reader_path::Result<()> {
+            fn check_bounds<ReaderSource: #datatypes_reader_path::ReadSeek>(&self, datareader: &mut #datatypes_reader_path::DataTypeReader<ReaderSource>) -> #datatypes_reader_path::Result<()> {
                 #(#fields)*
                 Ok(())
             }
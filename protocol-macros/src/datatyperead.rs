@@ -1,5 +1,6 @@
 use std::rc::Rc;
 
+mod enum_options;
 mod field_options;
 
 mod argument_parser;
@@ -26,6 +27,7 @@ pub struct StructAttr {
     pub datatype: StructDataType,    // datatype overwrite for the struct
     pub ommit_trait: OmmitableTrait, // can ommit either DataTypeRead or DatatypeSize
     pub internal: bool,              // to use crate instead of quakeworld as prefix
+    pub tag: Option<syn::Ident>,     // type of the tag preceding the variants of an enum
 }
 
 macro_rules! return_syn_error_parser_apply_function {
@@ -44,6 +46,7 @@ impl ParserApplyFunction for StructAttr {
             "internal" => internal_apply_to_struct(attribute, self)?,
             "datatype" => datatype_apply_to_struct(attribute, self)?,
             "ommit_trait" => ommit_trait_apply_to_struct(attribute, self)?,
+            "tag" => tag_apply_to_struct(attribute, self)?,
             _ => {
                 return Err(syn::Error::new(
                     attribute.name_ident.span(),
@@ -68,7 +71,7 @@ macro_rules! attribute_value_error {
 }
 
 #[derive(Default, PartialEq, Eq)]
-enum OffsetParsed {
+pub(crate) enum OffsetParsed {
     #[default]
     None,
     Auto,
@@ -102,7 +105,7 @@ impl From<&SizeOffset> for OffsetParsed {
 }
 
 #[derive(Default, PartialEq, Eq)]
-enum SizeParsed {
+pub(crate) enum SizeParsed {
     #[default]
     None,
     Auto,
@@ -136,7 +139,7 @@ impl From<&SizeOffset> for SizeParsed {
 }
 
 #[derive(Default, PartialEq, Eq, Clone, Debug)]
-enum SizeRecalc {
+pub(crate) enum SizeRecalc {
     #[default]
     None,
     ModuloSelfEnvironment,
@@ -324,6 +327,20 @@ fn ommit_trait_apply_to_struct(
     Ok(())
 }
 
+fn tag_apply_to_struct(
+    attribute: &AttributeParse,
+    struct_attributes: &mut StructAttr,
+) -> syn::Result<()> {
+    match &attribute.parsed_value {
+        AttributeTypeParsed::None => {}
+        AttributeTypeParsed::Single(AttributeValue::Ident(ident)) => {
+            struct_attributes.tag = Some(ident.clone());
+        }
+        _ => return_syn_error!(attribute.name_ident, "non Type"),
+    }
+    Ok(())
+}
+
 impl Parse for StructAttr {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut struct_attributes = StructAttr::default();
@@ -362,6 +379,13 @@ impl Parse for StructAttr {
         // a.apply_to_struct = Some(ommit_trait_apply_to_struct);
         attribute_parser.add_attribute(a);
 
+        // tag
+        let mut a = AttributeParse::new("tag");
+        a.name_ident = format_ident!("tag");
+        // only allows a single Ident, the integer type of the tag
+        a.add_type(AttributeType::Single(vec![AttributeTypeAllowed::Ident]));
+        attribute_parser.add_attribute(a);
+
         attribute_parser.parse_attributes(&input)?;
 
         for attr in &attribute_parser.attributes {
@@ -373,7 +397,7 @@ impl Parse for StructAttr {
 }
 
 #[derive(Debug, Clone, Default)]
-enum SizeOffset {
+pub(crate) enum SizeOffset {
     #[default]
     None,
     SizeInt(syn::LitInt),
@@ -391,7 +415,7 @@ enum SizeOffset {
 }
 
 #[derive(Debug, Clone, Default)]
-enum Environment {
+pub(crate) enum Environment {
     #[default]
     None,
     Auto,
@@ -408,7 +432,7 @@ enum FieldAttribute {
 }
 
 #[derive(Debug, Default, Clone)]
-struct FieldAttr {
+pub(crate) struct FieldAttr {
     pub attributes: Vec<FieldAttribute>,
     pub set_size: SizeOffset,
     pub set_offset: SizeOffset,
@@ -801,43 +825,46 @@ struct StructInformation {
     pub attributes: StructAttr,
 }
 
+pub(crate) fn parse_struct_attributes(attrs: &[Attribute]) -> syn::Result<StructAttr> {
+    let mut struct_attrib = StructAttr::default();
+    for attr in attrs {
+        if !attr.path.is_ident("datatyperead") {
+            continue;
+        }
+        let sa = attr.parse_args_with(Punctuated::<StructAttr, Token![,]>::parse_terminated)?;
+        struct_attrib = sa[0].clone();
+    }
+    Ok(struct_attrib)
+}
+
 pub fn datatyperead_derive_2(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    let struct_attrib = match parse_struct_attributes(&input.attrs) {
+        Ok(struct_attrib) => struct_attrib,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     // check if we are implementing on a struct
     let data_struct = match input.data {
         syn::Data::Struct(data_struct) => data_struct,
         syn::Data::Enum(data_enum) => {
-            return quote_spanned! {
-                data_enum.enum_token.span =>
-                compile_error!("DataTypeReader can only be used on structs not enums");
-            }
-            .into()
+            return enum_options::datatyperead_enum(
+                input.ident,
+                input.generics,
+                data_enum,
+                struct_attrib,
+            )
         }
         syn::Data::Union(data_union) => {
             return quote_spanned! {
                 data_union.union_token.span =>
-                compile_error!("DataTypeReader can only be used on structs not unions");
+                compile_error!("DataTypeReader can only be used on structs and enums not unions");
             }
             .into()
         }
     };
 
-    let mut struct_attrib = StructAttr::default();
-    for attr in &input.attrs {
-        if !attr.path.is_ident("datatyperead") {
-            continue;
-        }
-
-        let attr_span = attr.span();
-        let a = attr.parse_args_with(Punctuated::<StructAttr, Token![,]>::parse_terminated);
-        if let Ok(sa) = a {
-            {
-                struct_attrib = sa[0].clone();
-            }
-        };
-    }
-
     let is_generic = input.generics.params.first().is_some();
 
     let mut struct_information = StructInformation {
@@ -937,10 +964,12 @@ pub fn datatyperead_derive_2(input: TokenStream) -> TokenStream {
         let field_environment = match &f.attributes.environment {
             Environment::None => quote! {},
             Environment::Auto => quote! {
-                #field_identifier . environment( datareader , stringify!(#field_name));
+                <#field_type as #datatypes_reader_path ::DataTypeRead>::environment(
+                    &#field_identifier, datareader, stringify!(#field_name));
             },
             Environment::String(lit_str) => quote! {
-                #field_identifier . environment( datareader , #lit_str);
+                <#field_type as #datatypes_reader_path ::DataTypeRead>::environment(
+                    &#field_identifier, datareader, #lit_str);
             },
             Environment::Ident(ident) => {
                 quote! {}
@@ -1118,7 +1147,7 @@ pub fn datatyperead_derive_2(input: TokenStream) -> TokenStream {
 
     let read_trait = quote! {
         impl #struct_impl_generics #datatypes_reader_path::DataTypeRead for #si_identifier #struct_type_generics #struct_where_clause {
-            fn read(datareader: &mut #datatypes_reader_path::DataTypeReader) -> #datatypes_reader_path::Result <Self> {
+            fn read<ReaderSource: #datatypes_reader_path::ReadSeek>(datareader: &mut #datatypes_reader_path::DataTypeReader<ReaderSource>) -> #datatypes_reader_path::Result <Self> {
                 #crate_prefix ::trace::trace_start!(datareader, stringify!( #si_identifier));
 
                     #(#field_creations)*
@@ -0,0 +1,239 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Token,
+};
+
+use super::argument_parser::{
+    AttributeParse, AttributeParser, AttributeType, AttributeTypeAllowed, AttributeTypeParsed,
+    AttributeValue, ParserApplyFunction, StructDataType,
+};
+use super::StructAttr;
+
+/// attributes of an enum variant
+/// `tag = 0` is the value of the tag selecting the variant,
+/// `fallback` selects the variant for every tag no other variant claims
+#[derive(Debug, Default, Clone)]
+pub(crate) struct VariantAttr {
+    pub tag: Option<syn::LitInt>,
+    pub fallback: bool,
+}
+
+impl ParserApplyFunction for VariantAttr {
+    fn apply_parsed_attribute(&mut self, attribute: &AttributeParse) -> syn::Result<()> {
+        match (attribute.name.as_str(), &attribute.parsed_value) {
+            (_, AttributeTypeParsed::None) => {}
+            ("tag", AttributeTypeParsed::Single(AttributeValue::Int(lit_int))) => {
+                self.tag = Some(lit_int.clone())
+            }
+            ("fallback", AttributeTypeParsed::Blank) => self.fallback = true,
+            _ => {
+                return Err(syn::Error::new(
+                    attribute.name_ident.span(),
+                    format!("`{}` attribute does not support this value", attribute.name),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Parse for VariantAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut variant_attributes = VariantAttr::default();
+
+        let mut attribute_parser = AttributeParser::default();
+
+        // tag
+        let mut a = AttributeParse::new("tag");
+        a.name_ident = format_ident!("tag");
+        // only allows a single Int
+        a.add_type(AttributeType::Single(vec![AttributeTypeAllowed::Int]));
+        attribute_parser.add_attribute(a);
+
+        // fallback
+        let mut a = AttributeParse::new("fallback");
+        a.name_ident = format_ident!("fallback");
+        // only allow it to be present
+        a.add_type(AttributeType::Blank);
+        attribute_parser.add_attribute(a);
+
+        attribute_parser.parse_attributes(&input)?;
+
+        for attr in &attribute_parser.attributes {
+            variant_attributes.apply_parsed_attribute(attr)?;
+        }
+        Ok(variant_attributes)
+    }
+}
+
+/// DataTypeRead and DataTypeSize for enums: a tag of the type given with `tag` is read
+/// followed by the payload of the variant it selects.
+/// variants carry their payload as a single unnamed field, unit variants without a `tag` or
+/// `fallback` (e.g. a `#[default] None`) are never read
+pub(crate) fn datatyperead_enum(
+    identifier: syn::Ident,
+    generics: syn::Generics,
+    data_enum: syn::DataEnum,
+    attributes: StructAttr,
+) -> TokenStream {
+    let tag_type = match &attributes.tag {
+        Some(tag_type) => tag_type.clone(),
+        None => {
+            return quote_spanned! {
+                data_enum.enum_token.span =>
+                compile_error!("DataTypeReader needs the type of the tag for enums, e.g. `#[datatyperead(tag = u32)]`");
+            }
+            .into()
+        }
+    };
+
+    let crate_prefix = match attributes.internal {
+        true => quote!(crate),
+        false => quote!(quakeworld),
+    };
+    let datatypes_reader_path = quote! {#crate_prefix ::datatypes :: reader};
+
+    let mut variant_reads = vec![];
+    let mut variant_sizes = vec![];
+    let mut fallback = None;
+    for variant in data_enum.variants.iter() {
+        let variant_span = variant.span();
+        let variant_name = variant.ident.clone();
+
+        let mut variant_attributes = VariantAttr::default();
+        for attr in &variant.attrs {
+            if !attr.path.is_ident("datatyperead") {
+                continue;
+            }
+            match attr.parse_args_with(Punctuated::<VariantAttr, Token![,]>::parse_terminated) {
+                Ok(a) => variant_attributes = a[0].clone(),
+                Err(e) => return e.to_compile_error().into(),
+            }
+        }
+
+        let creation = match &variant.fields {
+            syn::Fields::Unit => quote! { Self:: #variant_name },
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let payload_type = fields.unnamed[0].ty.clone();
+                variant_sizes.push(quote! {
+                    size = size.max(<#payload_type as #datatypes_reader_path ::DataTypeSize>::datatype_size());
+                });
+                quote! {
+                    Self:: #variant_name (<#payload_type as #datatypes_reader_path ::DataTypeRead>::read(datareader)?)
+                }
+            }
+            _ => {
+                return quote_spanned! {
+                    variant_span =>
+                    compile_error!("DataTypeReader variants can only carry a single unnamed field");
+                }
+                .into()
+            }
+        };
+
+        let read = quote! {
+            {
+                #crate_prefix ::trace::trace_annotate!(datareader, stringify!(#variant_name));
+                #creation
+            }
+        };
+
+        if let Some(tag) = &variant_attributes.tag {
+            variant_reads.push(quote! { #tag => #read, });
+        }
+        if variant_attributes.fallback {
+            if fallback.is_some() {
+                return quote_spanned! {
+                    variant_span =>
+                    compile_error!("DataTypeReader enums can only have one `fallback` variant");
+                }
+                .into();
+            }
+            fallback = Some(read);
+        } else if variant_attributes.tag.is_none() && !matches!(variant.fields, syn::Fields::Unit) {
+            return quote_spanned! {
+                variant_span =>
+                compile_error!("DataTypeReader variants with a payload need a `tag` or `fallback`");
+            }
+            .into();
+        }
+    }
+
+    let fallback = match fallback {
+        Some(read) => quote! { _ => #read, },
+        None => quote! {
+            _ => return Err(#datatypes_reader_path ::Error::UnknownTag(
+                    stringify!(#identifier).to_string(), tag as i64)),
+        },
+    };
+
+    let prefix = match &attributes.prefix {
+        Some(e) => e.value().to_uppercase(),
+        None => "".to_string(),
+    };
+    let enum_name = identifier.to_string().to_uppercase();
+    let datatype = match attributes.datatype {
+        StructDataType::Ident(ref ident) => {
+            quote! { #datatypes_reader_path :: DataType :: #ident }
+        }
+        _ => match generics.params.first() {
+            Some(_) => {
+                let datatype = format_ident!("{}{}GENERIC", prefix, enum_name);
+                quote! { #datatypes_reader_path :: DataType :: #datatype }
+            }
+            None => {
+                let datatype = format_ident!("{}{}", prefix, enum_name);
+                quote! { #datatypes_reader_path :: DataType :: #datatype (self.clone()) }
+            }
+        },
+    };
+
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let size_trait = match attributes.ommit_trait.size {
+        true => quote! {},
+        // the tag and the largest payload
+        false => quote! {
+            impl #impl_generics #datatypes_reader_path :: DataTypeSize for #identifier #type_generics #where_clause {
+                fn datatype_size() -> usize {
+                    let mut size: usize = 0;
+                    #(#variant_sizes)*
+                    size + <#tag_type as #datatypes_reader_path ::DataTypeSize>::datatype_size()
+                }
+            }
+        },
+    };
+
+    let read_trait = match attributes.ommit_trait.read {
+        true => quote! {},
+        false => quote! {
+            impl #impl_generics #datatypes_reader_path::DataTypeRead for #identifier #type_generics #where_clause {
+                fn read<ReaderSource: #datatypes_reader_path::ReadSeek>(datareader: &mut #datatypes_reader_path::DataTypeReader<ReaderSource>) -> #datatypes_reader_path::Result <Self> {
+                    #crate_prefix ::trace::trace_start!(datareader, stringify!(#identifier));
+                    #crate_prefix ::trace::trace_annotate!(datareader, "tag");
+                    let tag = <#tag_type as #datatypes_reader_path ::DataTypeRead>::read(datareader)?;
+                    let s = match tag {
+                        #(#variant_reads)*
+                        #fallback
+                    };
+                    #crate_prefix ::trace::trace_stop!(datareader, s.to_datatype());
+                    Ok(s)
+                }
+
+                fn to_datatype(&self) -> #datatypes_reader_path::DataType {
+                    #datatype
+                }
+            }
+        },
+    };
+
+    let gen = quote! {
+        #read_trait
+        #size_trait
+    };
+    gen.into()
+}
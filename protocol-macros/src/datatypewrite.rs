@@ -0,0 +1,192 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, punctuated::Punctuated, spanned::Spanned, DeriveInput, Token};
+
+use crate::datatyperead::{
+    Environment, FieldAttr, OffsetParsed, SizeParsed, SizeRecalc, StructAttr,
+};
+
+/// derive macro for DataTypeWrite, the counterpart of DataTypeRead
+/// it honors the same datatyperead attributes and writes everything little endian
+pub fn datatypewrite_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let data_struct = match input.data {
+        syn::Data::Struct(data_struct) => data_struct,
+        syn::Data::Enum(data_enum) => {
+            return quote_spanned! {
+                data_enum.enum_token.span =>
+                compile_error!("DataTypeWrite can only be used on structs not enums");
+            }
+            .into()
+        }
+        syn::Data::Union(data_union) => {
+            return quote_spanned! {
+                data_union.union_token.span =>
+                compile_error!("DataTypeWrite can only be used on structs not unions");
+            }
+            .into()
+        }
+    };
+
+    let mut struct_attrib = StructAttr::default();
+    for attr in &input.attrs {
+        if !attr.path.is_ident("datatyperead") {
+            continue;
+        }
+        if let Ok(sa) = attr.parse_args_with(Punctuated::<StructAttr, Token![,]>::parse_terminated)
+        {
+            struct_attrib = sa[0].clone();
+        }
+    }
+
+    let crate_prefix = match struct_attrib.internal {
+        true => quote!(crate),
+        false => quote!(quakeworld),
+    };
+    let reader_path = quote! {#crate_prefix ::datatypes :: reader};
+    let writer_path = quote! {#crate_prefix ::datatypes :: writer};
+
+    let mut field_writes = vec![];
+    for field in data_struct.fields.iter() {
+        let field_span = field.span();
+        let field_name =
+            match &field.ident {
+                Some(f) => f.clone(),
+                None => return quote_spanned! {
+                    field_span =>
+                    compile_error!("DataTypeWrite can only be used on structs with named fields");
+                }
+                .into(),
+            };
+        let field_type = field.ty.clone();
+
+        let mut attributes = FieldAttr::default();
+        for attr in &field.attrs {
+            if !attr.path.is_ident("datatyperead") {
+                continue;
+            }
+            let attr_span = attr.span();
+            match attr.parse_args_with(Punctuated::<FieldAttr, Token![,]>::parse_terminated) {
+                Ok(a) => attributes = a[0].clone(),
+                Err(e) => {
+                    let b = format!("{}", e);
+                    return quote_spanned! {
+                        attr_span =>
+                        compile_error!(#b);
+                    }
+                    .into();
+                }
+            }
+        }
+
+        let field_environment = match &attributes.environment {
+            Environment::None | Environment::Ident(_) => quote! {},
+            Environment::Auto => quote! {
+                <#field_type as #writer_path ::DataTypeWrite>::environment(
+                    &self. #field_name, datawriter, stringify!(#field_name));
+            },
+            Environment::String(lit_str) => quote! {
+                <#field_type as #writer_path ::DataTypeWrite>::environment(
+                    &self. #field_name, datawriter, #lit_str);
+            },
+        };
+
+        // fields with an offset are written there and the position is restored afterwards
+        let offset_parsed: OffsetParsed = (&attributes.set_offset).into();
+        let offset_name = format!("{}_offset", field_name);
+        let field_offset = match offset_parsed {
+            OffsetParsed::None => None,
+            OffsetParsed::Int(lit_int) => Some(quote! { #lit_int }),
+            OffsetParsed::Str(lit_str) => {
+                Some(quote! { u64::from(datawriter.get_env_error(#lit_str)?) })
+            }
+            OffsetParsed::Auto => {
+                Some(quote! { u64::from(datawriter.get_env_error(#offset_name)?) })
+            }
+        };
+        let (field_offset, field_offset_after) = match field_offset {
+            Some(offset) => (
+                quote! {
+                    let old_offset = datawriter.position();
+                    let current_field_offset: u64 = #offset;
+                    datawriter.set_position(current_field_offset);
+                },
+                quote! {
+                    datawriter.set_position(old_offset);
+                },
+            ),
+            None => (quote! {}, quote! {}),
+        };
+
+        let field_size_recalc = match attributes.size_recalc {
+            SizeRecalc::None => quote! {},
+            SizeRecalc::ModuloSelfEnvironment => quote! {
+                let modulo_type_size = < #field_type as #reader_path ::DataTypeSize>::datatype_size();
+                let modulo_remainder = size_from_environment % modulo_type_size;
+                if modulo_remainder != 0 {
+                    return Err(#reader_path ::Error::DirectoryEntrySize(
+                        size_from_environment,
+                        modulo_type_size,
+                        modulo_remainder));
+                }
+                let size_from_environment = size_from_environment / modulo_type_size;
+            },
+        };
+
+        let write_exact_type = match attributes.string {
+            true => quote! { write_exact_generic_string },
+            false => quote! { write_exact_generic },
+        };
+
+        let size_parsed: SizeParsed = (&attributes.set_size).into();
+        let size_name = format!("{}_size", field_name);
+        let size = match size_parsed {
+            SizeParsed::None => None,
+            SizeParsed::Int(lit_int) => Some(quote! { #lit_int }),
+            SizeParsed::Str(lit_str) => Some(quote! { datawriter.get_env_error(#lit_str)?.into() }),
+            SizeParsed::Auto => Some(quote! { datawriter.get_env_error(#size_name)?.into() }),
+        };
+        let write = match size {
+            None => quote! {
+                <#field_type as #writer_path ::DataTypeWrite>::write(&self. #field_name, datawriter)?;
+            },
+            Some(size) => quote! {
+                let size_from_environment: usize = #size;
+                #field_size_recalc
+                datawriter. #write_exact_type (&self. #field_name, size_from_environment)?;
+            },
+        };
+
+        field_writes.push(quote! {
+            #field_offset
+            #write
+            #field_offset_after
+            #field_environment
+        });
+    }
+
+    // every type parameter has to be writable too
+    let mut generics = input.generics.clone();
+    let type_parameters: Vec<_> = generics.type_params().map(|t| t.ident.clone()).collect();
+    {
+        let where_clause = generics.make_where_clause();
+        for t in type_parameters {
+            where_clause
+                .predicates
+                .push(syn::parse_quote! { #t: #writer_path ::DataTypeWrite });
+        }
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    let identifier = format_ident!("{}", input.ident);
+
+    let gen = quote! {
+        impl #impl_generics #writer_path ::DataTypeWrite for #identifier #type_generics #where_clause {
+            fn write(&self, datawriter: &mut #writer_path ::DataTypeWriter) -> #writer_path ::Result<()> {
+                #(#field_writes)*
+                Ok(())
+            }
+        }
+    };
+    gen.into()
+}
@@ -3,6 +3,7 @@ use proc_macro::TokenStream;
 mod datatypeboundcheck;
 mod datatyperead;
 mod datatypesize;
+mod datatypewrite;
 mod helpers;
 mod parsemessage;
 
@@ -16,6 +17,11 @@ pub fn datatype_read(input: TokenStream) -> TokenStream {
     datatyperead::datatyperead_derive_2(input)
 }
 
+#[proc_macro_derive(DataTypeWrite, attributes(datatyperead))]
+pub fn datatype_write(input: TokenStream) -> TokenStream {
+    datatypewrite::datatypewrite_derive(input)
+}
+
 #[proc_macro_derive(DataTypeBoundCheckDerive, attributes(check_bounds))]
 pub fn datatypbe_boundcheck(input: TokenStream) -> TokenStream {
     datatypeboundcheck::datatype_bound_check_derive(input)
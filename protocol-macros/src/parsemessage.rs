@@ -43,6 +43,13 @@ fn impl_parsemessage_macro(ast: &syn::DeriveInput) -> TokenStream {
         format_ident!("read_{}", q.to_string().to_lowercase())
     });
 
+    let field_name_write = fields.iter().map(|field| &field.ident);
+    let field_function_write = fields.iter().map(|field| {
+        let ft = &field.ty;
+        let q = quote! { #ft };
+        format_ident!("write_{}", q.to_string().to_lowercase())
+    });
+
     let struct_name = &ast.ident;
 
     let gen = quote! {
@@ -64,6 +71,14 @@ fn impl_parsemessage_macro(ast: &syn::DeriveInput) -> TokenStream {
                 trace::trace_stop!(message, v);
                 Ok(v)
             }
+
+            pub fn write(&self, message: &mut Message) -> Result<usize, MessageError>
+            {
+                Ok(0
+                #(
+                    + message.#field_function_write(self.#field_name_write.clone())
+                 )*)
+            }
         }
     };
     gen.into()
@@ -0,0 +1,385 @@
+use serde::Serialize;
+
+use super::{Bsp, Error, Result};
+use crate::datatypes::common::{Plane, Vector3};
+
+pub const CONTENTS_EMPTY: i32 = -1;
+pub const CONTENTS_SOLID: i32 = -2;
+pub const CONTENTS_WATER: i32 = -3;
+pub const CONTENTS_SLIME: i32 = -4;
+pub const CONTENTS_LAVA: i32 = -5;
+pub const CONTENTS_SKY: i32 = -6;
+pub const CONTENTS_CURRENT_0: i32 = -9;
+pub const CONTENTS_CURRENT_DOWN: i32 = -14;
+
+/// keeps the end of a trace this far away from the plane it hit
+const DIST_EPSILON: f32 = 0.03125;
+
+/// what is at a point of the map
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Contents {
+    Empty,
+    Solid,
+    Water,
+    Slime,
+    Lava,
+    Sky,
+    Other(i32),
+}
+
+impl From<i32> for Contents {
+    fn from(contents: i32) -> Self {
+        match contents {
+            CONTENTS_EMPTY => Contents::Empty,
+            CONTENTS_SOLID => Contents::Solid,
+            // currents are water that pushes
+            CONTENTS_WATER | CONTENTS_CURRENT_DOWN..=CONTENTS_CURRENT_0 => Contents::Water,
+            CONTENTS_SLIME => Contents::Slime,
+            CONTENTS_LAVA => Contents::Lava,
+            CONTENTS_SKY => Contents::Sky,
+            c => Contents::Other(c),
+        }
+    }
+}
+
+/// result of [`Bsp::trace`]
+#[derive(Serialize, Clone, Debug)]
+pub struct HullTrace {
+    /// the whole trace was in solid
+    pub all_solid: bool,
+    /// the start was in solid
+    pub start_solid: bool,
+    pub in_open: bool,
+    pub in_water: bool,
+    /// how far the trace got, 1.0 if nothing was hit
+    pub fraction: f32,
+    pub end_position: Vector3<f32>,
+    /// the plane that was hit facing the start
+    pub plane: Option<Plane>,
+}
+
+type Point = [f32; 3];
+
+fn lerp(a: &Point, b: &Point, fraction: f32) -> Point {
+    [
+        a[0] + fraction * (b[0] - a[0]),
+        a[1] + fraction * (b[1] - a[1]),
+        a[2] + fraction * (b[2] - a[2]),
+    ]
+}
+
+fn plane_distance(plane: &Plane, p: &Point) -> f32 {
+    match plane.r#type {
+        t @ 0..=2 => p[t as usize] - plane.distance,
+        _ => plane.normal.x * p[0] + plane.normal.y * p[1] + plane.normal.z * p[2] - plane.distance,
+    }
+}
+
+/// hull 0 is made from the nodes, hull 1 and 2 are the clipnodes for player and large sized boxes
+struct Hull<'a> {
+    bsp: &'a Bsp,
+    hull: usize,
+    first: i32,
+}
+
+impl Hull<'_> {
+    /// plane and children of a node, negative children are contents
+    fn node(&self, num: i32) -> Option<(&Plane, [i32; 2])> {
+        let (plane_index, children) = match self.hull {
+            0 => {
+                let node = self.bsp.nodes.get(num as usize)?;
+                let child = |c: i32| match c {
+                    c if c >= 0 => c,
+                    c => self
+                        .bsp
+                        .leaves
+                        .get((-1 - c) as usize)
+                        .map_or(CONTENTS_SOLID, |l| l.r#type),
+                };
+                (node.plane_index, [child(node.front), child(node.back)])
+            }
+            _ => {
+                let node = self.bsp.clip_nodes.get(num as usize)?;
+                (node.plane_index, [node.front, node.back])
+            }
+        };
+        Some((self.bsp.planes.get(plane_index as usize)?, children))
+    }
+
+    fn point_contents(&self, mut num: i32, p: &Point) -> i32 {
+        while num >= 0 {
+            let (plane, children) = match self.node(num) {
+                Some(n) => n,
+                None => return CONTENTS_SOLID,
+            };
+            num = match plane_distance(plane, p) < 0.0 {
+                true => children[1],
+                false => children[0],
+            };
+        }
+        num
+    }
+
+    /// SV_RecursiveHullCheck, returns false once the trace is blocked
+    fn recursive_check(
+        &self,
+        num: i32,
+        p1f: f32,
+        p2f: f32,
+        p1: Point,
+        p2: Point,
+        trace: &mut HullTrace,
+    ) -> bool {
+        if num < 0 {
+            if num != CONTENTS_SOLID {
+                trace.all_solid = false;
+                if num == CONTENTS_EMPTY {
+                    trace.in_open = true;
+                } else {
+                    trace.in_water = true;
+                }
+            } else {
+                trace.start_solid = true;
+            }
+            return true;
+        }
+
+        let (plane, children) = match self.node(num) {
+            Some(n) => n,
+            None => return true,
+        };
+        let t1 = plane_distance(plane, &p1);
+        let t2 = plane_distance(plane, &p2);
+        if t1 >= 0.0 && t2 >= 0.0 {
+            return self.recursive_check(children[0], p1f, p2f, p1, p2, trace);
+        }
+        if t1 < 0.0 && t2 < 0.0 {
+            return self.recursive_check(children[1], p1f, p2f, p1, p2, trace);
+        }
+
+        // put the crosspoint DIST_EPSILON pixels on the near side
+        let mut fraction = match t1 < 0.0 {
+            true => (t1 + DIST_EPSILON) / (t1 - t2),
+            false => (t1 - DIST_EPSILON) / (t1 - t2),
+        }
+        .clamp(0.0, 1.0);
+        let mut midf = p1f + (p2f - p1f) * fraction;
+        let mut mid = lerp(&p1, &p2, fraction);
+        let side = (t1 < 0.0) as usize;
+
+        // move up to the node
+        if !self.recursive_check(children[side], p1f, midf, p1, mid, trace) {
+            return false;
+        }
+
+        // go past the node
+        if self.point_contents(children[side ^ 1], &mid) != CONTENTS_SOLID {
+            return self.recursive_check(children[side ^ 1], midf, p2f, mid, p2, trace);
+        }
+
+        // never got out of the solid area
+        if trace.all_solid {
+            return false;
+        }
+
+        // the other side of the node is solid, this is the impact point
+        trace.plane = Some(match side {
+            0 => *plane,
+            _ => Plane {
+                normal: plane.normal.scale(-1.0),
+                distance: -plane.distance,
+                r#type: plane.r#type,
+            },
+        });
+
+        while self.point_contents(self.first, &mid) == CONTENTS_SOLID {
+            // shouldn't really happen, but does occasionally
+            fraction -= 0.1;
+            if fraction < 0.0 {
+                trace.fraction = midf;
+                trace.end_position = Vector3::new(mid[0], mid[1], mid[2]);
+                return false;
+            }
+            midf = p1f + (p2f - p1f) * fraction;
+            mid = lerp(&p1, &p2, fraction);
+        }
+
+        trace.fraction = midf;
+        trace.end_position = Vector3::new(mid[0], mid[1], mid[2]);
+        false
+    }
+}
+
+impl Bsp {
+    fn hull(&self, hull: usize) -> Result<Hull<'_>> {
+        let model = self.models.first();
+        let first = match hull {
+            0 => model.map_or(0, |m| m.node_id0),
+            1 => model.map_or(0, |m| m.node_id1),
+            2 => model.map_or(0, |m| m.node_id2),
+            _ => return Err(Error::InvalidHull(hull)),
+        };
+        Ok(Hull {
+            bsp: self,
+            hull,
+            first,
+        })
+    }
+
+    /// contents of the world at origin
+    pub fn point_contents(&self, origin: &Vector3<f32>) -> Contents {
+        self.hull_point_contents(origin, 0)
+            .unwrap_or(Contents::Solid)
+    }
+
+    /// contents of the world for a box of the hull size at origin
+    pub fn hull_point_contents(&self, origin: &Vector3<f32>, hull: usize) -> Result<Contents> {
+        let hull = self.hull(hull)?;
+        Ok(hull.point_contents(hull.first, &origin.as_array()).into())
+    }
+
+    /// traces the world from start to end like SV_RecursiveHullCheck,
+    /// hull 0 is a point, 1 a player sized and 2 a shambler sized box centered at the origin
+    pub fn trace(
+        &self,
+        start: &Vector3<f32>,
+        end: &Vector3<f32>,
+        hull: usize,
+    ) -> Result<HullTrace> {
+        let hull = self.hull(hull)?;
+        let mut trace = HullTrace {
+            all_solid: true,
+            start_solid: false,
+            in_open: false,
+            in_water: false,
+            fraction: 1.0,
+            end_position: *end,
+            plane: None,
+        };
+        hull.recursive_check(
+            hull.first,
+            0.0,
+            1.0,
+            start.as_array(),
+            end.as_array(),
+            &mut trace,
+        );
+        if trace.fraction == 1.0 {
+            trace.end_position = *end;
+        }
+        Ok(trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::bsp::Model;
+    use crate::datatypes::common::{ClipNode, Leaf, Node};
+
+    // a wall at x >= 64 and water below z = 0, the clip hull moves the wall to x >= 48
+    fn bsp() -> Bsp {
+        let plane = |normal: Vector3<f32>, distance: f32, r#type: i32| Plane {
+            normal,
+            distance,
+            r#type,
+        };
+        Bsp {
+            models: vec![Model {
+                node_id0: 0,
+                node_id1: 0,
+                node_id2: 0,
+                ..Default::default()
+            }],
+            planes: vec![
+                plane(Vector3::new(1.0, 0.0, 0.0), 64.0, 0),
+                plane(Vector3::new(0.0, 0.0, 1.0), 0.0, 2),
+                plane(Vector3::new(1.0, 0.0, 0.0), 48.0, 0),
+            ],
+            nodes: vec![
+                Node {
+                    plane_index: 0,
+                    front: -1,
+                    back: 1,
+                    ..Default::default()
+                },
+                Node {
+                    plane_index: 1,
+                    front: -2,
+                    back: -3,
+                    ..Default::default()
+                },
+            ],
+            leaves: [CONTENTS_SOLID, CONTENTS_EMPTY, CONTENTS_WATER]
+                .into_iter()
+                .map(|r#type| Leaf {
+                    r#type,
+                    ..Default::default()
+                })
+                .collect(),
+            clip_nodes: vec![ClipNode {
+                plane_index: 2,
+                front: CONTENTS_SOLID,
+                back: CONTENTS_EMPTY,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn point_contents() {
+        let bsp = bsp();
+        assert_eq!(
+            bsp.point_contents(&Vector3::new(0.0, 0.0, 10.0)),
+            Contents::Empty
+        );
+        assert_eq!(
+            bsp.point_contents(&Vector3::new(0.0, 0.0, -10.0)),
+            Contents::Water
+        );
+        assert_eq!(
+            bsp.point_contents(&Vector3::new(100.0, 0.0, 0.0)),
+            Contents::Solid
+        );
+        assert_eq!(
+            bsp.hull_point_contents(&Vector3::new(50.0, 0.0, 0.0), 1)
+                .unwrap(),
+            Contents::Solid
+        );
+        assert!(matches!(
+            bsp.hull_point_contents(&Vector3::new(0.0, 0.0, 0.0), 3),
+            Err(Error::InvalidHull(3))
+        ));
+        assert_eq!(Contents::from(-10), Contents::Water);
+    }
+
+    #[test]
+    fn trace_hulls() {
+        let bsp = bsp();
+        let start = Vector3::new(0.0, 0.0, 10.0);
+        let end = Vector3::new(128.0, 0.0, 10.0);
+
+        let trace = bsp.trace(&start, &end, 0).unwrap();
+        assert!(!trace.all_solid && !trace.start_solid && trace.in_open);
+        assert!((trace.fraction - (64.0 - DIST_EPSILON) / 128.0).abs() < 1e-6);
+        assert!((trace.end_position.x - (64.0 - DIST_EPSILON)).abs() < 1e-4);
+        let plane = trace.plane.unwrap();
+        assert_eq!(plane.normal.as_array(), [-1.0, 0.0, 0.0]);
+        assert_eq!(plane.distance, -64.0);
+
+        let trace = bsp.trace(&start, &end, 1).unwrap();
+        assert!((trace.end_position.x - (48.0 - DIST_EPSILON)).abs() < 1e-4);
+
+        // into the water
+        let trace = bsp
+            .trace(&start, &Vector3::new(0.0, 0.0, -10.0), 0)
+            .unwrap();
+        assert_eq!(trace.fraction, 1.0);
+        assert!(trace.in_open && trace.in_water);
+        assert!(trace.plane.is_none());
+
+        // starting in the wall
+        let trace = bsp.trace(&Vector3::new(100.0, 0.0, 10.0), &end, 0).unwrap();
+        assert!(trace.all_solid && trace.start_solid);
+    }
+}
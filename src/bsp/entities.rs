@@ -0,0 +1,356 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::error::{Error, Result};
+use crate::datatypes::common::Vector3;
+
+/// a single entity of the entities lump, keys keep the order of the map and keys and values
+/// keep their bytes, maps use the high bit for colored text
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Entity {
+    pub fields: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Entity {
+    /// the raw value of key, a key set twice returns the last value like the engine does
+    pub fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key.as_bytes())
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// the value of key, None if it is not set or not valid utf8
+    pub fn get(&self, key: &str) -> Option<&str> {
+        std::str::from_utf8(self.get_bytes(key)?).ok()
+    }
+
+    /// replaces the value of key or adds it
+    pub fn set(&mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        let key = key.into();
+        let value = value.into();
+        match self.fields.iter_mut().rev().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.fields.push((key, value)),
+        }
+    }
+
+    /// removes all values of key
+    pub fn remove(&mut self, key: &str) {
+        self.fields.retain(|(k, _)| k != key.as_bytes());
+    }
+
+    pub fn classname(&self) -> Option<&str> {
+        self.get("classname")
+    }
+
+    pub fn targetname(&self) -> Option<&str> {
+        self.get("targetname")
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.get("target")
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.get("model")
+    }
+
+    /// 0 when not set
+    pub fn spawnflags(&self) -> u32 {
+        self.get("spawnflags")
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .map_or(0, |v| v as u32)
+    }
+
+    pub fn origin(&self) -> Option<Vector3<f32>> {
+        parse_vector(self.get("origin")?)
+    }
+
+    pub fn angle(&self) -> Option<f32> {
+        self.get("angle")?.trim().parse().ok()
+    }
+
+    /// pitch, yaw and roll from `angles` or `mangle`, otherwise the yaw from `angle`
+    /// where -1 is up and -2 is down
+    pub fn angles(&self) -> Option<Vector3<f32>> {
+        if let Some(angles) = self.get("angles").or_else(|| self.get("mangle")) {
+            return parse_vector(angles);
+        }
+        match self.angle()? {
+            -1.0 => Some(Vector3::new(-90.0, 0.0, 0.0)),
+            -2.0 => Some(Vector3::new(90.0, 0.0, 0.0)),
+            a => Some(Vector3::new(0.0, a, 0.0)),
+        }
+    }
+
+    /// the index into the bsp models, `*N` for brush entities and 0 for the worldspawn
+    pub fn model_index(&self) -> Option<usize> {
+        if self.classname() == Some("worldspawn") {
+            return Some(0);
+        }
+        self.model()?.strip_prefix('*')?.parse().ok()
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(b"{\n");
+        for (key, value) in &self.fields {
+            out.push(b'"');
+            out.extend_from_slice(key);
+            out.extend_from_slice(b"\" \"");
+            out.extend_from_slice(value);
+            out.extend_from_slice(b"\"\n");
+        }
+        out.extend_from_slice(b"}\n");
+    }
+}
+
+fn parse_vector(value: &str) -> Option<Vector3<f32>> {
+    let mut values = value.split_whitespace().map(|v| v.parse::<f32>());
+    let vector = Vector3::new(
+        values.next()?.ok()?,
+        values.next()?.ok()?,
+        values.next()?.ok()?,
+    );
+    match values.next() {
+        Some(_) => None,
+        None => Some(vector),
+    }
+}
+
+/// all entities of the map, the first one is the worldspawn
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Entities {
+    pub entities: Vec<Entity>,
+}
+
+struct Tokenizer<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// the next token and if it was quoted, works like COM_Parse
+    fn token(&mut self) -> Result<Option<(&'a [u8], bool)>> {
+        loop {
+            while self.position < self.data.len() && self.data[self.position] <= b' ' {
+                self.position += 1;
+            }
+            if self.data[self.position..].starts_with(b"//") {
+                while self.position < self.data.len() && self.data[self.position] != b'\n' {
+                    self.position += 1;
+                }
+                continue;
+            }
+            break;
+        }
+        let start = self.position;
+        let token = match self.data.get(self.position) {
+            None => return Ok(None),
+            Some(b'"') => {
+                let end = match self.data[start + 1..].iter().position(|c| *c == b'"') {
+                    Some(end) => start + 1 + end,
+                    None => {
+                        return Err(Error::EntityParse(start, "unterminated string".into()));
+                    }
+                };
+                self.position = end + 1;
+                return Ok(Some((&self.data[start + 1..end], true)));
+            }
+            Some(b'{') | Some(b'}') => {
+                self.position += 1;
+                &self.data[start..self.position]
+            }
+            Some(_) => {
+                while self.position < self.data.len()
+                    && self.data[self.position] > b' '
+                    && !matches!(self.data[self.position], b'{' | b'}' | b'"')
+                {
+                    self.position += 1;
+                }
+                &self.data[start..self.position]
+            }
+        };
+        Ok(Some((token, false)))
+    }
+}
+
+impl Entities {
+    /// parses the text of the entities lump, a trailing \0 is ignored
+    pub fn parse(data: &[u8]) -> Result<Entities> {
+        let end = data.iter().position(|c| *c == 0).unwrap_or(data.len());
+        let mut tokenizer = Tokenizer {
+            data: &data[..end],
+            position: 0,
+        };
+        let mut entities = vec![];
+        loop {
+            let position = tokenizer.position;
+            match tokenizer.token()? {
+                None => break,
+                Some((b"{", false)) => {}
+                Some((token, _)) => {
+                    return Err(Error::EntityParse(
+                        position,
+                        format!("expected {{, got ({})", String::from_utf8_lossy(token)),
+                    ))
+                }
+            }
+            let mut entity = Entity::default();
+            loop {
+                let position = tokenizer.position;
+                let key = match tokenizer.token()? {
+                    Some((b"}", false)) => break,
+                    Some((token, _)) => token.to_vec(),
+                    None => {
+                        return Err(Error::EntityParse(
+                            position,
+                            "EOF without closing brace".into(),
+                        ))
+                    }
+                };
+                let position = tokenizer.position;
+                let value = match tokenizer.token()? {
+                    Some((b"}", false)) => {
+                        return Err(Error::EntityParse(
+                            position,
+                            "closing brace without data".into(),
+                        ))
+                    }
+                    Some((token, _)) => token.to_vec(),
+                    None => return Err(Error::EntityParse(position, "EOF without data".into())),
+                };
+                entity.fields.push((key, value));
+            }
+            entities.push(entity);
+        }
+        Ok(Entities { entities })
+    }
+
+    /// the text of the entities lump the way qbsp writes it, without the trailing \0
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        for entity in &self.entities {
+            entity.write(&mut out);
+        }
+        out
+    }
+
+    pub fn worldspawn(&self) -> Option<&Entity> {
+        self.entities
+            .first()
+            .filter(|e| e.classname() == Some("worldspawn"))
+    }
+
+    pub fn by_classname<'a>(&'a self, classname: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.classname() == Some(classname))
+    }
+
+    pub fn by_targetname<'a>(&'a self, targetname: &'a str) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.targetname() == Some(targetname))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// the text of [`Entities::to_bytes`], bytes that are not utf8 are replaced
+impl fmt::Display for Entities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTITIES: &[u8] = b"{\n\"classname\" \"worldspawn\"\n\"wad\" \"gfx.wad\"\n}\n\
+        // a comment\n\
+        { \"classname\" \"info_player_deathmatch\" \"origin\" \"-352 64 24\" \"angle\" \"90\" }\n\
+        {\n\"classname\" \"func_door\"\n\"model\" \"*3\"\n\"spawnflags\" \"32\"\n\
+        \"targetname\" \"t1\"\n\"angle\" \"-1\"\n}\n\0";
+
+    #[test]
+    fn parse_entities() {
+        let entities = Entities::parse(ENTITIES).unwrap();
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities.worldspawn().unwrap().get("wad"), Some("gfx.wad"));
+        assert_eq!(entities.worldspawn().unwrap().model_index(), Some(0));
+
+        let spawn = entities
+            .by_classname("info_player_deathmatch")
+            .next()
+            .unwrap();
+        assert_eq!(spawn.origin().unwrap().as_array(), [-352.0, 64.0, 24.0]);
+        assert_eq!(spawn.angles().unwrap().as_array(), [0.0, 90.0, 0.0]);
+        assert_eq!(spawn.spawnflags(), 0);
+        assert_eq!(spawn.model_index(), None);
+
+        let door = entities.by_targetname("t1").next().unwrap();
+        assert_eq!(door.model_index(), Some(3));
+        assert_eq!(door.spawnflags(), 32);
+        assert_eq!(door.angles().unwrap().as_array(), [-90.0, 0.0, 0.0]);
+        assert!(door.origin().is_none());
+    }
+
+    #[test]
+    fn serialize_entities() {
+        let mut entities = Entities::parse(ENTITIES).unwrap();
+        entities.entities[1].set("angle", "180");
+        entities.entities[2].remove("targetname");
+        let text = entities.to_string();
+        assert!(text.starts_with("{\n\"classname\" \"worldspawn\"\n\"wad\" \"gfx.wad\"\n}\n"));
+        assert_eq!(Entities::parse(text.as_bytes()).unwrap(), entities);
+        assert_eq!(entities.entities[1].angle(), Some(180.0));
+        assert_eq!(entities.by_targetname("t1").count(), 0);
+    }
+
+    #[test]
+    fn high_bit_text() {
+        let data = b"{ \"classname\" \"worldspawn\" \"message\" \"\xe4\xf2\xe9\xed\" }\n";
+        let entities = Entities::parse(data).unwrap();
+        let worldspawn = entities.worldspawn().unwrap();
+        assert_eq!(
+            worldspawn.get_bytes("message"),
+            Some(&b"\xe4\xf2\xe9\xed"[..])
+        );
+        assert_eq!(worldspawn.get("message"), None);
+        assert_eq!(Entities::parse(&entities.to_bytes()).unwrap(), entities);
+        assert_eq!(
+            entities.to_bytes(),
+            b"{\n\"classname\" \"worldspawn\"\n\"message\" \"\xe4\xf2\xe9\xed\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Entities::parse(b"").unwrap().is_empty());
+        for data in [
+            &b"\"classname\" \"worldspawn\""[..],
+            b"{ \"classname\" \"worldspawn\"",
+            b"{ \"classname\" }",
+            b"{ \"classname\" \"worldspawn }",
+        ] {
+            assert!(matches!(
+                Entities::parse(data),
+                Err(Error::EntityParse(_, _))
+            ));
+        }
+    }
+}
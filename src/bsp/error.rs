@@ -1,4 +1,7 @@
 use crate::datatypes::reader::Error as ReaderError;
+use crate::texture::Error as TextureError;
+#[cfg(feature = "vfs")]
+use crate::vfs::Error as VfsError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,6 +10,31 @@ pub enum Error {
     Io(std::io::Error),
     #[error("datatypereader error: {0}")]
     Reader(ReaderError),
+    #[error("entity parse error at ({0}): {1}")]
+    EntityParse(usize, String),
+    #[error("bsp version ({0:#x}) is not supported")]
+    UnsupportedVersion(u32),
+    #[error("hull ({0}) does not exist, only 0 to 2")]
+    InvalidHull(usize),
+    #[error("face ({0}) does not exist")]
+    FaceIndex(usize),
+    #[error("face ({0}) references missing edges, vertices or texture info")]
+    FaceGeometry(usize),
+    #[error("lightmap of face ({0}) is outside of the lightmap data")]
+    LightmapBounds(usize),
+    #[error("face ({0}) has no light style in slot ({1})")]
+    LightmapStyle(usize, usize),
+    #[error("texture error: {0}")]
+    Texture(TextureError),
+    #[error("lit header is invalid")]
+    LitHeader,
+    #[error("lit version ({0}) is not supported")]
+    LitVersion(i32),
+    #[error("lit size ({0}) does not match the lightmaps ({1})")]
+    LitSize(usize, usize),
+    #[cfg(feature = "vfs")]
+    #[error("vfs error: {0}")]
+    Vfs(VfsError),
 }
 
 impl From<ReaderError> for Error {
@@ -15,4 +43,17 @@ impl From<ReaderError> for Error {
     }
 }
 
+impl From<TextureError> for Error {
+    fn from(err: TextureError) -> Error {
+        Error::Texture(err)
+    }
+}
+
+#[cfg(feature = "vfs")]
+impl From<VfsError> for Error {
+    fn from(err: VfsError) -> Error {
+        Error::Vfs(err)
+    }
+}
+
 pub type Result<T> = core::result::Result<T, Error>;
@@ -0,0 +1,382 @@
+use serde::Serialize;
+
+use super::{Bsp, Error, Result, TextureMip, TextureParsed};
+use crate::datatypes::common::Face;
+use crate::texture;
+
+/// a style slot of a face that is not used
+pub const STYLE_NONE: u8 = 255;
+/// light styles a face can have
+pub const MAX_STYLES: usize = 4;
+
+/// the lightmap area of a face in texture space, a lightmap sample covers 16 texels
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaceExtents {
+    pub texture_mins: [i32; 2],
+    pub extents: [i32; 2],
+}
+
+impl FaceExtents {
+    /// lightmap samples along s
+    pub fn width(&self) -> u32 {
+        (self.extents[0] >> 4) as u32 + 1
+    }
+
+    /// lightmap samples along t
+    pub fn height(&self) -> u32 {
+        (self.extents[1] >> 4) as u32 + 1
+    }
+}
+
+/// the light of a face for a single light style
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LightmapStyle {
+    pub style: u8,
+    pub data: Vec<u8>,
+}
+
+/// all lightmaps of a face, each style has width * height samples of channels bytes,
+/// 1 for greyscale and 3 for rgb
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Lightmap {
+    pub face: usize,
+    pub width: u32,
+    pub height: u32,
+    pub channels: u32,
+    pub styles: Vec<LightmapStyle>,
+}
+
+impl Lightmap {
+    /// the lightmap of the style in slot as a greyscale or rgb png
+    pub fn to_png(&self, slot: usize) -> Result<Vec<u8>> {
+        let style = self
+            .styles
+            .get(slot)
+            .ok_or(Error::LightmapStyle(self.face, slot))?;
+        Ok(match self.channels {
+            3 => texture::png::from_rgb_data(&style.data, self.width, self.height)?,
+            _ => texture::png::from_greyscale_data(&style.data, self.width, self.height)?,
+        })
+    }
+}
+
+/// colored lightmaps of a `.lit` file (QLIT version 1), three bytes for every
+/// sample of the bsp lightmaps
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Lit {
+    pub data: Vec<u8>,
+}
+
+impl Lit {
+    pub fn parse(data: &[u8]) -> Result<Lit> {
+        if data.len() < 8 || &data[..4] != b"QLIT" {
+            return Err(Error::LitHeader);
+        }
+        let version = i32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        if version != 1 {
+            return Err(Error::LitVersion(version));
+        }
+        Ok(Lit {
+            data: data[8..].to_vec(),
+        })
+    }
+}
+
+/// the style slots of a face, unused slots are [`STYLE_NONE`]
+pub fn face_styles(face: &Face) -> [u8; MAX_STYLES] {
+    [
+        face.light_type,
+        face.light_base,
+        *face.light_additional.first().unwrap_or(&STYLE_NONE),
+        *face.light_additional.get(1).unwrap_or(&STYLE_NONE),
+    ]
+}
+
+impl Bsp {
+    /// sets the colored lightmaps, they have to match the size of the lightmaps
+    pub fn set_lit(&mut self, lit: Lit) -> Result<()> {
+        if lit.data.len() != self.light_maps.len() * 3 {
+            return Err(Error::LitSize(lit.data.len(), self.light_maps.len() * 3));
+        }
+        self.lit = Some(lit);
+        Ok(())
+    }
+
+    /// loads the `.lit` next to the bsp at path, e.g. `maps/dm2.lit` for `maps/dm2.bsp`.
+    /// returns false when there is none
+    #[cfg(feature = "vfs")]
+    pub fn load_lit(&mut self, vfs: &crate::vfs::Vfs, path: &str) -> Result<bool> {
+        let path = std::path::Path::new(path).with_extension("lit");
+        let path = path.to_string_lossy();
+        if !vfs.exists(path.as_ref()) {
+            return Ok(false);
+        }
+        let data = vfs.read(path.as_ref(), None)?;
+        self.set_lit(Lit::parse(&data)?)?;
+        Ok(true)
+    }
+
+    /// CalcSurfaceExtents, the vertices are projected in double precision
+    /// like most current engines do
+    pub fn face_extents(&self, index: usize) -> Result<FaceExtents> {
+        let face = self.faces.get(index).ok_or(Error::FaceIndex(index))?;
+        let info = self
+            .texture_infos
+            .get(face.texture_index as usize)
+            .ok_or(Error::FaceGeometry(index))?;
+        if face.edge_index < 0 || face.edge_count == 0 {
+            return Err(Error::FaceGeometry(index));
+        }
+
+        let axes = [
+            (&info.vec_s, info.distance_s),
+            (&info.vec_t, info.distance_t),
+        ];
+        let mut mins = [f64::MAX; 2];
+        let mut maxs = [f64::MIN; 2];
+        for i in 0..face.edge_count as usize {
+            let edge = *self
+                .edges_list
+                .get(face.edge_index as usize + i)
+                .ok_or(Error::FaceGeometry(index))?;
+            // negative edges are walked backwards
+            let vertex = match edge >= 0 {
+                true => self.edges.get(edge as usize).map(|e| e.vertex_0),
+                false => self
+                    .edges
+                    .get(edge.unsigned_abs() as usize)
+                    .map(|e| e.vertex_1),
+            }
+            .and_then(|v| self.vertices.get(v as usize))
+            .ok_or(Error::FaceGeometry(index))?;
+            for (j, (vec, distance)) in axes.iter().enumerate() {
+                let value = vertex.x as f64 * vec.x as f64
+                    + vertex.y as f64 * vec.y as f64
+                    + vertex.z as f64 * vec.z as f64
+                    + *distance as f64;
+                mins[j] = mins[j].min(value);
+                maxs[j] = maxs[j].max(value);
+            }
+        }
+
+        let mut extents = FaceExtents::default();
+        for j in 0..2 {
+            let bmin = (mins[j] / 16.0).floor() as i32;
+            let bmax = (maxs[j] / 16.0).ceil() as i32;
+            extents.texture_mins[j] = bmin * 16;
+            extents.extents[j] = (bmax - bmin) * 16;
+        }
+        Ok(extents)
+    }
+
+    /// the lightmaps of a face, none for faces without light like sky and liquids
+    pub fn face_lightmap(&self, index: usize) -> Result<Option<Lightmap>> {
+        self.face_lightmap_from(index, &self.light_maps, 1)
+    }
+
+    /// the rgb lightmaps of a face from the loaded [`Lit`],
+    /// greyscale like [`Bsp::face_lightmap`] when there is none
+    pub fn face_lightmap_colored(&self, index: usize) -> Result<Option<Lightmap>> {
+        match &self.lit {
+            Some(lit) => self.face_lightmap_from(index, &lit.data, 3),
+            None => self.face_lightmap(index),
+        }
+    }
+
+    fn face_lightmap_from(
+        &self,
+        index: usize,
+        data: &[u8],
+        channels: u32,
+    ) -> Result<Option<Lightmap>> {
+        let face = self.faces.get(index).ok_or(Error::FaceIndex(index))?;
+        let styles: Vec<u8> = face_styles(face)
+            .into_iter()
+            .take_while(|s| *s != STYLE_NONE)
+            .collect();
+        if face.lightmap_index < 0 || styles.is_empty() {
+            return Ok(None);
+        }
+
+        let extents = self.face_extents(index)?;
+        let (width, height) = (extents.width(), extents.height());
+        let size = (width * height * channels) as usize;
+        let offset = face.lightmap_index as usize * channels as usize;
+        let styles = styles
+            .into_iter()
+            .enumerate()
+            .map(|(slot, style)| {
+                let start = offset + slot * size;
+                let data = data
+                    .get(start..start + size)
+                    .ok_or(Error::LightmapBounds(index))?;
+                Ok(LightmapStyle {
+                    style,
+                    data: data.to_vec(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Lightmap {
+            face: index,
+            width,
+            height,
+            channels,
+            styles,
+        }))
+    }
+
+    /// the lightmaps of all faces that have light
+    pub fn lightmaps(&self) -> Result<Vec<Lightmap>> {
+        (0..self.faces.len())
+            .filter_map(|face| self.face_lightmap(face).transpose())
+            .collect()
+    }
+
+    /// the lightmaps of style slot as textures named `lightmap_<face>`,
+    /// to be packed with [`crate::texture::atlas::Atlas`]
+    pub fn lightmap_textures(&self, slot: usize) -> Result<Vec<TextureParsed>> {
+        Ok(self
+            .lightmaps()?
+            .into_iter()
+            .filter_map(|lightmap| {
+                let style = lightmap.styles.into_iter().nth(slot)?;
+                Some(TextureParsed {
+                    name: format!("lightmap_{}", lightmap.face),
+                    mip_levels: vec![TextureMip {
+                        width: lightmap.width,
+                        height: lightmap.height,
+                        data: style.data,
+                    }],
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::common::{Edge, TextureFaceInfo, Vector3};
+    use crate::texture::atlas::{Atlas, Statistics};
+
+    // a 32x16 quad with two styles, a face without light and one pointing past the data
+    fn bsp() -> Bsp {
+        let edge = |vertex_0, vertex_1| Edge { vertex_0, vertex_1 };
+        let face = |light_base, lightmap_index| Face {
+            edge_index: 0,
+            edge_count: 4,
+            light_type: 0,
+            light_base,
+            light_additional: vec![STYLE_NONE, STYLE_NONE],
+            lightmap_index,
+            ..Default::default()
+        };
+        Bsp {
+            vertices: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(32.0, 0.0, 0.0),
+                Vector3::new(32.0, 16.0, 0.0),
+                Vector3::new(0.0, 16.0, 0.0),
+            ],
+            edges: vec![edge(0, 0), edge(0, 1), edge(1, 2), edge(3, 2), edge(3, 0)],
+            edges_list: vec![1, 2, -3, 4],
+            texture_infos: vec![TextureFaceInfo {
+                vec_s: Vector3::new(1.0, 0.0, 0.0),
+                vec_t: Vector3::new(0.0, 1.0, 0.0),
+                ..Default::default()
+            }],
+            faces: vec![face(2, 1), face(STYLE_NONE, -1), face(STYLE_NONE, 10)],
+            light_maps: (0..13).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn face_lightmaps() {
+        let bsp = bsp();
+        let extents = bsp.face_extents(0).unwrap();
+        assert_eq!(extents.texture_mins, [0, 0]);
+        assert_eq!(extents.extents, [32, 16]);
+        assert_eq!((extents.width(), extents.height()), (3, 2));
+
+        let lightmap = bsp.face_lightmap(0).unwrap().unwrap();
+        assert_eq!(lightmap.styles.len(), 2);
+        assert_eq!(lightmap.styles[0].style, 0);
+        assert_eq!(lightmap.styles[0].data, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(lightmap.styles[1].style, 2);
+        assert_eq!(lightmap.styles[1].data, vec![7, 8, 9, 10, 11, 12]);
+        assert!(lightmap.to_png(1).unwrap().starts_with(b"\x89PNG"));
+        assert!(matches!(
+            lightmap.to_png(2),
+            Err(Error::LightmapStyle(0, 2))
+        ));
+
+        assert!(bsp.face_lightmap(1).unwrap().is_none());
+        assert!(matches!(
+            bsp.face_lightmap(2),
+            Err(Error::LightmapBounds(2))
+        ));
+        assert!(matches!(bsp.face_lightmap(3), Err(Error::FaceIndex(3))));
+    }
+
+    #[test]
+    fn colored_lightmaps() {
+        let mut bsp = bsp();
+        // without a lit the lightmaps stay greyscale
+        let lightmap = bsp.face_lightmap_colored(0).unwrap().unwrap();
+        assert_eq!(lightmap.channels, 1);
+        assert_eq!(lightmap.styles[0].data, vec![1, 2, 3, 4, 5, 6]);
+
+        let mut data = b"QLIT\x01\x00\x00\x00".to_vec();
+        data.extend((0..39).map(|v| v * 2));
+        bsp.set_lit(Lit::parse(&data).unwrap()).unwrap();
+        let lightmap = bsp.face_lightmap_colored(0).unwrap().unwrap();
+        assert_eq!(lightmap.channels, 3);
+        assert_eq!(
+            lightmap.styles[1].data,
+            (21..39).map(|v| v * 2).collect::<Vec<u8>>()
+        );
+        assert!(lightmap.to_png(0).unwrap().starts_with(b"\x89PNG"));
+        assert!(matches!(
+            bsp.face_lightmap_colored(2),
+            Err(Error::LightmapBounds(2))
+        ));
+
+        assert!(matches!(
+            bsp.set_lit(Lit::parse(&data[..20]).unwrap()),
+            Err(Error::LitSize(12, 39))
+        ));
+        assert!(matches!(Lit::parse(b"QLIT"), Err(Error::LitHeader)));
+        assert!(matches!(
+            Lit::parse(b"QLIT\x02\x00\x00\x00"),
+            Err(Error::LitVersion(2))
+        ));
+    }
+
+    #[test]
+    fn lightmap_atlas() {
+        let mut bsp = bsp();
+        bsp.faces.pop();
+        assert_eq!(bsp.lightmaps().unwrap().len(), 1);
+        assert!(bsp.lightmap_textures(2).unwrap().is_empty());
+
+        let textures = bsp.lightmap_textures(1).unwrap();
+        let statistics = Statistics::gather(&textures, 0);
+        let mut atlas = Atlas::new(statistics.minimum_box.width, statistics.minimum_box.height);
+        atlas.insert_textures(statistics.textures);
+        let atlas_texture = atlas.generate_texture(&textures).unwrap();
+        let texture = &atlas_texture.textures[atlas_texture.map["lightmap_0"]];
+        let stride = atlas_texture.size.width;
+        let position = texture.position.y * stride + texture.position.x;
+        assert_eq!(
+            atlas_texture.data[position as usize..position as usize + 3],
+            [7, 8, 9]
+        );
+        let position = position + stride;
+        assert_eq!(
+            atlas_texture.data[position as usize..position as usize + 3],
+            [10, 11, 12]
+        );
+    }
+}
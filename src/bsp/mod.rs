@@ -1,10 +1,13 @@
-use crate::datatypes::bsp::Model;
+use crate::datatypes::bsp::{
+    ClipNode2, ClipNode29, Edge2, Edge29, Face2, Face29, Leaf2, Leaf29, Leaf2Psb, Model, Node2,
+    Node29, Node2Psb, VERSION_2PSB, VERSION_BSP2, VERSION_BSP29,
+};
 use crate::datatypes::common::{
     AsciiString, BoundingBox, ClipNode, DirectoryEntry, Edge, Face, Leaf, Node, Plane,
     TextureFaceInfo, TextureInfo, Vector3,
 };
 use crate::datatypes::reader::{self, DataTypeSize};
-use crate::datatypes::reader::{DataType, DataTypeRead, DataTypeReader};
+use crate::datatypes::reader::{DataType, DataTypeRead, DataTypeReader, ReadSeek};
 use serde::Serialize;
 
 #[cfg(feature = "trace")]
@@ -17,6 +20,39 @@ pub type Header = crate::datatypes::bsp::Header;
 mod error;
 pub use error::{Error, Result};
 
+pub mod entities;
+pub use entities::{Entities, Entity};
+
+pub mod visibility;
+
+pub mod collision;
+pub use collision::{Contents, HullTrace};
+
+pub mod lightmap;
+pub use lightmap::{FaceExtents, Lightmap, LightmapStyle, Lit};
+
+/// the variant of the bsp format, they only differ in the width of some lumps
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Bsp29,
+    /// ints instead of shorts for indices
+    Bsp2Psb,
+    /// like 2PSB with float bounding boxes for nodes and leaves
+    Bsp2,
+}
+
+impl Format {
+    pub fn from_version(version: u32) -> Result<Format> {
+        match version {
+            VERSION_BSP29 => Ok(Format::Bsp29),
+            VERSION_2PSB => Ok(Format::Bsp2Psb),
+            VERSION_BSP2 => Ok(Format::Bsp2),
+            v => Err(Error::UnsupportedVersion(v)),
+        }
+    }
+}
+
 #[derive(Serialize, Clone, Debug, Default)]
 pub struct TextureMip {
     pub width: u32,
@@ -30,9 +66,10 @@ pub struct TextureParsed {
     pub mip_levels: Vec<TextureMip>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Bsp {
     pub header: Header,
+    pub format: Format,
     pub textures: Vec<TextureParsed>,
     pub texture_infos: Vec<TextureFaceInfo>,
     pub models: Vec<Model>,
@@ -42,9 +79,20 @@ pub struct Bsp {
     pub vertices: Vec<Vector3<f32>>,
     pub edges_list: Vec<i32>,
     pub clip_nodes: Vec<ClipNode>,
+    /// lightmap samples of all faces, see [`Bsp::face_lightmap`]
     pub light_maps: Vec<u8>,
+    /// colored lightmaps, see [`Bsp::load_lit`]
+    pub lit: Option<Lit>,
     pub leaves: Vec<Leaf>,
     pub planes: Vec<Plane>,
+    /// empty if the entities lump could not be parsed, see [`Bsp::entities_error`]
+    pub entities: Entities,
+    /// why the entities lump could not be parsed, the geometry is still usable
+    pub entities_error: Option<Error>,
+    /// compressed visibility of the leaves, see [`Bsp::leaf_visibility`]
+    pub visibility: Vec<u8>,
+    /// face indices of the leaves
+    pub faces_list: Vec<u32>,
 }
 
 macro_rules! read_directory_entry {
@@ -60,6 +108,13 @@ macro_rules! read_directory_entry {
         trace_stop!($datatypereader);
         de_data
     }};
+    // reads the on disk type and converts it
+    ($datatypereader: ident, $directory_entry: expr, $type: ty as $into: ty, $trace_name: literal) => {{
+        read_directory_entry!($datatypereader, $directory_entry, $type, $trace_name)
+            .into_iter()
+            .map(<$into>::from)
+            .collect::<Vec<$into>>()
+    }};
 }
 
 impl Bsp {
@@ -69,14 +124,20 @@ impl Bsp {
             #[cfg(feature = "trace")]
             trace.clone(),
         );
+        Bsp::parse_reader(&mut dtr)
+    }
+
+    /// parses from any source, only the lumps are read and not the whole file
+    pub fn parse_reader<R: ReadSeek>(dtr: &mut DataTypeReader<R>) -> Result<Self> {
         // read the header
-        let bsp_header = <Header as reader::DataTypeRead>::read(&mut dtr)?;
+        let bsp_header = <Header as reader::DataTypeRead>::read(dtr)?;
+        let format = Format::from_version(bsp_header.version)?;
 
         // parsing all the textures
         dtr.set_position(bsp_header.textures.offset as u64);
         trace_start!(dtr, "textures");
         let texture_header =
-            <crate::datatypes::common::TextureHeader as DataTypeRead>::read(&mut dtr)?;
+            <crate::datatypes::common::TextureHeader as DataTypeRead>::read(dtr)?;
 
         let mut textures: Vec<TextureParsed> = vec![];
         // reading mip texture info
@@ -87,7 +148,7 @@ impl Bsp {
             let offset_current = offset as u64 + bsp_header.textures.offset as u64;
             dtr.set_position(offset_current);
             trace_start!(dtr, format!("texture {}", count));
-            let t = <crate::datatypes::common::TextureInfo>::read(&mut dtr)?;
+            let t = <crate::datatypes::common::TextureInfo>::read(dtr)?;
             let mut mipt_tex: Vec<TextureMip> = vec![];
             let width = t.width;
             let height = t.height;
@@ -147,28 +208,95 @@ impl Bsp {
 
         let vertices = read_directory_entry!(dtr, bsp_header.vertices, Vector3::<f32>, "vertices");
 
-        let edges = read_directory_entry!(dtr, bsp_header.edges, Edge, "edges");
+        let edges = match format {
+            Format::Bsp29 => read_directory_entry!(dtr, bsp_header.edges, Edge29 as Edge, "edges"),
+            Format::Bsp2Psb | Format::Bsp2 => {
+                read_directory_entry!(dtr, bsp_header.edges, Edge2 as Edge, "edges")
+            }
+        };
 
-        let faces = read_directory_entry!(dtr, bsp_header.faces, Face, "faces");
+        let faces = match format {
+            Format::Bsp29 => read_directory_entry!(dtr, bsp_header.faces, Face29 as Face, "faces"),
+            Format::Bsp2Psb | Format::Bsp2 => {
+                read_directory_entry!(dtr, bsp_header.faces, Face2 as Face, "faces")
+            }
+        };
 
-        let nodes = read_directory_entry!(dtr, bsp_header.nodes, Node, "nodes");
+        let nodes = match format {
+            Format::Bsp29 => {
+                let nodes = read_directory_entry!(dtr, bsp_header.nodes, Node29, "nodes");
+                let count = nodes.len();
+                nodes.into_iter().map(|n| n.into_node(count)).collect()
+            }
+            Format::Bsp2Psb => {
+                read_directory_entry!(dtr, bsp_header.nodes, Node2Psb as Node, "nodes")
+            }
+            Format::Bsp2 => read_directory_entry!(dtr, bsp_header.nodes, Node2 as Node, "nodes"),
+        };
 
-        let leaves = read_directory_entry!(dtr, bsp_header.leaves, Leaf, "leaves");
+        let leaves = match format {
+            Format::Bsp29 => {
+                read_directory_entry!(dtr, bsp_header.leaves, Leaf29 as Leaf, "leaves")
+            }
+            Format::Bsp2Psb => {
+                read_directory_entry!(dtr, bsp_header.leaves, Leaf2Psb as Leaf, "leaves")
+            }
+            Format::Bsp2 => read_directory_entry!(dtr, bsp_header.leaves, Leaf2 as Leaf, "leaves"),
+        };
 
         // `read` lighmaps
         trace_start!(dtr, "lightmaps");
         trace_annotate!(dtr, "data");
-        let light_maps = dtr.read_data_from_directory_entry(bsp_header.leaves)?;
+        let light_maps = dtr.read_data_from_directory_entry(bsp_header.lightmaps)?;
         trace_stop!(dtr);
 
-        let clip_nodes = read_directory_entry!(dtr, bsp_header.clipnodes, ClipNode, "clipnodes");
+        let clip_nodes = match format {
+            Format::Bsp29 => read_directory_entry!(
+                dtr,
+                bsp_header.clipnodes,
+                ClipNode29 as ClipNode,
+                "clipnodes"
+            ),
+            Format::Bsp2Psb | Format::Bsp2 => {
+                read_directory_entry!(
+                    dtr,
+                    bsp_header.clipnodes,
+                    ClipNode2 as ClipNode,
+                    "clipnodes"
+                )
+            }
+        };
 
         let edges_list = read_directory_entry!(dtr, bsp_header.edges_list, i32, "idgelist");
 
         let planes = read_directory_entry!(dtr, bsp_header.planes, Plane, "planes");
 
+        let faces_list = match format {
+            Format::Bsp29 => {
+                read_directory_entry!(dtr, bsp_header.faces_list, u16 as u32, "faces list")
+            }
+            Format::Bsp2Psb | Format::Bsp2 => {
+                read_directory_entry!(dtr, bsp_header.faces_list, u32, "faces list")
+            }
+        };
+
+        trace_start!(dtr, "visibility");
+        trace_annotate!(dtr, "data");
+        let visibility = dtr.read_data_from_directory_entry(bsp_header.visibility)?;
+        trace_stop!(dtr);
+
+        trace_start!(dtr, "entities");
+        trace_annotate!(dtr, "data");
+        let entities = dtr.read_data_from_directory_entry(bsp_header.entities)?;
+        trace_stop!(dtr);
+        let (entities, entities_error) = match Entities::parse(&entities) {
+            Ok(entities) => (entities, None),
+            Err(e) => (Entities::default(), Some(e)),
+        };
+
         Ok(Bsp {
             header: bsp_header,
+            format,
             textures,
             models,
             edges,
@@ -178,9 +306,288 @@ impl Bsp {
             faces,
             vertices,
             light_maps,
+            lit: None,
             leaves,
             planes,
             texture_infos,
+            entities,
+            entities_error,
+            visibility,
+            faces_list,
         })
     }
+
+    /// the brush model of the entity, the worldspawn is model 0
+    pub fn entity_model(&self, entity: &Entity) -> Option<&Model> {
+        self.models.get(entity.model_index()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::writer::{DataTypeWrite, DataTypeWriter};
+
+    fn write<T: DataTypeWrite>(values: &[T]) -> Vec<u8> {
+        let mut writer = DataTypeWriter::new();
+        for value in values {
+            value.write(&mut writer).unwrap();
+        }
+        writer.into_data()
+    }
+
+    // a file with only the lumps that differ between the formats
+    fn file(version: u32, lumps: [Vec<u8>; 6]) -> Vec<u8> {
+        let mut data = vec![0; 124];
+        let mut lump = |lump: Vec<u8>| {
+            let entry = DirectoryEntry {
+                offset: data.len() as u32,
+                size: lump.len() as u32,
+            };
+            data.extend(lump);
+            entry
+        };
+        let [edges, faces, nodes, leaves, clipnodes, faces_list] = lumps;
+        let header = Header {
+            version,
+            // a texture count of 0
+            textures: lump(vec![0; 4]),
+            edges: lump(edges),
+            faces: lump(faces),
+            nodes: lump(nodes),
+            leaves: lump(leaves),
+            clipnodes: lump(clipnodes),
+            faces_list: lump(faces_list),
+            ..Default::default()
+        };
+        data[..124].copy_from_slice(&write(&[header]));
+        data
+    }
+
+    fn bounding_box_i16() -> BoundingBox<Vector3<i16>> {
+        BoundingBox {
+            min: Vector3::new(-8, -16, -24),
+            max: Vector3::new(8, 16, 24),
+        }
+    }
+
+    fn check(bsp: &Bsp, format: Format) {
+        assert_eq!(bsp.format, format);
+        assert_eq!((bsp.edges[0].vertex_0, bsp.edges[0].vertex_1), (1, 2));
+        assert_eq!(bsp.faces[0].edge_count, 4);
+        assert_eq!(bsp.faces[0].texture_index, 3);
+        assert_eq!(bsp.faces[0].lightmap_index, 64);
+        assert_eq!((bsp.nodes[0].front, bsp.nodes[0].back), (1, -2));
+        assert_eq!(
+            bsp.nodes[0].bounding_box.min.as_array(),
+            [-8.0, -16.0, -24.0]
+        );
+        assert_eq!(bsp.nodes[0].face_count, 5);
+        assert_eq!(bsp.leaves[0].r#type, -1);
+        assert_eq!(bsp.leaves[0].bounding_box.max.as_array(), [8.0, 16.0, 24.0]);
+        assert_eq!((bsp.leaves[0].face_index, bsp.leaves[0].face_count), (1, 2));
+        assert_eq!(bsp.leaves[0].sound_lava, 4);
+        assert_eq!((bsp.clip_nodes[0].front, bsp.clip_nodes[0].back), (-2, 1));
+        assert_eq!(bsp.faces_list, vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn parse_bsp29() {
+        let data = file(
+            VERSION_BSP29,
+            [
+                write(&[Edge29 {
+                    vertex_0: 1,
+                    vertex_1: 2,
+                }]),
+                write(&[Face29 {
+                    edge_count: 4,
+                    texture_index: 3,
+                    light_additional: vec![255, 255],
+                    lightmap_index: 64,
+                    ..Default::default()
+                }]),
+                // children at or above the node count are leaves
+                write(&[
+                    Node29 {
+                        front: 1,
+                        back: 0xfffe,
+                        bounding_box: bounding_box_i16(),
+                        face_count: 5,
+                        ..Default::default()
+                    },
+                    Node29::default(),
+                ]),
+                write(&[Leaf29 {
+                    r#type: -1,
+                    bounding_box: bounding_box_i16(),
+                    face_index: 1,
+                    face_count: 2,
+                    sound_lava: 4,
+                    ..Default::default()
+                }]),
+                write(&[ClipNode29 {
+                    plane_index: 0,
+                    front: 0xfffe,
+                    back: 1,
+                }]),
+                write(&[7_u16, 8, 9]),
+            ],
+        );
+        check(
+            &Bsp::parse(
+                data,
+                #[cfg(feature = "trace")]
+                None,
+            )
+            .unwrap(),
+            Format::Bsp29,
+        );
+    }
+
+    #[test]
+    fn parse_bsp2() {
+        let lumps = |nodes, leaves| {
+            [
+                write(&[Edge2 {
+                    vertex_0: 1,
+                    vertex_1: 2,
+                }]),
+                write(&[Face2 {
+                    edge_count: 4,
+                    texture_index: 3,
+                    light_additional: vec![255, 255],
+                    lightmap_index: 64,
+                    ..Default::default()
+                }]),
+                nodes,
+                leaves,
+                write(&[ClipNode2 {
+                    plane_index: 0,
+                    front: -2,
+                    back: 1,
+                }]),
+                write(&[7_u32, 8, 9]),
+            ]
+        };
+
+        let data = file(
+            VERSION_2PSB,
+            lumps(
+                write(&[Node2Psb {
+                    front: 1,
+                    back: -2,
+                    bounding_box: bounding_box_i16(),
+                    face_count: 5,
+                    ..Default::default()
+                }]),
+                write(&[Leaf2Psb {
+                    r#type: -1,
+                    bounding_box: bounding_box_i16(),
+                    face_index: 1,
+                    face_count: 2,
+                    sound_lava: 4,
+                    ..Default::default()
+                }]),
+            ),
+        );
+        check(
+            &Bsp::parse(
+                data,
+                #[cfg(feature = "trace")]
+                None,
+            )
+            .unwrap(),
+            Format::Bsp2Psb,
+        );
+
+        let bounding_box = BoundingBox {
+            min: Vector3::new(-8.0, -16.0, -24.0),
+            max: Vector3::new(8.0, 16.0, 24.0),
+        };
+        let data = file(
+            VERSION_BSP2,
+            lumps(
+                write(&[Node2 {
+                    front: 1,
+                    back: -2,
+                    bounding_box,
+                    face_count: 5,
+                    ..Default::default()
+                }]),
+                write(&[Leaf2 {
+                    r#type: -1,
+                    bounding_box,
+                    face_index: 1,
+                    face_count: 2,
+                    sound_lava: 4,
+                    ..Default::default()
+                }]),
+            ),
+        );
+        check(
+            &Bsp::parse(
+                data,
+                #[cfg(feature = "trace")]
+                None,
+            )
+            .unwrap(),
+            Format::Bsp2,
+        );
+
+        let data = file(30, Default::default());
+        assert!(matches!(
+            Bsp::parse(
+                data,
+                #[cfg(feature = "trace")]
+                None
+            ),
+            Err(Error::UnsupportedVersion(30))
+        ));
+    }
+
+    #[test]
+    fn large_bsp29_children() {
+        let node = Node29 {
+            front: 40000,
+            back: 40001,
+            ..Default::default()
+        }
+        .into_node(40001);
+        assert_eq!((node.front, node.back), (40000, -25535));
+
+        let clip_node = ClipNode::from(ClipNode29 {
+            plane_index: 0,
+            front: 40000,
+            back: 0xffff,
+        });
+        assert_eq!((clip_node.front, clip_node.back), (40000, -1));
+    }
+
+    #[test]
+    fn broken_entities() {
+        let parse = |entities: &[u8]| {
+            let mut data = file(VERSION_BSP29, Default::default());
+            let entry = write(&[DirectoryEntry {
+                offset: data.len() as u32,
+                size: entities.len() as u32,
+            }]);
+            // the entities entry follows the version
+            data[4..12].copy_from_slice(&entry);
+            data.extend_from_slice(entities);
+            Bsp::parse(
+                data,
+                #[cfg(feature = "trace")]
+                None,
+            )
+            .unwrap()
+        };
+        let bsp = parse(b"{ \"classname\" \"worldspawn\" }\n\0");
+        assert_eq!(bsp.entities.len(), 1);
+        assert!(bsp.entities_error.is_none());
+
+        let bsp = parse(b"{ \"classname\" \"worldspawn\"\0");
+        assert!(bsp.entities.is_empty());
+        assert!(matches!(bsp.entities_error, Some(Error::EntityParse(_, _))));
+    }
 }
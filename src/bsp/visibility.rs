@@ -0,0 +1,159 @@
+use super::Bsp;
+use crate::datatypes::common::Vector3;
+
+/// run length decompression of a visibility row, a 0 is followed by the count of 0 bytes
+pub fn decompress(data: &[u8], row: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(row);
+    let mut data = data.iter();
+    while out.len() < row {
+        match data.next() {
+            Some(0) => {
+                let count = *data.next().unwrap_or(&0) as usize;
+                out.resize((out.len() + count).min(row), 0);
+            }
+            Some(b) => out.push(*b),
+            None => break,
+        }
+    }
+    // truncated data sees nothing
+    out.resize(row, 0);
+    out
+}
+
+impl Bsp {
+    /// number of leaves with visibility information, leaf 0 is the solid leaf and has none
+    pub fn visibility_leaf_count(&self) -> usize {
+        match self.models.first() {
+            Some(model) => model.leafs_count.max(0) as usize,
+            None => self.leaves.len().saturating_sub(1),
+        }
+    }
+
+    /// the leaf containing origin, walking the nodes of the world model
+    pub fn point_in_leaf(&self, origin: &Vector3<f32>) -> usize {
+        let mut node = match self.models.first() {
+            Some(model) => model.node_id0,
+            None => 0,
+        };
+        while node >= 0 {
+            let n = match self.nodes.get(node as usize) {
+                Some(n) => n,
+                None => return 0,
+            };
+            let plane = match self.planes.get(n.plane_index as usize) {
+                Some(p) => p,
+                None => return 0,
+            };
+            let d = plane.normal.dot_product(origin) - plane.distance;
+            node = if d > 0.0 { n.front } else { n.back };
+        }
+        (-1 - node) as usize
+    }
+
+    /// the decompressed visibility of leaf, bit n - 1 is set when leaf n is visible.
+    /// leaves without visibility see everything like the engine treats them
+    pub fn leaf_visibility(&self, leaf: usize) -> Vec<u8> {
+        let row = self.visibility_leaf_count().div_ceil(8);
+        let offset = match self.leaves.get(leaf) {
+            Some(l) if leaf != 0 && l.visibility_list_index >= 0 => {
+                l.visibility_list_index as usize
+            }
+            _ => return vec![0xff; row],
+        };
+        if self.visibility.is_empty() {
+            return vec![0xff; row];
+        }
+        decompress(self.visibility.get(offset..).unwrap_or_default(), row)
+    }
+
+    /// if anything in leaf a can potentially see leaf b
+    pub fn leaf_can_see(&self, a: usize, b: usize) -> bool {
+        if b == 0 || b > self.visibility_leaf_count() {
+            return false;
+        }
+        let visibility = self.leaf_visibility(a);
+        visibility[(b - 1) >> 3] & (1 << ((b - 1) & 7)) != 0
+    }
+
+    /// all leaves visible from leaf
+    pub fn visible_leaves(&self, leaf: usize) -> Vec<usize> {
+        let visibility = self.leaf_visibility(leaf);
+        (1..=self.visibility_leaf_count())
+            .filter(|l| visibility[(l - 1) >> 3] & (1 << ((l - 1) & 7)) != 0)
+            .collect()
+    }
+
+    /// the faces of leaf from the faces list
+    pub fn leaf_faces(&self, leaf: usize) -> &[u32] {
+        let leaf = match self.leaves.get(leaf) {
+            Some(l) => l,
+            None => return &[],
+        };
+        let start = leaf.face_index as usize;
+        let end = start + leaf.face_count as usize;
+        self.faces_list.get(start..end).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::bsp::Model;
+    use crate::datatypes::common::{Leaf, Node, Plane};
+
+    // x > 0 is leaf 1, everything else leaf 2, a third leaf is never reached
+    fn bsp() -> Bsp {
+        Bsp {
+            models: vec![Model {
+                leafs_count: 3,
+                ..Default::default()
+            }],
+            planes: vec![Plane {
+                normal: Vector3::new(1.0, 0.0, 0.0),
+                distance: 0.0,
+                r#type: 0,
+            }],
+            nodes: vec![Node {
+                front: -2,
+                back: -3,
+                ..Default::default()
+            }],
+            leaves: [-1, 0, 1, -1]
+                .into_iter()
+                .map(|visibility_list_index| Leaf {
+                    visibility_list_index,
+                    face_index: 1,
+                    face_count: 2,
+                    ..Default::default()
+                })
+                .collect(),
+            // leaf 1 sees 1 and 2, leaf 2 sees nothing
+            visibility: vec![0b011, 0, 1],
+            faces_list: vec![4, 5, 6],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decompress_rows() {
+        assert_eq!(decompress(&[1, 0, 3, 2], 5), vec![1, 0, 0, 0, 2]);
+        assert_eq!(decompress(&[1, 0, 9], 3), vec![1, 0, 0]);
+        assert_eq!(decompress(&[1], 3), vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn leaf_queries() {
+        let bsp = bsp();
+        assert_eq!(bsp.point_in_leaf(&Vector3::new(10.0, 0.0, 0.0)), 1);
+        assert_eq!(bsp.point_in_leaf(&Vector3::new(-10.0, 5.0, 0.0)), 2);
+        assert!(bsp.leaf_can_see(1, 2));
+        assert!(!bsp.leaf_can_see(1, 3));
+        assert!(!bsp.leaf_can_see(2, 1));
+        assert!(!bsp.leaf_can_see(1, 0));
+        assert_eq!(bsp.visible_leaves(1), vec![1, 2]);
+        assert!(bsp.visible_leaves(2).is_empty());
+        // no visibility data sees everything
+        assert_eq!(bsp.visible_leaves(3), vec![1, 2, 3]);
+        assert_eq!(bsp.leaf_faces(1), &[5, 6]);
+    }
+}
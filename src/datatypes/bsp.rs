@@ -1,4 +1,4 @@
-use protocol_macros::DataTypeRead;
+use protocol_macros::{DataTypeRead, DataTypeWrite};
 /// Structs needed to read the Quakeworld data formats
 /// based on: https://www.gamers.org/dEngine/quake/spec/quake-spec34/qkspec_4.htm
 use serde::Serialize;
@@ -10,12 +10,19 @@ use super::{
 use crate::trace::{trace_annotate, trace_start, trace_stop};
 use protocol_macros::DataTypeBoundCheckDerive;
 
-use crate::datatypes::common::{DataType, DirectoryEntry};
+use crate::datatypes::common::{ClipNode, DataType, DirectoryEntry, Edge, Face, Leaf, Node};
+
+/// `Header.version` of the original format
+pub const VERSION_BSP29: u32 = 29;
+/// `Header.version` of 2PSB, "2PSB" as little endian
+pub const VERSION_2PSB: u32 = u32::from_le_bytes(*b"2PSB");
+/// `Header.version` of BSP2, "BSP2" as little endian
+pub const VERSION_BSP2: u32 = u32::from_le_bytes(*b"BSP2");
 
 /// BSP related structs
 /// BSP Header
 ///https://www.gamers.org/dEngine/quake/spec/quake-spec34/qkspec_4.htm
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeBoundCheckDerive, Default)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, DataTypeBoundCheckDerive, Default)]
 #[datatyperead(prefix = "bsp", internal)]
 pub struct Header {
     /// Model version
@@ -96,7 +103,7 @@ pub struct Header {
     pub models: DirectoryEntry,
 }
 
-#[derive(Serialize, Clone, Debug, Default, DataTypeRead)]
+#[derive(Serialize, Clone, Debug, Default, DataTypeRead, DataTypeWrite)]
 #[datatyperead(prefix = "bsp", internal)]
 pub struct Model {
     pub bounding_box: BoundingBox<Vector3<f32>>,
@@ -109,3 +116,323 @@ pub struct Model {
     pub face_index: i32,
     pub face_count: i32,
 }
+
+// on disk layouts of the bsp formats, BSP29 uses shorts where 2PSB and BSP2 use ints,
+// BSP2 also stores the node and leaf bounding boxes as floats
+
+fn widen(bounding_box: &BoundingBox<Vector3<i16>>) -> BoundingBox<Vector3<f32>> {
+    let widen = |v: &Vector3<i16>| Vector3::new(v.x as f32, v.y as f32, v.z as f32);
+    BoundingBox {
+        min: widen(&bounding_box.min),
+        max: widen(&bounding_box.max),
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Edge29 {
+    pub vertex_0: u16,
+    pub vertex_1: u16,
+}
+
+impl From<Edge29> for Edge {
+    fn from(edge: Edge29) -> Self {
+        Edge {
+            vertex_0: edge.vertex_0 as u32,
+            vertex_1: edge.vertex_1 as u32,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Edge2 {
+    pub vertex_0: u32,
+    pub vertex_1: u32,
+}
+
+impl From<Edge2> for Edge {
+    fn from(edge: Edge2) -> Self {
+        Edge {
+            vertex_0: edge.vertex_0,
+            vertex_1: edge.vertex_1,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Face29 {
+    pub plane_index: u16,
+    pub side: u16,
+    pub edge_index: i32,
+    pub edge_count: u16,
+    pub texture_index: u16,
+    pub light_type: u8,
+    pub light_base: u8,
+    #[datatyperead(size_from = 2)]
+    pub light_additional: Vec<u8>,
+    // TODO: implement a way to read slices
+    // pub light_additional: [u8; 2],
+    pub lightmap_index: i32,
+}
+
+impl From<Face29> for Face {
+    fn from(face: Face29) -> Self {
+        Face {
+            plane_index: face.plane_index as u32,
+            side: face.side as u32,
+            edge_index: face.edge_index,
+            edge_count: face.edge_count as u32,
+            texture_index: face.texture_index as u32,
+            light_type: face.light_type,
+            light_base: face.light_base,
+            light_additional: face.light_additional,
+            lightmap_index: face.lightmap_index,
+        }
+    }
+}
+
+/// the face of 2PSB and BSP2
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Face2 {
+    pub plane_index: u32,
+    pub side: u32,
+    pub edge_index: i32,
+    pub edge_count: u32,
+    pub texture_index: u32,
+    pub light_type: u8,
+    pub light_base: u8,
+    #[datatyperead(size_from = 2)]
+    pub light_additional: Vec<u8>,
+    pub lightmap_index: i32,
+}
+
+impl From<Face2> for Face {
+    fn from(face: Face2) -> Self {
+        Face {
+            plane_index: face.plane_index,
+            side: face.side,
+            edge_index: face.edge_index,
+            edge_count: face.edge_count,
+            texture_index: face.texture_index,
+            light_type: face.light_type,
+            light_base: face.light_base,
+            light_additional: face.light_additional,
+            lightmap_index: face.lightmap_index,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Node29 {
+    pub plane_index: u32,
+    pub front: u16,
+    pub back: u16,
+    pub bounding_box: BoundingBox<Vector3<i16>>,
+    pub face_index: u16,
+    pub face_count: u16,
+}
+
+impl Node29 {
+    /// children are unsigned, the ones at or above the node count of the map are leaves
+    pub fn into_node(self, count: usize) -> Node {
+        let child = |c: u16| match (c as usize) < count {
+            true => c as i32,
+            false => c as i32 - 0x10000,
+        };
+        Node {
+            plane_index: self.plane_index,
+            front: child(self.front),
+            back: child(self.back),
+            bounding_box: widen(&self.bounding_box),
+            face_index: self.face_index as u32,
+            face_count: self.face_count as u32,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Node2Psb {
+    pub plane_index: u32,
+    pub front: i32,
+    pub back: i32,
+    pub bounding_box: BoundingBox<Vector3<i16>>,
+    pub face_index: u32,
+    pub face_count: u32,
+}
+
+impl From<Node2Psb> for Node {
+    fn from(node: Node2Psb) -> Self {
+        Node {
+            plane_index: node.plane_index,
+            front: node.front,
+            back: node.back,
+            bounding_box: widen(&node.bounding_box),
+            face_index: node.face_index,
+            face_count: node.face_count,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Node2 {
+    pub plane_index: u32,
+    pub front: i32,
+    pub back: i32,
+    pub bounding_box: BoundingBox<Vector3<f32>>,
+    pub face_index: u32,
+    pub face_count: u32,
+}
+
+impl From<Node2> for Node {
+    fn from(node: Node2) -> Self {
+        Node {
+            plane_index: node.plane_index,
+            front: node.front,
+            back: node.back,
+            bounding_box: node.bounding_box,
+            face_index: node.face_index,
+            face_count: node.face_count,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Leaf29 {
+    pub r#type: i32,
+    pub visibility_list_index: i32,
+    pub bounding_box: BoundingBox<Vector3<i16>>,
+    pub face_index: u16,
+    pub face_count: u16,
+    pub sound_water: u8,
+    pub sound_sky: u8,
+    pub sound_slime: u8,
+    pub sound_lava: u8,
+}
+
+impl From<Leaf29> for Leaf {
+    fn from(leaf: Leaf29) -> Self {
+        Leaf {
+            r#type: leaf.r#type,
+            visibility_list_index: leaf.visibility_list_index,
+            bounding_box: widen(&leaf.bounding_box),
+            face_index: leaf.face_index as u32,
+            face_count: leaf.face_count as u32,
+            sound_water: leaf.sound_water,
+            sound_sky: leaf.sound_sky,
+            sound_slime: leaf.sound_slime,
+            sound_lava: leaf.sound_lava,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Leaf2Psb {
+    pub r#type: i32,
+    pub visibility_list_index: i32,
+    pub bounding_box: BoundingBox<Vector3<i16>>,
+    pub face_index: u32,
+    pub face_count: u32,
+    pub sound_water: u8,
+    pub sound_sky: u8,
+    pub sound_slime: u8,
+    pub sound_lava: u8,
+}
+
+impl From<Leaf2Psb> for Leaf {
+    fn from(leaf: Leaf2Psb) -> Self {
+        Leaf {
+            r#type: leaf.r#type,
+            visibility_list_index: leaf.visibility_list_index,
+            bounding_box: widen(&leaf.bounding_box),
+            face_index: leaf.face_index,
+            face_count: leaf.face_count,
+            sound_water: leaf.sound_water,
+            sound_sky: leaf.sound_sky,
+            sound_slime: leaf.sound_slime,
+            sound_lava: leaf.sound_lava,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct Leaf2 {
+    pub r#type: i32,
+    pub visibility_list_index: i32,
+    pub bounding_box: BoundingBox<Vector3<f32>>,
+    pub face_index: u32,
+    pub face_count: u32,
+    pub sound_water: u8,
+    pub sound_sky: u8,
+    pub sound_slime: u8,
+    pub sound_lava: u8,
+}
+
+impl From<Leaf2> for Leaf {
+    fn from(leaf: Leaf2) -> Self {
+        Leaf {
+            r#type: leaf.r#type,
+            visibility_list_index: leaf.visibility_list_index,
+            bounding_box: leaf.bounding_box,
+            face_index: leaf.face_index,
+            face_count: leaf.face_count,
+            sound_water: leaf.sound_water,
+            sound_sky: leaf.sound_sky,
+            sound_slime: leaf.sound_slime,
+            sound_lava: leaf.sound_lava,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct ClipNode29 {
+    pub plane_index: u32,
+    pub front: u16,
+    pub back: u16,
+}
+
+// clipnode children are unsigned, only the last 16 values are contents
+fn clip_child(child: u16) -> i32 {
+    match child {
+        0xfff0.. => child as i32 - 0x10000,
+        _ => child as i32,
+    }
+}
+
+impl From<ClipNode29> for ClipNode {
+    fn from(clip_node: ClipNode29) -> Self {
+        ClipNode {
+            plane_index: clip_node.plane_index,
+            front: clip_child(clip_node.front),
+            back: clip_child(clip_node.back),
+        }
+    }
+}
+
+/// the clipnode of 2PSB and BSP2
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "bsp", internal)]
+pub struct ClipNode2 {
+    pub plane_index: u32,
+    pub front: i32,
+    pub back: i32,
+}
+
+impl From<ClipNode2> for ClipNode {
+    fn from(clip_node: ClipNode2) -> Self {
+        ClipNode {
+            plane_index: clip_node.plane_index,
+            front: clip_node.front,
+            back: clip_node.back,
+        }
+    }
+}
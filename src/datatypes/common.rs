@@ -3,13 +3,14 @@ use serde::Serialize;
 
 use std::ops::Index;
 
-use protocol_macros::DataTypeRead;
+use protocol_macros::{DataTypeRead, DataTypeWrite};
 
 use super::reader::{
-    DataTypeBoundCheck, DataTypeRead, DataTypeReader, DataTypeSize, Error, Result,
+    DataTypeBoundCheck, DataTypeRead, DataTypeReader, DataTypeSize, Error, ReadSeek, Result,
 };
 
-use crate::trace::{trace_start, trace_stop};
+use super::writer::{self, DataTypeWriter};
+use crate::trace::{trace_annotate, trace_start, trace_stop};
 
 use super::bsp;
 use super::mdl;
@@ -20,7 +21,7 @@ pub trait AsciiString {
 }
 
 /// A vector or position
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(ommit_trait = DataTypeSize, internal)]
 pub struct Vector3<T: DataTypeRead + 'static>
 where
@@ -112,7 +113,7 @@ impl AsciiString for Vec<u8> {
 }
 
 /// Bounding box
-#[derive(Serialize, Clone, Debug, Copy, Default, DataTypeRead)]
+#[derive(Serialize, Clone, Debug, Copy, Default, DataTypeRead, DataTypeWrite)]
 #[datatyperead(internal)]
 pub struct BoundingBox<T: DataTypeRead + DataTypeSize + 'static>
 where
@@ -150,6 +151,8 @@ pub enum DataType {
     BSPMODEL(bsp::Model),
     DIRECTORYENTRY(DirectoryEntry),
     MDLSKIN(mdl::Skin),
+    MDLSKINGROUP(mdl::SkinGroup),
+    MDLSKINTYPE(mdl::SkinType),
     MDLFRAME(mdl::Frame),
     MDLFRAMESIMPLE(mdl::FrameSimple),
     MDLFRAMEGROUP(mdl::FrameGroup),
@@ -160,11 +163,18 @@ pub enum DataType {
     TRIANGLE(Triangle),
     TEXTURECOORDINATE(TextureCoordinate),
     VERTEX(Vertex),
-    EDGE(Edge),
-    FACE(Face),
-    NODE(Node),
-    LEAF(Leaf),
-    CLIPNODE(ClipNode),
+    BSPEDGE29(bsp::Edge29),
+    BSPEDGE2(bsp::Edge2),
+    BSPFACE29(bsp::Face29),
+    BSPFACE2(bsp::Face2),
+    BSPNODE29(bsp::Node29),
+    BSPNODE2PSB(bsp::Node2Psb),
+    BSPNODE2(bsp::Node2),
+    BSPLEAF29(bsp::Leaf29),
+    BSPLEAF2PSB(bsp::Leaf2Psb),
+    BSPLEAF2(bsp::Leaf2),
+    BSPCLIPNODE29(bsp::ClipNode29),
+    BSPCLIPNODE2(bsp::ClipNode2),
     QTV(crate::qtv::QtvType),
     VECTOR3GENERIC,
     BOUNDINGBOXGENERIC,
@@ -185,7 +195,7 @@ impl DataType {
 
 /// Directory entry: describes the position and size of a chunk of data inside a BSP File
 #[derive(Serialize, Clone, Debug, Copy, DataTypeRead, Default)]
-#[datatyperead(internal)]
+#[datatyperead(ommit_trait = DataTypeRead, internal)]
 pub struct DirectoryEntry {
     /// Offset from the sart of the file
     pub offset: u32,
@@ -193,23 +203,58 @@ pub struct DirectoryEntry {
     pub size: u32,
 }
 
-impl DirectoryEntry {
-    pub fn environment(&self, datatypereader: &mut DataTypeReader, name: impl Into<String>) {
+// not derived so the environment can set both the size and the offset
+impl DataTypeRead for DirectoryEntry {
+    fn read<R: ReadSeek>(datareader: &mut DataTypeReader<R>) -> Result<Self> {
+        trace_start!(datareader, "DirectoryEntry");
+        trace_annotate!(datareader, "offset");
+        let offset = <u32 as DataTypeRead>::read(datareader)?;
+        trace_annotate!(datareader, "size");
+        let size = <u32 as DataTypeRead>::read(datareader)?;
+        let s = Self { offset, size };
+        trace_stop!(datareader, s.to_datatype());
+        Ok(s)
+    }
+
+    fn to_datatype(&self) -> DataType {
+        DataType::DIRECTORYENTRY(*self)
+    }
+
+    fn environment<R: ReadSeek>(
+        &self,
+        datatypereader: &mut DataTypeReader<R>,
+        name: impl Into<String>,
+    ) {
         let name = name.into();
         datatypereader.set_env(format!("{}_size", name), self.size);
         datatypereader.set_env(format!("{}_offset", name), self.offset);
     }
 }
 
+// not derived so the environment can set both the size and the offset
+impl writer::DataTypeWrite for DirectoryEntry {
+    fn write(&self, datawriter: &mut DataTypeWriter) -> writer::Result<()> {
+        writer::DataTypeWrite::write(&self.offset, datawriter)?;
+        writer::DataTypeWrite::write(&self.size, datawriter)
+    }
+
+    fn environment(&self, datawriter: &mut DataTypeWriter, name: impl Into<String>) {
+        let name = name.into();
+        datawriter.set_env(format!("{}_size", name), self.size);
+        datawriter.set_env(format!("{}_offset", name), self.offset);
+    }
+}
+
 impl DataTypeBoundCheck for DirectoryEntry {
-    fn check_bounds(&self, datatypereader: &mut DataTypeReader) -> Result<()> {
-        let size = datatypereader.data.len() as u32;
-        if self.offset + self.size > size {
+    fn check_bounds<R: ReadSeek>(&self, datatypereader: &mut DataTypeReader<R>) -> Result<()> {
+        let size = datatypereader.len();
+        let end = self.offset as u64 + self.size as u64;
+        if end > size {
             return Err(Error::BoundCheckError(
                 self.offset.into(),
                 self.size.into(),
-                (self.offset + self.size - size).into(),
-                size.into(),
+                end - size,
+                size,
             ));
         }
         Ok(())
@@ -217,7 +262,7 @@ impl DataTypeBoundCheck for DirectoryEntry {
 }
 
 impl<T: DataTypeBoundCheck> DataTypeBoundCheck for Vec<T> {
-    fn check_bounds(&self, datatypereader: &mut DataTypeReader) -> Result<()> {
+    fn check_bounds<R: ReadSeek>(&self, datatypereader: &mut DataTypeReader<R>) -> Result<()> {
         for e in self {
             e.check_bounds(datatypereader)?
         }
@@ -225,7 +270,7 @@ impl<T: DataTypeBoundCheck> DataTypeBoundCheck for Vec<T> {
     }
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
 #[datatyperead(internal)]
 pub struct TextureCoordinate {
     pub onseam: i32,
@@ -233,14 +278,14 @@ pub struct TextureCoordinate {
     pub t: i32,
 }
 
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct Triangle {
     pub faces_front: u32,
     pub vertex: Vector3<i32>,
 }
 
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct Vertex {
     pub v: Vector3<u8>,
@@ -263,63 +308,62 @@ impl Index<usize> for Vertex {
     }
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
-#[datatyperead(internal)]
+// the in memory bsp structs shared by all bsp formats, see bsp::Node29 etc. for the on disk layouts
+
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct Edge {
-    pub vertex_0: u16,
-    pub vertex_1: u16,
+    pub vertex_0: u32,
+    pub vertex_1: u32,
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
-#[datatyperead(internal)]
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct Node {
     pub plane_index: u32,
-    pub front: u16,
-    pub back: u16,
-    pub bounding_box: BoundingBox<i16>,
-    pub face_index: u16,
-    pub face_count: u16,
+    /// leaves are stored as -(leaf + 1)
+    pub front: i32,
+    pub back: i32,
+    pub bounding_box: BoundingBox<Vector3<f32>>,
+    pub face_index: u32,
+    pub face_count: u32,
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
-#[datatyperead(internal)]
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct Face {
-    pub plane_index: u16,
-    pub side: u16,
+    pub plane_index: u32,
+    pub side: u32,
     pub edge_index: i32,
-    pub edge_count: u16,
-    pub texture_index: u16,
+    pub edge_count: u32,
+    pub texture_index: u32,
     pub light_type: u8,
     pub light_base: u8,
-    #[datatyperead(size_from = 2)]
     pub light_additional: Vec<u8>,
-    // TODO: implement a way to read slices
-    // pub light_additional: [u8; 2],
     pub lightmap_index: i32,
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
-#[datatyperead(internal)]
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct Leaf {
     pub r#type: i32,
+    /// offset into the visibility lump, -1 if the leaf has none
     pub visibility_list_index: i32,
-    pub bounding_box: BoundingBox<i16>,
-    pub face_index: u16,
-    pub face_count: u16,
+    pub bounding_box: BoundingBox<Vector3<f32>>,
+    /// index and count into the faces list
+    pub face_index: u32,
+    pub face_count: u32,
+    pub sound_water: u8,
     pub sound_sky: u8,
-    pub sound_limit: u8,
+    pub sound_slime: u8,
     pub sound_lava: u8,
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
-#[datatyperead(internal)]
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct ClipNode {
     pub plane_index: u32,
-    pub front: i16,
-    pub back: u16,
+    /// negative values are contents
+    pub front: i32,
+    pub back: i32,
 }
 
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct Plane {
     pub normal: Vector3<f32>,
@@ -327,7 +371,7 @@ pub struct Plane {
     pub r#type: i32,
 }
 
-#[derive(Serialize, Clone, Debug, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct TextureHeader {
     #[datatyperead(environment = "texture_header_count")]
@@ -336,7 +380,7 @@ pub struct TextureHeader {
     pub offsets: Vec<i32>,
 }
 
-#[derive(Serialize, Clone, Debug, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct TextureInfo {
     #[datatyperead(size_from = 16, string)]
@@ -349,7 +393,7 @@ pub struct TextureInfo {
     pub offset8: u32, // offset to u_char Pix[width/8 * height/8]
 }
 
-#[derive(Serialize, Clone, Debug, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct TextureFaceInfo {
     pub vec_s: Vector3<f32>,
@@ -368,7 +412,7 @@ pub struct Texture {
     pub data: Vec<u8>,
 }
 
-#[derive(Serialize, Clone, Debug, DataTypeRead, Default)]
+#[derive(Serialize, Clone, Debug, DataTypeRead, DataTypeWrite, Default)]
 #[datatyperead(internal)]
 pub struct Bsp {
     pub header: bsp::Header,
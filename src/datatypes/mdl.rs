@@ -1,14 +1,11 @@
-use protocol_macros::DataTypeRead;
+use protocol_macros::{DataTypeRead, DataTypeWrite};
 /// Structs needed to read the Quakeworld data formats
 /// based on: https://www.gamers.org/dEngine/quake/spec/quake-spec34/qkspec_4.htm
 use serde::Serialize;
 
-use super::common::{BoundingBox, DataType, Vector3, Vertex};
-use super::reader::{DataTypeRead, DataTypeReader, DataTypeSize, Error, Result};
-use crate::datatypes::reader;
-use crate::trace::{trace_annotate, trace_start, trace_stop};
+use super::common::{BoundingBox, Vector3, Vertex};
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
 #[datatyperead(prefix = "mdl", internal)]
 pub struct Header {
     pub magic: u32,
@@ -28,15 +25,19 @@ pub struct Header {
     pub size: f32,
 }
 
-#[derive(Serialize, Debug, Default, Clone)]
-// #[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
-// #[datatyperead(prefix = "mdl", internal)]
-pub struct Frame {
-    pub frame_type: u32,
-    pub frame: FrameType,
+/// a u32 of 0 is followed by a single frame, anything else by a group of frames
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[datatyperead(prefix = "mdl", tag = u32, internal)]
+pub enum Frame {
+    #[default]
+    None,
+    #[datatyperead(tag = 0)]
+    Single(FrameSimple),
+    #[datatyperead(tag = 1, fallback)]
+    Group(FrameGroup),
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
 #[datatyperead(prefix = "mdl", internal)]
 pub struct FrameSimple {
     pub bounding_box: BoundingBox<Vertex>,
@@ -46,7 +47,7 @@ pub struct FrameSimple {
     pub vertex: Vec<Vertex>,
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
 #[datatyperead(prefix = "mdl", internal)]
 pub struct FrameGroup {
     #[datatyperead(environment)]
@@ -58,48 +59,32 @@ pub struct FrameGroup {
     pub frames: Vec<FrameSimple>,
 }
 
-#[derive(Serialize, Debug, Default, Clone)]
-pub enum FrameType {
-    #[default]
-    None,
-    Single(FrameSimple),
-    Group(FrameGroup),
-}
-
-impl DataTypeRead for Frame {
-    fn read(dtr: &mut DataTypeReader) -> Result<Self> {
-        trace_annotate!(dtr, "type");
-        let frame_type = <u32 as reader::DataTypeRead>::read(dtr)?;
-        let frame = if frame_type == 0 {
-            FrameType::Single(<FrameSimple as reader::DataTypeRead>::read(dtr)?)
-        } else {
-            FrameType::Group(<FrameGroup as reader::DataTypeRead>::read(dtr)?)
-        };
-        Ok(Self { frame_type, frame })
-    }
-
-    fn to_datatype(&self) -> DataType {
-        DataType::MDLFRAME(self.clone())
-    }
-
-    fn environment(&self, datatypereader: &mut DataTypeReader, name: impl Into<String>) {
-        panic!("we do get called?");
-        // compile_error!("you need to implement the environment function");
-    }
-}
-
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
 #[datatyperead(prefix = "mdl", internal)]
 pub struct Skin {
-    pub time: f32,
     #[datatyperead(size_from = "skin_size")]
     pub data: Vec<u8>,
 }
 
-#[derive(Serialize, Debug, Default, Clone)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite)]
+#[datatyperead(prefix = "mdl", internal)]
+pub struct SkinGroup {
+    #[datatyperead(environment)]
+    pub count: u32,
+    #[datatyperead(size_from = "count")]
+    pub times: Vec<f32>,
+    #[datatyperead(size_from = "count")]
+    pub skins: Vec<Skin>,
+}
+
+/// a u32 of 0 is followed by a single skin, anything else by a group of skins
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead)]
+#[datatyperead(prefix = "mdl", tag = u32, internal)]
 pub enum SkinType {
     #[default]
     None,
+    #[datatyperead(tag = 0)]
     Single(Skin),
-    Group(Vec<Skin>),
+    #[datatyperead(tag = 1, fallback)]
+    Group(SkinGroup),
 }
@@ -8,6 +8,7 @@ pub mod common;
 pub mod mdl;
 pub mod pak;
 pub mod reader;
+pub mod writer;
 
 mod error;
 pub use error::{Error, Result};
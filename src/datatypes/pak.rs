@@ -1,7 +1,7 @@
 use crate::datatypes::reader::{
     DataTypeBoundCheck, DataTypeRead, DataTypeReader, DataTypeSize, Error, Result,
 };
-use protocol_macros::DataTypeRead;
+use protocol_macros::{DataTypeRead, DataTypeWrite};
 use serde::Serialize;
 
 use protocol_macros::DataTypeBoundCheckDerive;
@@ -12,7 +12,7 @@ use crate::trace::{trace_start, trace_stop};
 /// PAK related structs
 /// PAK Header
 type Version = u32;
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeBoundCheckDerive)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, DataTypeBoundCheckDerive)]
 #[datatyperead(prefix = "pak", internal)]
 pub struct Header {
     /// Pak version
@@ -22,7 +22,7 @@ pub struct Header {
     pub directory_offset: DirectoryEntry,
 }
 
-#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeBoundCheckDerive)]
+#[derive(Serialize, Clone, Debug, Copy, DataTypeRead, DataTypeWrite, DataTypeBoundCheckDerive)]
 #[datatyperead(prefix = "pak", internal)]
 pub struct HeaderLight {
     /// Pak version
@@ -31,7 +31,7 @@ pub struct HeaderLight {
     pub directory_offset: DirectoryEntry,
 }
 
-#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeBoundCheckDerive)]
+#[derive(Serialize, Debug, Default, Clone, DataTypeRead, DataTypeWrite, DataTypeBoundCheckDerive)]
 #[datatyperead(prefix = "pak", internal)]
 pub struct File {
     #[datatyperead(size_from = 56, string)]
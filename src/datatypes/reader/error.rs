@@ -18,6 +18,10 @@ pub enum Error {
     EnvironmentVariableNotSet(String, String),
     #[error("environment variable ({0})")]
     EnvironmentVariableNotFound(String),
+    #[error("write size error: expected({0}) got({1})")]
+    WriteSizeError(usize, usize),
+    #[error("unknown tag ({1}) for ({0})")]
+    UnknownTag(String, i64),
     #[error("DirectoryEntry size mismatch ({0}) ({1}) ({2})")]
     DirectoryEntrySize(usize, usize, usize),
 }
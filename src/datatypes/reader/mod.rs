@@ -2,7 +2,7 @@ use paste::paste;
 use serde::Serialize;
 use std::collections::HashMap;
 
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 
 use crate::datatypes::common::AsciiString;
 pub use crate::datatypes::common::DataType;
@@ -16,32 +16,133 @@ pub use error::{Error, Result};
 mod environment;
 pub use environment::{Environment, IntoEnvironment};
 
-// DataTypeReader: implements a generic parser for structs
-#[cfg(not(feature = "trace"))]
-pub struct DataTypeReader {
-    pub data: Vec<u8>,
-    pub cursor: Cursor<Vec<u8>>,
-    pub env: HashMap<String, Environment>,
-}
+/// anything DataTypeReader can read from
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
 
-#[cfg(feature = "trace")]
-pub struct DataTypeReader {
-    pub data: Vec<u8>,
-    pub cursor: Cursor<Vec<u8>>,
+// DataTypeReader: implements a generic parser for structs
+// reads from any Read + Seek source, positions are absolute offsets into it
+pub struct DataTypeReader<R: ReadSeek = Cursor<Vec<u8>>> {
+    pub cursor: R,
+    position: u64,
+    // the position of cursor differs from position, seek before the next read
+    seek_pending: bool,
+    len: u64,
+    #[cfg(feature = "trace")]
     pub trace: Option<Trace>,
     pub env: HashMap<String, Environment>,
 }
 
 impl DataTypeReader {
     pub fn new(data: Vec<u8>, #[cfg(feature = "trace")] trace: Option<Trace>) -> Self {
+        let len = data.len() as u64;
+        DataTypeReader::with_len(
+            Cursor::new(data),
+            0,
+            len,
+            #[cfg(feature = "trace")]
+            trace,
+        )
+    }
+}
+
+impl<'a> DataTypeReader<Cursor<&'a [u8]>> {
+    /// reads from borrowed data, e.g. a memory mapped file
+    pub fn from_slice(data: &'a [u8], #[cfg(feature = "trace")] trace: Option<Trace>) -> Self {
+        DataTypeReader::with_len(
+            Cursor::new(data),
+            0,
+            data.len() as u64,
+            #[cfg(feature = "trace")]
+            trace,
+        )
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl DataTypeReader<Cursor<memmap2::Mmap>> {
+    /// maps the file into memory and reads from the mapping
+    ///
+    /// # Safety
+    /// the file must not be modified while it is mapped, see [`memmap2::Mmap`]
+    pub unsafe fn from_file_mmap(
+        file: &std::fs::File,
+        #[cfg(feature = "trace")] trace: Option<Trace>,
+    ) -> Result<Self> {
+        let map = memmap2::Mmap::map(file)?;
+        let len = map.len() as u64;
+        Ok(DataTypeReader::with_len(
+            Cursor::new(map),
+            0,
+            len,
+            #[cfg(feature = "trace")]
+            trace,
+        ))
+    }
+}
+
+impl<R: ReadSeek> DataTypeReader<R> {
+    /// reads from the current position of reader on, e.g. a BufReader<File>
+    pub fn from_reader(mut reader: R, #[cfg(feature = "trace")] trace: Option<Trace>) -> Result<Self> {
+        let position = reader.stream_position()?;
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(position))?;
+        Ok(DataTypeReader::with_len(
+            reader,
+            position,
+            len,
+            #[cfg(feature = "trace")]
+            trace,
+        ))
+    }
+
+    fn with_len(
+        cursor: R,
+        position: u64,
+        len: u64,
+        #[cfg(feature = "trace")] trace: Option<Trace>,
+    ) -> Self {
         DataTypeReader {
-            data: data.clone(),
-            cursor: Cursor::new(data),
+            cursor,
+            position,
+            seek_pending: false,
+            len,
             #[cfg(feature = "trace")]
-            trace: trace.clone(),
+            trace,
             env: HashMap::new(),
         }
     }
+
+    /// size of the source
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn into_inner(self) -> R {
+        self.cursor
+    }
+
+    /// fills buf from the current position
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<()> {
+        let size = buf.len() as u64;
+        if self.position + size > self.len {
+            return Err(Error::ReadSizeError(self.position, self.len, size));
+        }
+        if self.seek_pending {
+            self.cursor.seek(SeekFrom::Start(self.position))?;
+            self.seek_pending = false;
+        }
+        if self.cursor.read_exact(buf).is_err() {
+            return Err(Error::ReadError);
+        }
+        self.position += size;
+        Ok(())
+    }
+
     pub fn read_exact(&mut self, buf: &mut Vec<u8>) -> Result<()> {
         let n = buf.capacity();
         trace_start!(self, format!("Vec<u8>[{}]", n));
@@ -95,11 +196,15 @@ impl DataTypeReader {
     }
 
     pub fn position(&self) -> u64 {
-        self.cursor.position()
+        self.position
     }
 
+    /// the seek happens on the next read
     pub fn set_position(&mut self, pos: u64) {
-        self.cursor.set_position(pos);
+        if pos != self.position {
+            self.position = pos;
+            self.seek_pending = true;
+        }
     }
 
     pub fn read_data_from_directory_entry(
@@ -122,9 +227,7 @@ impl DataTypeReader {
         trace_stop!(self, DataType::GENERICVECTOR(n));
         Ok(())
     }
-}
 
-impl DataTypeReader {
     pub fn read_exact_generic<T: DataTypeRead>(&mut self, buf: &mut Vec<T>) -> Result<()> {
         let n = buf.capacity();
         trace_start!(self, format!("Vec<generic>[{}]", n));
@@ -138,7 +241,7 @@ impl DataTypeReader {
 }
 
 pub trait DataTypeRead: Sized {
-    fn read(_datatypereader: &mut DataTypeReader) -> Result<Self> {
+    fn read<R: ReadSeek>(_datatypereader: &mut DataTypeReader<R>) -> Result<Self> {
         // @TODO: not sure if we should panic here
         Err(Error::NotImplemented)
         // compile_error!("you need to implement the read function");
@@ -146,7 +249,11 @@ pub trait DataTypeRead: Sized {
     fn to_datatype(&self) -> DataType {
         DataType::None
     }
-    fn environment(&self, datatypereader: &mut DataTypeReader, name: impl Into<String>) {
+    fn environment<R: ReadSeek>(
+        &self,
+        datatypereader: &mut DataTypeReader<R>,
+        name: impl Into<String>,
+    ) {
         // compile_error!("you need to implement the environment function");
     }
 }
@@ -159,7 +266,7 @@ pub trait DataTypeSize: Sized {
 
 // Bound checking trait
 pub trait DataTypeBoundCheck {
-    fn check_bounds(&self, datatypereader: &mut DataTypeReader) -> Result<()>;
+    fn check_bounds<R: ReadSeek>(&self, datatypereader: &mut DataTypeReader<R>) -> Result<()>;
 }
 // datatypereader_generate_to_datatype
 //     ($($ty:ty), *) => {
@@ -209,21 +316,11 @@ macro_rules! datatypereader_generate_base_type {
         }
 
         impl DataTypeRead for $ty {
-            fn  [< read >] (datareader: &mut DataTypeReader,) ->  Result<$ty> {
+            fn  [< read >] <R: ReadSeek>(datareader: &mut DataTypeReader<R>,) ->  Result<$ty> {
                 const TYPE_SIZE:usize = std::mem::size_of::<$ty>();
-                let current_position: u64 = datareader.cursor.position();
                 trace_start!(datareader, stringify!($ty));
-                let len = datareader.cursor.get_ref().len() as u64;
-                if (current_position + TYPE_SIZE as u64) > len {
-                    return Err(Error::ReadSizeError(current_position, len, TYPE_SIZE as u64));
-                }
                 let mut a: [u8; TYPE_SIZE] = [0; TYPE_SIZE];
-                match datareader.cursor.read_exact(&mut a) {
-                    Err(_) => {
-                        return Err(Error::ReadError)
-                    }
-                    Ok(_) => {},
-                };
+                datareader.read_bytes(&mut a)?;
 
                 let v;
                 v = $ty::from_le_bytes(a);
@@ -235,7 +332,7 @@ macro_rules! datatypereader_generate_base_type {
                 DataType::[< $ty:upper >](self.clone())
             }
 
-            fn environment(&self, datatypereader: &mut DataTypeReader, name: impl Into<String>) {
+            fn environment<R: ReadSeek>(&self, datatypereader: &mut DataTypeReader<R>, name: impl Into<String>) {
                 let name = name.into();
                 datatypereader.set_env(name, self.clone());
             }
@@ -280,24 +377,14 @@ macro_rules! datatypereader_generate_sized_dispatch_general {
         #[derive(Serialize, Debug,  Clone, Default)]
         pub struct $typename(pub Vec<$ty>);
         impl DataTypeRead for $typename {
-        fn  [< read >] (datareader: &mut DataTypeReader,
+        fn  [< read >] <R: ReadSeek>(datareader: &mut DataTypeReader<R>,
         ) ->  Result<$typename> {
         const TYPE_SIZE:usize = std::mem::size_of::<$ty>();
-        let current_position: u64 = datareader.cursor.position();
         trace_start!(datareader, stringify!($ty));
-        let len = datareader.cursor.get_ref().len() as u64;
-        if (current_position + TYPE_SIZE as u64 * $size) > len {
-        return Err(Error::ReadSizeError(current_position, len, TYPE_SIZE as u64));
-        }
         let mut ret: Vec<$ty> = vec![];
         for _ in 0..$size {
         let mut a: [u8; TYPE_SIZE] = [0; TYPE_SIZE];
-        match datareader.cursor.read_exact(&mut a) {
-        Err(_) => {
-        return Err(Error::ReadError)
-        }
-        Ok(_) => {},
-        };
+        datareader.read_bytes(&mut a)?;
 
         let v;
         v = $ty::from_le_bytes(a);
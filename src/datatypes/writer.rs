@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+pub use crate::datatypes::reader::{Environment, Error, IntoEnvironment, Result};
+
+// DataTypeWriter: the counterpart to DataTypeReader, writes structs little endian
+#[derive(Debug, Default)]
+pub struct DataTypeWriter {
+    pub data: Vec<u8>,
+    position: usize,
+    pub env: HashMap<String, Environment>,
+}
+
+impl DataTypeWriter {
+    pub fn new() -> Self {
+        DataTypeWriter::default()
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// writes at the current position, the data grows as needed
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let end = self.position + bytes.len();
+        if end > self.data.len() {
+            self.data.resize(end, 0);
+        }
+        self.data[self.position..end].copy_from_slice(bytes);
+        self.position = end;
+    }
+
+    /// writes exactly size elements
+    pub fn write_exact_generic<T: DataTypeWrite>(&mut self, buf: &[T], size: usize) -> Result<()> {
+        if buf.len() != size {
+            return Err(Error::WriteSizeError(size, buf.len()));
+        }
+        for b in buf {
+            b.write(self)?;
+        }
+        Ok(())
+    }
+
+    /// writes the string and pads it with \0 to size bytes
+    pub fn write_exact_generic_string(&mut self, buf: &[u8], size: usize) -> Result<()> {
+        if buf.len() > size {
+            return Err(Error::WriteSizeError(size, buf.len()));
+        }
+        self.write_bytes(buf);
+        self.write_bytes(&vec![0; size - buf.len()]);
+        Ok(())
+    }
+
+    pub fn set_env<T: IntoEnvironment>(&mut self, name: impl Into<String>, value: T) {
+        self.env.insert(name.into(), value.into());
+    }
+
+    pub fn get_env(&mut self, name: impl Into<String>) -> Option<Environment> {
+        self.env.get(&name.into()).cloned()
+    }
+
+    pub fn get_env_error(&mut self, name: impl Into<String>) -> Result<Environment> {
+        let name = name.into();
+        if let Some(v) = self.env.get(&name) {
+            return Ok(v.clone());
+        }
+        Err(Error::EnvironmentVariableNotFound(name))
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position as u64
+    }
+
+    /// moving past the end fills the gap with \0 on the next write
+    pub fn set_position(&mut self, pos: u64) {
+        self.position = pos as usize;
+    }
+}
+
+pub trait DataTypeWrite {
+    fn write(&self, datawriter: &mut DataTypeWriter) -> Result<()>;
+    fn environment(&self, _datawriter: &mut DataTypeWriter, _name: impl Into<String>) {}
+}
+
+macro_rules! datatypewriter_generate_base_type {
+    ($($ty:ty), *) => {
+        $(
+        impl DataTypeWrite for $ty {
+            fn write(&self, datawriter: &mut DataTypeWriter) -> Result<()> {
+                datawriter.write_bytes(&self.to_le_bytes());
+                Ok(())
+            }
+
+            fn environment(&self, datawriter: &mut DataTypeWriter, name: impl Into<String>) {
+                datawriter.set_env(name, *self);
+            }
+        }
+        )*
+    }
+}
+
+// generate write functions for base types
+datatypewriter_generate_base_type!(u8, u16, u32, i8, i16, i32, f32);
@@ -0,0 +1,284 @@
+use crate::protocol::errors::DemParseError;
+#[cfg(feature = "trace")]
+use crate::protocol::message::trace::{MessageTrace, ToTraceValue, TraceOptions, TraceValue};
+use crate::protocol::message::Message;
+use crate::protocol::message::MessageFlags;
+use crate::protocol::message::MessageType;
+use crate::protocol::types::*;
+use serde::Serialize;
+
+#[cfg(feature = "ascii_strings")]
+use crate::utils::ascii_converter::AsciiConverter;
+
+use crate::protocol::message::trace;
+
+/// netquake protocol version
+pub const NQ_PROTOCOL_VERSION: u32 = 15;
+
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub struct DemFrame {
+    pub frame: u32,
+    /// server time of the last svc_time in the block
+    pub time: f64,
+    pub view_angle: AngleVector,
+    pub messages: Vec<ServerMessage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Dem {
+    pub size: usize,
+    pub finished: bool,
+    pub message: Message,
+    pub frame: u32,
+    pub time: f64,
+    /// the forced cd track, -1 if none
+    pub cd_track: i32,
+    #[cfg(feature = "trace")]
+    pub trace_options: TraceOptions,
+}
+
+impl Dem {
+    pub fn new(
+        buffer: Vec<u8>,
+        #[cfg(feature = "ascii_strings")] maybe_ascii_converter: Option<AsciiConverter>,
+        #[cfg(feature = "trace")] trace_options: TraceOptions,
+    ) -> Result<Dem, DemParseError> {
+        let size = buffer.len();
+        let flags = MessageFlags {
+            protocol: NQ_PROTOCOL_VERSION,
+            ..Default::default()
+        };
+
+        // the demo starts with the cd track as text terminated by a newline
+        let newline = match buffer.iter().position(|b| *b == b'\n') {
+            Some(p) => p,
+            None => return Err(DemParseError::CdTrack),
+        };
+        let cd_track = std::str::from_utf8(&buffer[..newline])
+            .ok()
+            .and_then(|s| s.trim().parse::<i32>().ok())
+            .ok_or(DemParseError::CdTrack)?;
+
+        #[allow(unused_mut)]
+        let mut message = Message::new(
+            Box::new(buffer),
+            newline + 1,
+            size,
+            false,
+            flags,
+            #[cfg(feature = "ascii_strings")]
+            maybe_ascii_converter,
+            MessageType::NetQuake,
+            #[cfg(feature = "trace")]
+            MessageTrace::default(),
+        );
+
+        #[cfg(feature = "trace")]
+        {
+            message.trace.enabled = trace_options.enabled;
+        }
+
+        Ok(Dem {
+            size,
+            finished: newline + 1 >= size,
+            message,
+            frame: 0,
+            time: 0.0,
+            cd_track,
+            #[cfg(feature = "trace")]
+            trace_options,
+        })
+    }
+
+    pub fn parse_frame(&mut self) -> Result<DemFrame, DemParseError> {
+        trace::trace_annotate!(self.message, format!("({:.4})({})", self.time, self.frame));
+        trace::trace_start!(self.message, false);
+        trace::trace_annotate!(self.message, "size");
+        let size = self.message.read_i32(false)?.max(0) as usize;
+        let mut view_angle = AngleVector::default();
+        for i in 0..3 {
+            trace::trace_annotate!(self.message, "view_angle");
+            view_angle[i] = self.message.read_f32(false)?;
+        }
+
+        let start = self.message.position;
+        let stop = start + size;
+        if stop > self.message.length {
+            return Err(DemParseError::BlockSize(size, self.size, start));
+        }
+
+        // limit the message to the block so the reader stops at its end
+        let length = self.message.length;
+        self.message.length = stop;
+        let messages = self.read_messages();
+        self.message.length = length;
+        self.message.position = stop;
+        let messages = messages?;
+
+        for m in &messages {
+            if let ServerMessage::NQTime(t) = m {
+                self.time = t.time as f64;
+            }
+        }
+
+        let frame = DemFrame {
+            frame: self.frame,
+            time: self.time,
+            view_angle,
+            messages,
+        };
+        self.frame += 1;
+        if self.message.position >= self.message.length {
+            self.finished = true;
+        }
+        trace::trace_stop!(self.message);
+        Ok(frame)
+    }
+
+    fn read_messages(&mut self) -> Result<Vec<ServerMessage>, DemParseError> {
+        let mut messages = Vec::new();
+        while self.message.position < self.message.length {
+            trace::trace_annotate!(self.message, "message type");
+            let cmd = self.message.read_u8(false)?;
+            let m = if cmd & NQUpdateTypes::SIGNAL.bits() as u8 != 0 {
+                NQEntityUpdate::read(&mut self.message, cmd)?
+            } else {
+                match ServerClient::try_from(cmd) {
+                    Ok(c) => c.read_message(&mut self.message)?,
+                    Err(_) => return Err(DemParseError::UnknownType(cmd)),
+                }
+            };
+            if let ServerMessage::Disconnect(_) = m {
+                self.finished = true;
+            }
+            messages.push(m);
+        }
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dem(data: Vec<u8>) -> Dem {
+        Dem::new(
+            data,
+            #[cfg(feature = "ascii_strings")]
+            None,
+            #[cfg(feature = "trace")]
+            TraceOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn string(s: &str) -> StringByte {
+        let mut m = Message::empty();
+        m.write_stringbyte(s);
+        m.position = 0;
+        m.length = m.buffer.len();
+        m.read_stringbyte(false).unwrap()
+    }
+
+    fn block(data: &mut Vec<u8>, view_angle: [f32; 3], messages: &[ServerMessage]) {
+        let mut m = Message {
+            r#type: MessageType::NetQuake,
+            ..Message::empty()
+        };
+        for message in messages {
+            message.write(&mut m).unwrap();
+        }
+        let m: Vec<u8> = m.into();
+        data.extend_from_slice(&(m.len() as i32).to_le_bytes());
+        for a in view_angle {
+            data.extend_from_slice(&a.to_le_bytes());
+        }
+        data.extend_from_slice(&m);
+    }
+
+    #[test]
+    fn parse_dem() {
+        let mut data = b"-1\n".to_vec();
+        let serverinfo = ServerMessage::NQServerinfo(NQServerinfo {
+            protocol: NQ_PROTOCOL_VERSION,
+            maxclients: 8,
+            gametype: 1,
+            levelname: string("The Abandoned Base"),
+            models: vec![string("maps/e1m1.bsp"), string("progs/player.mdl")],
+            sounds: vec![string("weapons/r_exp3.wav")],
+        });
+        block(
+            &mut data,
+            [0.0; 3],
+            &[
+                ServerMessage::NQPrint(NQPrint {
+                    message: string("hello"),
+                }),
+                serverinfo.clone(),
+            ],
+        );
+        let update = ServerMessage::NQEntityUpdate(NQEntityUpdate {
+            bits: NQUpdateTypes::SIGNAL
+                | NQUpdateTypes::MOREBITS
+                | NQUpdateTypes::LONGENTITY
+                | NQUpdateTypes::FRAME
+                | NQUpdateTypes::ORIGIN1
+                | NQUpdateTypes::ORIGIN3
+                | NQUpdateTypes::ANGLE2,
+            entity_index: 300,
+            frame: Some(4),
+            origin: CoordinateVectorOption {
+                x: Some(16.0),
+                y: None,
+                z: Some(-8.5),
+            },
+            angle: AngleVectorOption {
+                x: None,
+                y: Some(90.0),
+                z: None,
+            },
+            ..Default::default()
+        });
+        block(
+            &mut data,
+            [1.0, 2.0, 3.0],
+            &[
+                ServerMessage::NQTime(NQTime { time: 1.5 }),
+                ServerMessage::NQUpdatestat(NQUpdatestat {
+                    stat: 0,
+                    value: 100_000,
+                }),
+                update.clone(),
+            ],
+        );
+        block(
+            &mut data,
+            [0.0; 3],
+            &[ServerMessage::Disconnect(Disconnect {})],
+        );
+
+        let mut dem = dem(data);
+        assert_eq!(dem.cd_track, -1);
+        let mut frames = vec![];
+        while !dem.finished {
+            frames.push(dem.parse_frame().unwrap());
+        }
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames[0].messages,
+            vec![
+                ServerMessage::NQPrint(NQPrint {
+                    message: string("hello")
+                }),
+                serverinfo
+            ]
+        );
+        assert_eq!(frames[1].time, 1.5);
+        assert_eq!(frames[1].view_angle.z, 3.0);
+        assert_eq!(frames[1].messages[2], update);
+        assert_eq!(
+            frames[2].messages,
+            vec![ServerMessage::Disconnect(Disconnect {})]
+        );
+    }
+}
@@ -0,0 +1,572 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::protocol::errors::FragfileError;
+use crate::protocol::types::{Print, StringByte};
+#[cfg(feature = "ascii_strings")]
+use crate::utils::ascii_converter::AsciiConverter;
+#[cfg(feature = "vfs")]
+use crate::vfs::{Vfs, VfsQueryFile};
+use serde::Serialize;
+
+/// print level of chat messages
+pub const PRINT_CHAT: u8 = 3;
+
+/// the obituaries of the standard qw progs, in the fragfile.dat format
+pub const STANDARD_FRAGFILE: &str = r#"#FRAGFILE VERSION ezquake-1.00
+#FRAGFILE GAMEDIR qw
+#META TITLE "standard qw obituaries"
+
+#DEFINE WEAPON_CLASS 1 axe "Axe"
+#DEFINE WEAPON_CLASS 2 sg "Shotgun"
+#DEFINE WEAPON_CLASS 3 ssg "Super Shotgun"
+#DEFINE WEAPON_CLASS 4 ng "Nailgun"
+#DEFINE WEAPON_CLASS 5 sng "Super Nailgun"
+#DEFINE WEAPON_CLASS 6 gl "Grenade Launcher"
+#DEFINE WEAPON_CLASS 7 rl "Rocket Launcher"
+#DEFINE WEAPON_CLASS 8 lg "Lightning Gun"
+#DEFINE WEAPON_CLASS 9 tele "Telefrag"
+#DEFINE WEAPON_CLASS 10 discharge "Discharge"
+#DEFINE WEAPON_CLASS 11 squish "Squish"
+#DEFINE WEAPON_CLASS 12 stomp "Stomp"
+#DEFINE WEAPON_CLASS 13 water "Water"
+#DEFINE WEAPON_CLASS 14 slime "Slime"
+#DEFINE WEAPON_CLASS 15 lava "Lava"
+#DEFINE WEAPON_CLASS 16 fall "Fall"
+#DEFINE WEAPON_CLASS 17 trap "Trap"
+
+#DEFINE OBITUARY PLAYER_DEATH 13 " sleeps with the fishes"
+#DEFINE OBITUARY PLAYER_DEATH 13 " sucks it down"
+#DEFINE OBITUARY PLAYER_DEATH 14 " gulped a load of slime"
+#DEFINE OBITUARY PLAYER_DEATH 14 " can't exist on slime alone"
+#DEFINE OBITUARY PLAYER_DEATH 15 " burst into flames"
+#DEFINE OBITUARY PLAYER_DEATH 15 " turned into hot slag"
+#DEFINE OBITUARY PLAYER_DEATH 15 " visits the Volcano God"
+#DEFINE OBITUARY PLAYER_DEATH 16 " cratered"
+#DEFINE OBITUARY PLAYER_DEATH 16 " fell to his death"
+#DEFINE OBITUARY PLAYER_DEATH 16 " fell to her death"
+#DEFINE OBITUARY PLAYER_DEATH 17 " blew up"
+#DEFINE OBITUARY PLAYER_DEATH 17 " was spiked"
+#DEFINE OBITUARY PLAYER_DEATH 17 " was zapped"
+#DEFINE OBITUARY PLAYER_DEATH 17 " ate a lavaball"
+#DEFINE OBITUARY PLAYER_DEATH 11 " was squished"
+#DEFINE OBITUARY PLAYER_DEATH " died"
+#DEFINE OBITUARY PLAYER_DEATH " tried to leave"
+
+#DEFINE OBITUARY PLAYER_SUICIDE 6 " tries to put the pin back in"
+#DEFINE OBITUARY PLAYER_SUICIDE 7 " becomes bored with life"
+#DEFINE OBITUARY PLAYER_SUICIDE 7 " discovers blast radius"
+#DEFINE OBITUARY PLAYER_SUICIDE 8 " electrocutes himself"
+#DEFINE OBITUARY PLAYER_SUICIDE 8 " electrocutes herself"
+#DEFINE OBITUARY PLAYER_SUICIDE 8 " railcutes himself"
+#DEFINE OBITUARY PLAYER_SUICIDE 10 " discharges into the slime"
+#DEFINE OBITUARY PLAYER_SUICIDE 10 " discharges into the lava"
+#DEFINE OBITUARY PLAYER_SUICIDE 10 " discharges into the water"
+#DEFINE OBITUARY PLAYER_SUICIDE 10 " heats up the water"
+#DEFINE OBITUARY PLAYER_SUICIDE " suicides"
+
+#DEFINE OBITUARY X_FRAGGED_BY_Y 1 " was ax-murdered by "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 2 " chewed on " "'s boomstick"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 3 " ate 2 loads of " "'s buckshot"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 4 " was nailed by "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 5 " was punctured by "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 5 " was perforated by "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 6 " eats " "'s pineapple"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 6 " was gibbed by " "'s grenade"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 7 " rides " "'s rocket"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 7 " was gibbed by " "'s rocket"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 7 " was brutalized by " "'s quad rocket"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 7 " was smeared by " "'s quad rocket"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 8 " accepts " "'s shaft"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 8 " gets a natural disaster from "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 9 " was telefragged by "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 10 " accepts " "'s discharge"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 10 " drains " "'s batteries"
+#DEFINE OBITUARY X_FRAGGED_BY_Y 12 " was jumped by "
+#DEFINE OBITUARY X_FRAGGED_BY_Y 12 " was crushed by "
+
+#DEFINE OBITUARY X_FRAGS_Y 7 " rips " " a new one"
+#DEFINE OBITUARY X_FRAGS_Y 11 " squishes "
+#DEFINE OBITUARY X_FRAGS_Y 12 " stomps "
+
+#DEFINE OBITUARY X_TEAMKILLS_UNKNOWN " checks his glasses"
+#DEFINE OBITUARY X_TEAMKILLS_UNKNOWN " checks her glasses"
+#DEFINE OBITUARY X_TEAMKILLS_UNKNOWN " loses another friend"
+#DEFINE OBITUARY X_TEAMKILLS_UNKNOWN " gets a frag for the other team"
+#DEFINE OBITUARY X_TEAMKILLS_UNKNOWN 11 " squished a teammate"
+#DEFINE OBITUARY X_TEAMKILLS_UNKNOWN 12 " mows down a teammate"
+
+#DEFINE OBITUARY X_TEAMKILLED_UNKNOWN 9 " was telefragged by his teammate"
+#DEFINE OBITUARY X_TEAMKILLED_UNKNOWN 9 " was telefragged by her teammate"
+#DEFINE OBITUARY X_TEAMKILLED_UNKNOWN 12 " was crushed by his teammate"
+#DEFINE OBITUARY X_TEAMKILLED_UNKNOWN 12 " was jumped by his teammate"
+"#;
+
+/// how the names in an obituary relate, x is the name at the start of the print
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObituaryType {
+    /// "x msg1", killed by the environment
+    PlayerDeath,
+    /// "x msg1"
+    PlayerSuicide,
+    /// "x msg1 y msg2", y killed x
+    XFraggedByY,
+    /// "x msg1 y msg2", x killed y
+    XFragsY,
+    /// "x msg1 y msg2", y killed x who is on the same team
+    XTeamkilledByY,
+    /// "x msg1 y msg2", x killed y who is on the same team
+    XTeamkillsY,
+    /// "x msg1", x killed someone
+    XFragsUnknown,
+    /// "x msg1", x killed someone on the same team
+    XTeamkillsUnknown,
+    /// "x msg1", someone on the same team killed x
+    XTeamkilledUnknown,
+}
+
+impl ObituaryType {
+    fn from_name(name: &str) -> Option<ObituaryType> {
+        let t = match name {
+            "PLAYER_DEATH" => ObituaryType::PlayerDeath,
+            "PLAYER_SUICIDE" => ObituaryType::PlayerSuicide,
+            "X_FRAGGED_BY_Y" => ObituaryType::XFraggedByY,
+            "X_FRAGS_Y" => ObituaryType::XFragsY,
+            "X_TEAMKILLED_BY_Y" => ObituaryType::XTeamkilledByY,
+            "X_TEAMKILLS_Y" => ObituaryType::XTeamkillsY,
+            "X_FRAGS_UNKNOWN" => ObituaryType::XFragsUnknown,
+            "X_TEAMKILLS_UNKNOWN" => ObituaryType::XTeamkillsUnknown,
+            "X_TEAMKILLED_UNKNOWN" => ObituaryType::XTeamkilledUnknown,
+            _ => return None,
+        };
+        Some(t)
+    }
+
+    /// if the print names a second player after msg1
+    pub fn has_y(&self) -> bool {
+        matches!(
+            self,
+            ObituaryType::XFraggedByY
+                | ObituaryType::XFragsY
+                | ObituaryType::XTeamkilledByY
+                | ObituaryType::XTeamkillsY
+        )
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObituaryKind {
+    Kill,
+    Teamkill,
+    Suicide,
+    /// killed by the environment
+    Death,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct WeaponClass {
+    pub short_name: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct ObituaryRule {
+    pub r#type: ObituaryType,
+    pub weapon_class: Option<u32>,
+    pub message1: Vec<u8>,
+    pub message2: Vec<u8>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Obituary {
+    pub kind: ObituaryKind,
+    pub killer: Option<StringByte>,
+    pub victim: Option<StringByte>,
+    /// short name of the weapon class
+    pub weapon: Option<String>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub enum PrintClass {
+    Obituary(Obituary),
+    Chat(StringByte),
+    Other,
+}
+
+/// obituary rules as clients load them from fragfile.dat
+#[derive(Serialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Fragfile {
+    pub weapon_classes: HashMap<u32, WeaponClass>,
+    pub rules: Vec<ObituaryRule>,
+}
+
+// splits a line into words, quoted words keep their spaces
+fn tokens(line: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < line.len() {
+        if line[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if line[i] == b'"' {
+            let stop = match line[i + 1..].iter().position(|c| *c == b'"') {
+                Some(stop) => i + 1 + stop,
+                None => return Err("unterminated quote".to_string()),
+            };
+            tokens.push(line[i + 1..stop].to_vec());
+            i = stop + 1;
+        } else {
+            let stop = line[i..]
+                .iter()
+                .position(|c| c.is_ascii_whitespace())
+                .map_or(line.len(), |stop| i + stop);
+            tokens.push(line[i..stop].to_vec());
+            i = stop;
+        }
+    }
+    Ok(tokens)
+}
+
+fn number(token: &[u8]) -> Option<u32> {
+    std::str::from_utf8(token).ok()?.parse().ok()
+}
+
+#[cfg(feature = "ascii_strings")]
+fn string_byte(bytes: &[u8]) -> StringByte {
+    StringByte::new(bytes, &AsciiConverter::new())
+}
+
+#[cfg(not(feature = "ascii_strings"))]
+fn string_byte(bytes: &[u8]) -> StringByte {
+    StringByte::new(bytes)
+}
+
+impl ObituaryRule {
+    // the names of x and y if text matches the rule
+    fn matches<'a>(
+        &self,
+        text: &'a [u8],
+        is_player: &impl Fn(&[u8]) -> bool,
+    ) -> Option<(&'a [u8], Option<&'a [u8]>)> {
+        let m1 = self.message1.as_slice();
+        if !self.r#type.has_y() {
+            let x = text.strip_suffix(m1)?;
+            return (!x.is_empty() && is_player(x)).then_some((x, None));
+        }
+        let rest = text.strip_suffix(self.message2.as_slice())?;
+        // names can contain the message, try every place it appears
+        (1..rest.len().saturating_sub(m1.len()))
+            .filter(|start| rest[*start..].starts_with(m1))
+            .map(|start| (&rest[..start], &rest[start + m1.len()..]))
+            .find(|(x, y)| is_player(x) && is_player(y))
+            .map(|(x, y)| (x, Some(y)))
+    }
+}
+
+impl Fragfile {
+    pub fn parse(data: &[u8]) -> Result<Fragfile, FragfileError> {
+        let mut fragfile = Fragfile::default();
+        for (line_number, line) in data.split(|c| *c == b'\n').enumerate() {
+            let line_number = line_number + 1;
+            let tokens = tokens(line).map_err(|e| FragfileError::Syntax(line_number, e))?;
+            let tokens: Vec<&[u8]> = tokens.iter().map(|t| t.as_slice()).collect();
+            match tokens.as_slice() {
+                [b"#DEFINE", b"WEAPON_CLASS" | b"WC", id, short_name, name @ ..] => {
+                    let id = number(id).ok_or_else(|| {
+                        FragfileError::Syntax(line_number, "invalid weapon class".to_string())
+                    })?;
+                    let short_name = String::from_utf8_lossy(short_name).to_string();
+                    let name = match name.first() {
+                        Some(name) => String::from_utf8_lossy(name).to_string(),
+                        None => short_name.clone(),
+                    };
+                    fragfile
+                        .weapon_classes
+                        .insert(id, WeaponClass { short_name, name });
+                }
+                [b"#DEFINE", b"OBITUARY" | b"OBIT", t, arguments @ ..] => {
+                    let t = String::from_utf8_lossy(t);
+                    let r#type = ObituaryType::from_name(&t).ok_or_else(|| {
+                        FragfileError::Syntax(line_number, format!("unknown obituary ({})", t))
+                    })?;
+                    let (weapon_class, messages) = match arguments.split_first() {
+                        Some((id, messages)) if number(id).is_some() => (number(id), messages),
+                        _ => (None, arguments),
+                    };
+                    if let Some(id) = weapon_class {
+                        if !fragfile.weapon_classes.contains_key(&id) {
+                            return Err(FragfileError::UnknownWeaponClass(line_number, id));
+                        }
+                    }
+                    let (message1, message2) = match messages {
+                        [m1] => (m1.to_vec(), vec![]),
+                        [m1, m2] => (m1.to_vec(), m2.to_vec()),
+                        _ => {
+                            return Err(FragfileError::Syntax(
+                                line_number,
+                                "obituary needs one or two messages".to_string(),
+                            ))
+                        }
+                    };
+                    if message1.is_empty() {
+                        return Err(FragfileError::Syntax(
+                            line_number,
+                            "empty obituary message".to_string(),
+                        ));
+                    }
+                    fragfile.rules.push(ObituaryRule {
+                        r#type,
+                        weapon_class,
+                        message1,
+                        message2,
+                    });
+                }
+                // flag alerts are of no use to us
+                [b"#DEFINE", b"FLAG_ALERT" | b"FLAG", ..] => {}
+                [b"#DEFINE", ..] => {
+                    return Err(FragfileError::Syntax(
+                        line_number,
+                        "malformed define".to_string(),
+                    ))
+                }
+                // headers, meta data and comments
+                _ => {}
+            }
+        }
+        Ok(fragfile)
+    }
+
+    /// the rules of the standard qw progs
+    pub fn standard() -> Fragfile {
+        Fragfile::parse(STANDARD_FRAGFILE.as_bytes()).unwrap()
+    }
+
+    #[cfg(feature = "vfs")]
+    pub fn load(vfs: &Vfs, path: impl Into<VfsQueryFile>) -> Result<Fragfile, FragfileError> {
+        let data = vfs.read(path, None)?;
+        Fragfile::parse(&data)
+    }
+
+    /// the obituary of text, is_player decides if a part of the text is a name. the most
+    /// specific (longest) matching rule wins, of equally long ones the first.
+    pub fn obituary(&self, text: &[u8], is_player: impl Fn(&[u8]) -> bool) -> Option<Obituary> {
+        let text = text.strip_suffix(b"\n").unwrap_or(text);
+        let (rule, x, y) = self
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                let (x, y) = rule.matches(text, &is_player)?;
+                Some((rule, x, y))
+            })
+            .min_by_key(|(rule, _, _)| Reverse(rule.message1.len() + rule.message2.len()))?;
+
+        let x = Some(string_byte(x));
+        let y = y.map(string_byte);
+        let (kind, killer, victim) = match rule.r#type {
+            ObituaryType::PlayerDeath => (ObituaryKind::Death, None, x),
+            ObituaryType::PlayerSuicide => (ObituaryKind::Suicide, None, x),
+            ObituaryType::XFraggedByY => (ObituaryKind::Kill, y, x),
+            ObituaryType::XFragsY => (ObituaryKind::Kill, x, y),
+            ObituaryType::XTeamkilledByY => (ObituaryKind::Teamkill, y, x),
+            ObituaryType::XTeamkillsY => (ObituaryKind::Teamkill, x, y),
+            ObituaryType::XFragsUnknown => (ObituaryKind::Kill, x, None),
+            ObituaryType::XTeamkillsUnknown => (ObituaryKind::Teamkill, x, None),
+            ObituaryType::XTeamkilledUnknown => (ObituaryKind::Teamkill, None, x),
+        };
+        let weapon = rule
+            .weapon_class
+            .and_then(|id| self.weapon_classes.get(&id))
+            .map(|w| w.short_name.clone());
+        Some(Obituary {
+            kind,
+            killer,
+            victim,
+            weapon,
+        })
+    }
+
+    /// classifies the print without knowing the player names
+    pub fn classify(&self, print: &Print) -> PrintClass {
+        if print.from == PRINT_CHAT {
+            return PrintClass::Chat(print.message.clone());
+        }
+        match self.obituary(&print.message.bytes, |_| true) {
+            Some(obituary) => PrintClass::Obituary(obituary),
+            None => PrintClass::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(from: u8, text: &str) -> Print {
+        Print {
+            from,
+            message: string_byte(text.as_bytes()),
+        }
+    }
+
+    fn names(o: &Obituary) -> (ObituaryKind, Option<&[u8]>, Option<&[u8]>, Option<&str>) {
+        (
+            o.kind,
+            o.killer.as_ref().map(|k| k.bytes.as_slice()),
+            o.victim.as_ref().map(|v| v.bytes.as_slice()),
+            o.weapon.as_deref(),
+        )
+    }
+
+    #[test]
+    fn classify_standard() {
+        let fragfile = Fragfile::standard();
+        let obituary = |text: &str| match fragfile.classify(&print(1, text)) {
+            PrintClass::Obituary(o) => o,
+            c => panic!("{} is {:?}", text, c),
+        };
+
+        let o = obituary("bob was gibbed by alice's rocket\n");
+        assert_eq!(
+            names(&o),
+            (
+                ObituaryKind::Kill,
+                Some(b"alice".as_slice()),
+                Some(b"bob".as_slice()),
+                Some("rl")
+            )
+        );
+        let o = obituary("alice rips bob a new one\n");
+        assert_eq!(
+            names(&o),
+            (
+                ObituaryKind::Kill,
+                Some(b"alice".as_slice()),
+                Some(b"bob".as_slice()),
+                Some("rl")
+            )
+        );
+        let o = obituary("bob discharges into the water\n");
+        assert_eq!(
+            names(&o),
+            (
+                ObituaryKind::Suicide,
+                None,
+                Some(b"bob".as_slice()),
+                Some("discharge")
+            )
+        );
+        let o = obituary("bob sleeps with the fishes\n");
+        assert_eq!(
+            names(&o),
+            (
+                ObituaryKind::Death,
+                None,
+                Some(b"bob".as_slice()),
+                Some("water")
+            )
+        );
+        let o = obituary("bob checks his glasses\n");
+        assert_eq!(
+            names(&o),
+            (ObituaryKind::Teamkill, Some(b"bob".as_slice()), None, None)
+        );
+        // " was telefragged by his teammate" is more specific than " was telefragged by "
+        let o = obituary("bob was telefragged by his teammate\n");
+        assert_eq!(o.kind, ObituaryKind::Teamkill);
+
+        assert_eq!(
+            fragfile.classify(&print(3, "bob: gg\n")),
+            PrintClass::Chat(string_byte(b"bob: gg\n"))
+        );
+        assert_eq!(
+            fragfile.classify(&print(2, "bob entered the game\n")),
+            PrintClass::Other
+        );
+
+        // a name containing the message
+        let players: &[&[u8]] = &[b"x rides y", b"z"];
+        let o = fragfile
+            .obituary(b"x rides y rides z's rocket\n", |name| {
+                players.contains(&name)
+            })
+            .unwrap();
+        assert_eq!(o.killer.unwrap().bytes, b"z");
+        assert_eq!(o.victim.unwrap().bytes, b"x rides y");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            Fragfile::parse(b"#DEFINE OBITUARY X_FRAGS_Y 3 \" frags \""),
+            Err(FragfileError::UnknownWeaponClass(1, 3))
+        ));
+        assert!(matches!(
+            Fragfile::parse(b"\n#DEFINE OBITUARY NOT_A_TYPE \" a\""),
+            Err(FragfileError::Syntax(2, _))
+        ));
+        assert!(matches!(
+            Fragfile::parse(b"#DEFINE OBITUARY PLAYER_DEATH \" a"),
+            Err(FragfileError::Syntax(1, _))
+        ));
+        for line in [
+            "#DEFINE WEAPON_CLASS 1",
+            "#DEFINE WC",
+            "#DEFINE OBITUARY",
+            "#DEFINE OBITUARY PLAYER_DEATH",
+            "#DEFINE NOT_A_DEFINE 1 \" a\"",
+            "#DEFINE",
+        ] {
+            assert!(
+                matches!(
+                    Fragfile::parse(line.as_bytes()),
+                    Err(FragfileError::Syntax(1, _))
+                ),
+                "{}",
+                line
+            );
+        }
+        let fragfile = Fragfile::parse(b"// comment\n#DEFINE FLAG_ALERT X_TOUCHES_FLAG \"x\"\n");
+        assert_eq!(fragfile.unwrap(), Fragfile::default());
+    }
+
+    #[test]
+    fn first_rule_wins() {
+        let fragfile = Fragfile::parse(
+            b"#DEFINE WC 1 a\n#DEFINE WC 2 b\n\
+              #DEFINE OBIT X_FRAGS_Y 1 \" hits \"\n\
+              #DEFINE OBITUARY X_FRAGS_Y 2 \" hits \"\n",
+        )
+        .unwrap();
+        let o = fragfile.obituary(b"x hits y", |_| true).unwrap();
+        assert_eq!(o.weapon.as_deref(), Some("a"));
+    }
+
+    #[cfg(all(feature = "vfs", feature = "pak"))]
+    #[test]
+    fn load_from_vfs() {
+        use crate::pak::PakWriter;
+        use crate::vfs::{VfsInternalNode, VfsMetaData};
+
+        let data =
+            b"#DEFINE WEAPON_CLASS 1 flag \"Flag\"\n#DEFINE OBITUARY X_FRAGS_Y 1 \" caps on \"\n";
+        let pak = crate::create_pak!(("fragfile.dat", data));
+        let pak = crate::pak::Pak::parse(
+            "pak0.pak",
+            pak.write_data().unwrap(),
+            #[cfg(feature = "trace")]
+            None,
+        )
+        .unwrap();
+        let mut vfs = Vfs::new();
+        vfs.insert_node(
+            VfsInternalNode::new_from_pak(pak, VfsMetaData::default()),
+            "/",
+        );
+
+        let fragfile = Fragfile::load(&vfs, "/fragfile.dat").unwrap();
+        let o = fragfile.obituary(b"a caps on b", |_| true).unwrap();
+        assert_eq!(names(&o).3, Some("flag"));
+        assert!(Fragfile::load(&vfs, "/missing.dat").is_err());
+    }
+}
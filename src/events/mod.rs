@@ -0,0 +1,668 @@
+use std::collections::HashMap;
+
+pub mod fragfile;
+pub use fragfile::Fragfile;
+use fragfile::ObituaryKind;
+
+use crate::mvd::{Mvd, MvdFrame};
+use crate::protocol::errors::MvdParseError;
+use crate::protocol::types::*;
+use crate::state::State;
+use serde::Serialize;
+
+/// index of the items bitfield in [`crate::state::Stat`]
+pub const STAT_ITEMS: usize = 15;
+/// print level of obituaries
+pub const PRINT_MEDIUM: u8 = 1;
+/// the furthest a player can be from an item that was picked up to be credited with it
+pub const PICKUP_DISTANCE: f32 = 64.0;
+
+// models of items that are picked up, ammo and health boxes are maps/b_*.bsp
+const ITEM_MODELS: &[&[u8]] = &[
+    b"progs/armor.mdl",
+    b"progs/backpack.mdl",
+    b"progs/g_shot.mdl",
+    b"progs/g_nail.mdl",
+    b"progs/g_nail2.mdl",
+    b"progs/g_rock.mdl",
+    b"progs/g_rock2.mdl",
+    b"progs/g_light.mdl",
+    b"progs/quaddama.mdl",
+    b"progs/invulner.mdl",
+    b"progs/invisibl.mdl",
+    b"progs/suit.mdl",
+];
+
+lazy_static! {
+    // obituaries are matched against these without a fragfile
+    static ref STANDARD_FRAGFILE: Fragfile = Fragfile::standard();
+}
+
+fn is_item(model: &[u8]) -> bool {
+    model.starts_with(b"maps/b_") || ITEM_MODELS.contains(&model)
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Powerup {
+    Quad,
+    Pent,
+    Ring,
+    Suit,
+}
+
+impl Powerup {
+    pub const ALL: [Powerup; 4] = [Powerup::Quad, Powerup::Pent, Powerup::Ring, Powerup::Suit];
+
+    /// the bit in [`STAT_ITEMS`]
+    pub fn item_bit(&self) -> i32 {
+        match self {
+            Powerup::Ring => 1 << 19,
+            Powerup::Pent => 1 << 20,
+            Powerup::Suit => 1 << 21,
+            Powerup::Quad => 1 << 22,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub enum EventKind {
+    /// the serverdata was received, the demo (and with ktx the match) starts
+    MatchStart,
+    /// the server sent the intermission
+    MatchEnd,
+    Frags {
+        player: u16,
+        frags: i16,
+        delta: i16,
+    },
+    /// an obituary, killer is None for suicides and deaths by the environment, victim is None
+    /// for teamkills that do not name the victim
+    Kill {
+        killer: Option<u16>,
+        victim: Option<u16>,
+        /// short name of the weapon class, only known if a [`Fragfile`] rule matched
+        weapon: Option<String>,
+        teamkill: bool,
+        message: StringByte,
+    },
+    Death {
+        player: u16,
+    },
+    Gib {
+        player: u16,
+    },
+    Respawn {
+        player: u16,
+    },
+    /// an item disappeared, player is the closest player if one was close enough
+    Pickup {
+        player: Option<u16>,
+        entity: u16,
+        model: StringByte,
+    },
+    PowerupGained {
+        player: u16,
+        powerup: Powerup,
+    },
+    PowerupLost {
+        player: u16,
+        powerup: Powerup,
+    },
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Event {
+    pub time: f64,
+    pub frame: u32,
+    pub kind: EventKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Item {
+    model: u16,
+    origin: CoordinateVector,
+}
+
+/// keeps what is needed from earlier frames to turn frames into [`Event`]s
+#[derive(Clone, Debug, Default)]
+pub struct EventTracker {
+    frags: HashMap<u16, i16>,
+    flags: HashMap<u16, DfTypes>,
+    items: HashMap<u16, i32>,
+    item_entities: HashMap<u16, Item>,
+    fragfile: Option<Fragfile>,
+}
+
+impl EventTracker {
+    pub fn new() -> EventTracker {
+        EventTracker::default()
+    }
+
+    /// obituaries are matched against the rules of the fragfile instead of the standard ones,
+    /// prints no rule matches are not guessed from the player names in them
+    pub fn with_fragfile(fragfile: Fragfile) -> EventTracker {
+        EventTracker {
+            fragfile: Some(fragfile),
+            ..Default::default()
+        }
+    }
+
+    /// the events of frame, state has to have the frame applied already
+    pub fn process_frame(&mut self, frame: &MvdFrame, state: &State) -> Vec<Event> {
+        let mut kinds = vec![];
+        let mut obituaries = vec![];
+        let mut frag_deltas: HashMap<u16, i16> = HashMap::new();
+        let mut entities_updated = false;
+        for message in &frame.messages {
+            match message {
+                ServerMessage::Serverdata(_) => kinds.push(EventKind::MatchStart),
+                ServerMessage::Intermission(_) => kinds.push(EventKind::MatchEnd),
+                ServerMessage::Print(p) if p.from == PRINT_MEDIUM => obituaries.push(p),
+                ServerMessage::Updatefrags(u) => {
+                    let player = u.player_number as u16;
+                    let previous = self.frags.insert(player, u.frags).unwrap_or(0);
+                    let delta = u.frags - previous;
+                    if delta != 0 {
+                        *frag_deltas.entry(player).or_default() += delta;
+                        kinds.push(EventKind::Frags {
+                            player,
+                            frags: u.frags,
+                            delta,
+                        });
+                    }
+                }
+                ServerMessage::Playerinfo(Playerinfo::PlayerinfoMvdT(p)) => {
+                    let player = p.player_number as u16;
+                    let previous = self
+                        .flags
+                        .insert(player, p.flags)
+                        .unwrap_or(DfTypes::empty());
+                    let dead = p.flags.contains(DfTypes::DEAD);
+                    if dead && !previous.contains(DfTypes::DEAD) {
+                        kinds.push(EventKind::Death { player });
+                    }
+                    if p.flags.contains(DfTypes::GIB) && !previous.contains(DfTypes::GIB) {
+                        kinds.push(EventKind::Gib { player });
+                    }
+                    if !dead && previous.contains(DfTypes::DEAD) {
+                        kinds.push(EventKind::Respawn { player });
+                    }
+                }
+                ServerMessage::Packetentities(_) | ServerMessage::Deltapacketentities(_) => {
+                    entities_updated = true;
+                }
+                _ => {}
+            }
+        }
+
+        for print in obituaries {
+            if let Some(kill) = obituary(print, state, &frag_deltas, self.fragfile.as_ref()) {
+                kinds.push(kill);
+            }
+        }
+        self.powerups(state, &mut kinds);
+        if entities_updated {
+            self.pickups(state, &mut kinds);
+        }
+
+        kinds
+            .into_iter()
+            .map(|kind| Event {
+                time: frame.time,
+                frame: frame.frame,
+                kind,
+            })
+            .collect()
+    }
+
+    fn powerups(&mut self, state: &State, kinds: &mut Vec<EventKind>) {
+        let mut players: Vec<_> = state.players.iter().collect();
+        players.sort_by_key(|(index, _)| **index);
+        for (index, p) in players {
+            let items = p.stats[STAT_ITEMS];
+            let previous = self.items.insert(*index, items).unwrap_or(0);
+            for powerup in Powerup::ALL {
+                let bit = powerup.item_bit();
+                let player = *index;
+                match (previous & bit != 0, items & bit != 0) {
+                    (false, true) => kinds.push(EventKind::PowerupGained { player, powerup }),
+                    (true, false) => kinds.push(EventKind::PowerupLost { player, powerup }),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn pickups(&mut self, state: &State, kinds: &mut Vec<EventKind>) {
+        let mut gone: Vec<_> = self
+            .item_entities
+            .iter()
+            .filter(|(index, item)| match state.entities.get(index) {
+                Some(e) => e.model != item.model,
+                None => true,
+            })
+            .collect();
+        gone.sort_by_key(|(index, _)| **index);
+        for (index, item) in gone {
+            kinds.push(EventKind::Pickup {
+                player: closest_player(state, &item.origin),
+                entity: *index,
+                model: state
+                    .models
+                    .get(item.model as usize)
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+
+        self.item_entities = state
+            .entities
+            .iter()
+            .filter(|(_, e)| match state.models.get(e.model as usize) {
+                Some(model) => is_item(&model.bytes),
+                None => false,
+            })
+            .map(|(index, e)| {
+                let item = Item {
+                    model: e.model,
+                    origin: e.origin,
+                };
+                (*index, item)
+            })
+            .collect();
+    }
+}
+
+fn closest_player(state: &State, origin: &CoordinateVector) -> Option<u16> {
+    let distance = |o: &CoordinateVector| {
+        let (x, y, z) = (o.x - origin.x, o.y - origin.y, o.z - origin.z);
+        (x * x + y * y + z * z).sqrt()
+    };
+    state
+        .players
+        .iter()
+        .map(|(index, p)| (*index, distance(&p.origin)))
+        .filter(|(_, d)| *d <= PICKUP_DISTANCE)
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+        .map(|(index, _)| index)
+}
+
+// the players named in the print, in the order they appear
+fn named_players(text: &[u8], state: &State) -> Vec<u16> {
+    let mut found: Vec<(usize, usize, u16)> = vec![];
+    for (index, p) in &state.players {
+        let name = match p.userinfo.info().get(b"name") {
+            Some(name) => name,
+            None => continue,
+        };
+        if let Some(start) = text.windows(name.len()).position(|w| w == name) {
+            found.push((start, start + name.len(), *index));
+        }
+    }
+    // a name that is part of a longer name found at the same place does not count
+    let spans = found.clone();
+    found.retain(|(start, stop, _)| {
+        !spans
+            .iter()
+            .any(|(s, e, _)| s <= start && stop <= e && (e - s) > (stop - start))
+    });
+    found.sort();
+    found.into_iter().map(|(_, _, index)| index).collect()
+}
+
+fn player_by_name(state: &State, name: &[u8]) -> Option<u16> {
+    state
+        .players
+        .iter()
+        .filter(|(_, p)| p.userinfo.get(b"name").is_some_and(|n| n.bytes == name))
+        .map(|(index, _)| *index)
+        .min()
+}
+
+// obituaries are matched against the fragfile or the standard rules. without a fragfile a
+// print no rule matches is guessed from the players named in it, the one who gained or lost
+// frags in the same frame is the killer. if that does not tell the roles apart there is no kill.
+fn obituary(
+    print: &Print,
+    state: &State,
+    frag_deltas: &HashMap<u16, i16>,
+    fragfile: Option<&Fragfile>,
+) -> Option<EventKind> {
+    let rules = fragfile.unwrap_or(&STANDARD_FRAGFILE);
+    let o = rules.obituary(&print.message.bytes, |name| {
+        player_by_name(state, name).is_some()
+    });
+    if let Some(o) = o {
+        let player = |name: &Option<StringByte>| match name {
+            Some(name) => player_by_name(state, &name.bytes).map(Some),
+            None => Some(None),
+        };
+        return Some(EventKind::Kill {
+            killer: player(&o.killer)?,
+            victim: player(&o.victim)?,
+            weapon: o.weapon,
+            teamkill: o.kind == ObituaryKind::Teamkill,
+            message: print.message.clone(),
+        });
+    }
+    if fragfile.is_some() {
+        return None;
+    }
+
+    let changed = |player: &u16| frag_deltas.get(player).is_some_and(|d| *d != 0);
+    let players = named_players(&print.message.bytes, state);
+    let (killer, victim) = match players.as_slice() {
+        // suicides and deaths by the environment cost a frag
+        [victim] if frag_deltas.get(victim).is_some_and(|d| *d < 0) => (None, *victim),
+        [first, second, ..] if changed(first) && !changed(second) => (Some(*first), *second),
+        [first, second, ..] if changed(second) && !changed(first) => (Some(*second), *first),
+        _ => return None,
+    };
+    let teamkill = killer
+        .and_then(|killer| frag_deltas.get(&killer))
+        .is_some_and(|d| *d < 0);
+    Some(EventKind::Kill {
+        killer,
+        victim: Some(victim),
+        weapon: None,
+        teamkill,
+        message: print.message.clone(),
+    })
+}
+
+/// parses the rest of the demo and returns all its events
+pub fn events(mvd: &mut Mvd, fragfile: Option<Fragfile>) -> Result<Vec<Event>, MvdParseError> {
+    let mut state = State::new();
+    let mut tracker = match fragfile {
+        Some(fragfile) => EventTracker::with_fragfile(fragfile),
+        None => EventTracker::new(),
+    };
+    let mut events = vec![];
+    while !mvd.finished && mvd.message.position < mvd.message.length {
+        let frame = mvd.parse_frame()?;
+        let target = match frame.last.command {
+            DemoCommand::Read => &mvd.last,
+            _ => &frame.last,
+        };
+        state.apply_messages_mvd(&frame.messages, target);
+        events.extend(tracker.process_frame(&frame, &state));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvd::fixtures::{
+        all, frags, modellist, mvd, serverdata, string, target, userinfo, writer,
+    };
+    use crate::mvd::MvdTarget;
+
+    fn print(text: &str) -> ServerMessage {
+        ServerMessage::Print(Print {
+            from: PRINT_MEDIUM,
+            message: string(text),
+        })
+    }
+
+    fn playerinfo(player_number: u8, flags: DfTypes, x: f32) -> ServerMessage {
+        ServerMessage::Playerinfo(Playerinfo::PlayerinfoMvdT(PlayerinfoMvd {
+            player_number,
+            flags: flags | DfTypes::ORIGIN,
+            frame: 0,
+            origin: Some(CoordinateVectorOption {
+                x: Some(x),
+                y: None,
+                z: None,
+            }),
+            angle: None,
+            model: None,
+            skinnum: None,
+            effects: None,
+            weaponframe: None,
+        }))
+    }
+
+    fn item(entity_index: u16, model: Option<u16>, remove: bool) -> Packetentities {
+        Packetentities {
+            entities: vec![Packetentity {
+                entity_index,
+                bits: 0,
+                ftebits: FteDeltaExtension::empty(),
+                remove,
+                model,
+                frame: None,
+                colormap: None,
+                skin: None,
+                effects: None,
+                origin: Some(CoordinateVectorOption {
+                    x: Some(100.0),
+                    y: Some(0.0),
+                    z: Some(0.0),
+                }),
+                angle: None,
+                transparency: None,
+            }],
+        }
+    }
+
+    fn demo() -> Vec<u8> {
+        let mut writer = writer(
+            serverdata(),
+            &[modellist(&[
+                "maps/dm2.bsp",
+                "progs/player.mdl",
+                "progs/armor.mdl",
+            ])],
+        );
+        let mut write = |time: f64, target: MvdTarget, messages: Vec<ServerMessage>| {
+            writer.write_messages(time, &target, &messages).unwrap();
+        };
+        write(
+            0.0,
+            all(),
+            vec![
+                userinfo(0, 1, "\\name\\bob"),
+                userinfo(1, 2, "\\name\\bobby"),
+                playerinfo(0, DfTypes::empty(), 0.0),
+                playerinfo(1, DfTypes::empty(), 500.0),
+                ServerMessage::Packetentities(item(50, Some(3), false)),
+            ],
+        );
+        // bobby kills bob
+        write(
+            1.0,
+            all(),
+            vec![
+                print("bob was gibbed by bobby's rocket\n"),
+                frags(1, 1),
+                playerinfo(0, DfTypes::DEAD | DfTypes::GIB, 0.0),
+            ],
+        );
+        // bob respawns and picks up the armor
+        write(
+            2.0,
+            all(),
+            vec![
+                playerinfo(0, DfTypes::empty(), 90.0),
+                ServerMessage::Deltapacketentities(Deltapacketentities {
+                    from: 0,
+                    entities: item(50, None, true).entities,
+                }),
+            ],
+        );
+        write(
+            3.0,
+            target(DemoCommand::Stats, 1),
+            vec![ServerMessage::Updatestatlong(Updatestatlong {
+                stat: STAT_ITEMS as u8,
+                value: Powerup::Quad.item_bit(),
+            })],
+        );
+        // bobby dies on his own
+        write(
+            4.0,
+            all(),
+            vec![
+                print("bobby discharges into the water\n"),
+                frags(1, 0),
+                playerinfo(1, DfTypes::DEAD, 500.0),
+            ],
+        );
+        write(
+            5.0,
+            all(),
+            vec![ServerMessage::Intermission(Intermission {
+                origin: CoordinateVector::default(),
+                angle: AngleVector::default(),
+            })],
+        );
+        writer.write_end_of_demo(5.5).unwrap();
+        writer.into_data()
+    }
+
+    fn parse(fragfile: Option<Fragfile>) -> Vec<(f64, EventKind)> {
+        events(&mut mvd(demo()), fragfile)
+            .unwrap()
+            .into_iter()
+            .map(|e| ((e.time * 1000.0).round() / 1000.0, e.kind))
+            .collect()
+    }
+
+    #[test]
+    fn demo_events() {
+        let kinds = parse(None);
+        assert_eq!(
+            kinds,
+            vec![
+                (0.0, EventKind::MatchStart),
+                (
+                    1.0,
+                    EventKind::Frags {
+                        player: 1,
+                        frags: 1,
+                        delta: 1
+                    }
+                ),
+                (1.0, EventKind::Death { player: 0 }),
+                (1.0, EventKind::Gib { player: 0 }),
+                (
+                    1.0,
+                    EventKind::Kill {
+                        killer: Some(1),
+                        victim: Some(0),
+                        weapon: Some("rl".to_string()),
+                        teamkill: false,
+                        message: string("bob was gibbed by bobby's rocket\n"),
+                    }
+                ),
+                (2.0, EventKind::Respawn { player: 0 }),
+                (
+                    2.0,
+                    EventKind::Pickup {
+                        player: Some(0),
+                        entity: 50,
+                        model: string("progs/armor.mdl"),
+                    }
+                ),
+                (
+                    3.0,
+                    EventKind::PowerupGained {
+                        player: 1,
+                        powerup: Powerup::Quad
+                    }
+                ),
+                (
+                    4.0,
+                    EventKind::Frags {
+                        player: 1,
+                        frags: 0,
+                        delta: -1
+                    }
+                ),
+                (4.0, EventKind::Death { player: 1 }),
+                (
+                    4.0,
+                    EventKind::Kill {
+                        killer: None,
+                        victim: Some(1),
+                        weapon: Some("discharge".to_string()),
+                        teamkill: false,
+                        message: string("bobby discharges into the water\n"),
+                    }
+                ),
+                (5.0, EventKind::MatchEnd),
+            ]
+        );
+    }
+
+    #[test]
+    fn demo_events_fragfile() {
+        let kills: Vec<(Option<u16>, Option<u16>, Option<String>)> =
+            parse(Some(Fragfile::standard()))
+                .into_iter()
+                .filter_map(|(_, kind)| match kind {
+                    EventKind::Kill {
+                        killer,
+                        victim,
+                        weapon,
+                        ..
+                    } => Some((killer, victim, weapon)),
+                    _ => None,
+                })
+                .collect();
+        assert_eq!(
+            kills,
+            vec![
+                (Some(1), Some(0), Some("rl".to_string())),
+                (None, Some(1), Some("discharge".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn obituary_roles() {
+        let mut state = State::new();
+        state.apply_messages_mvd(
+            &[
+                userinfo(0, 1, "\\name\\bob"),
+                userinfo(1, 2, "\\name\\bobby"),
+            ],
+            &all(),
+        );
+        let kill = |text: &str, deltas: &[(u16, i16)]| {
+            let print = Print {
+                from: PRINT_MEDIUM,
+                message: string(text),
+            };
+            let deltas = deltas.iter().copied().collect();
+            match obituary(&print, &state, &deltas, None) {
+                Some(EventKind::Kill { killer, victim, .. }) => Some((killer, victim)),
+                _ => None,
+            }
+        };
+
+        // standard obituaries know their roles
+        assert_eq!(
+            kill("bobby rips bob a new one\n", &[]),
+            Some((Some(1), Some(0)))
+        );
+        // the teamkilled player is not named
+        assert_eq!(
+            kill("bob checks his glasses\n", &[(0, -1)]),
+            Some((Some(0), None))
+        );
+        // other prints are guessed from the frags
+        assert_eq!(
+            kill("bob pwns bobby\n", &[(0, 1)]),
+            Some((Some(0), Some(1)))
+        );
+        assert_eq!(
+            kill("bob pwns bobby\n", &[(1, 1)]),
+            Some((Some(1), Some(0)))
+        );
+        assert_eq!(kill("bob pwns bobby\n", &[]), None);
+        assert_eq!(kill("bob melts\n", &[(0, -1)]), Some((None, Some(0))));
+        assert_eq!(kill("bob left the game\n", &[]), None);
+    }
+}
@@ -12,10 +12,16 @@ pub mod datatypes;
 #[macro_use]
 pub mod trace;
 
+#[cfg(feature = "dem")]
+pub mod dem;
+#[cfg(feature = "events")]
+pub mod events;
 #[cfg(feature = "mvd")]
 pub mod mvd;
 #[cfg(feature = "network")]
 pub mod network;
+#[cfg(feature = "qwd")]
+pub mod qwd;
 #[cfg(feature = "protocol")]
 pub mod protocol;
 #[cfg(feature = "state")]
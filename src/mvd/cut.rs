@@ -0,0 +1,194 @@
+use crate::mvd::{Mvd, MvdWriter};
+use crate::protocol::errors::MvdCutError;
+use crate::protocol::types::{DemoCommand, ProtocolVersion};
+use crate::state::State;
+
+// demo time is summed up from milliseconds, half of one covers the float error
+const TIME_EPSILON: f64 = 0.0005;
+
+// the demo has no more frames to parse
+fn at_end(mvd: &Mvd) -> bool {
+    mvd.finished || mvd.message.position >= mvd.message.length
+}
+
+/// writes the frames of the [`Mvd`] between `start` and `end` (demo time in seconds) into a new
+/// demo that starts at 0. the state at `start` is written first, so the demo plays on its own.
+pub fn cut(mvd: &mut Mvd, start: f64, end: f64) -> Result<Vec<u8>, MvdCutError> {
+    if !start.is_finite() || !end.is_finite() || start < 0.0 || end < start {
+        return Err(MvdCutError::InvalidRange(start, end));
+    }
+
+    let mut state = State::new();
+    loop {
+        if at_end(mvd) {
+            return Err(MvdCutError::StartBeyondEnd(start, mvd.time));
+        }
+        if mvd.time + mvd.peek_demotime()? >= start - TIME_EPSILON {
+            break;
+        }
+        let frame = mvd.parse_frame()?;
+        let target = match frame.last.command {
+            DemoCommand::Read => &mvd.last,
+            _ => &frame.last,
+        };
+        state.apply_messages_mvd(&frame.messages, target);
+    }
+
+    let mut writer = MvdWriter::new(mvd.message.flags);
+    // nothing to restore if the serverdata is part of the cut
+    if state.serverdata.protocol != ProtocolVersion::None {
+        writer.write_prelude(&state)?;
+        writer.write_state(&state)?;
+    }
+
+    while !at_end(mvd) {
+        if mvd.time + mvd.peek_demotime()? > end + TIME_EPSILON {
+            break;
+        }
+        let mut frame = mvd.parse_frame()?;
+        match frame.last.command {
+            DemoCommand::Set | DemoCommand::Empty => continue,
+            // the frame before it in the cut might have a different target
+            DemoCommand::Read => frame.last = mvd.last.clone(),
+            _ => {}
+        }
+        frame.time = (frame.time - start).max(0.0);
+        writer.write_frame(&frame)?;
+    }
+    writer.write_end_of_demo(writer.time())?;
+    Ok(writer.into_data())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvd::fixtures::{all, frags, modellist, mvd, serverdata, target, userinfo, writer};
+    use crate::protocol::types::*;
+
+    // two players, player 1 gains a frag and the entity moves every 100ms
+    fn demo() -> Vec<u8> {
+        let mut writer = writer(
+            serverdata(),
+            &[
+                modellist(&["maps/dm2.bsp", "progs/player.mdl"]),
+                ServerMessage::Spawnbaseline(Spawnbaseline {
+                    index: 40,
+                    model_index: 2,
+                    ..Default::default()
+                }),
+            ],
+        );
+        writer
+            .write_messages(
+                0.0,
+                &all(),
+                &[
+                    userinfo(1, 11, "\\name\\a\\team\\red"),
+                    userinfo(2, 12, "\\name\\b\\team\\blue"),
+                ],
+            )
+            .unwrap();
+        for i in 1..=80 {
+            let time = i as f64 * 0.1;
+            writer
+                .write_messages(
+                    time,
+                    &all(),
+                    &[
+                        frags(1, i),
+                        ServerMessage::Packetentities(Packetentities {
+                            entities: vec![Packetentity {
+                                entity_index: 40,
+                                bits: 0,
+                                ftebits: FteDeltaExtension::empty(),
+                                remove: false,
+                                model: None,
+                                frame: Some(i as u8),
+                                colormap: None,
+                                skin: None,
+                                effects: None,
+                                origin: Some(CoordinateVectorOption {
+                                    x: Some(i as f32 * 8.0),
+                                    y: None,
+                                    z: None,
+                                }),
+                                angle: None,
+                                transparency: None,
+                            }],
+                        }),
+                    ],
+                )
+                .unwrap();
+            writer
+                .write_messages(
+                    time,
+                    &target(DemoCommand::Stats, 2),
+                    &[ServerMessage::Updatestatlong(Updatestatlong {
+                        stat: 0,
+                        value: 100 + i as i32,
+                    })],
+                )
+                .unwrap();
+        }
+        writer.write_end_of_demo(8.5).unwrap();
+        writer.into_data()
+    }
+
+    fn state_at(data: Vec<u8>, time: f64) -> State {
+        let mut demo = mvd(data);
+        let mut state = State::new();
+        while !demo.finished && demo.time + demo.peek_demotime().unwrap() <= time + TIME_EPSILON {
+            let frame = demo.parse_frame().unwrap();
+            let target = match frame.last.command {
+                DemoCommand::Read => demo.last.clone(),
+                _ => frame.last.clone(),
+            };
+            state.apply_messages_mvd(&frame.messages, &target);
+        }
+        state
+    }
+
+    #[test]
+    fn cut_range() {
+        let data = demo();
+        let cut_data = cut(&mut mvd(data.clone()), 3.0, 5.0).unwrap();
+
+        let mut demo = mvd(cut_data.clone());
+        let mut last_time = 0.0;
+        while !demo.finished {
+            let frame = demo.parse_frame().unwrap();
+            last_time = frame.time;
+        }
+        assert!(
+            (last_time - 2.0).abs() < 0.0001,
+            "last frame at {}",
+            last_time
+        );
+
+        let expected = state_at(data.clone(), 5.0);
+        let state = state_at(cut_data, 2.0);
+        assert_eq!(state.models.len(), expected.models.len());
+        assert_eq!(state.baseline_entities.len(), 1);
+        for index in [1, 2] {
+            let (p, e) = (&state.players[&index], &expected.players[&index]);
+            assert_eq!(p.uid, e.uid);
+            assert_eq!(p.frags, e.frags);
+            assert_eq!(p.stats, e.stats);
+            assert_eq!(p.userinfo.info(), e.userinfo.info());
+        }
+        assert_eq!(state.players[&1].frags, 50);
+        let (entity, expected) = (&state.entities[&40], &expected.entities[&40]);
+        assert_eq!(entity.origin, expected.origin);
+        assert_eq!(entity.frame, expected.frame);
+        assert_eq!(entity.model, expected.model);
+
+        assert!(matches!(
+            cut(&mut mvd(data.clone()), 5.0, 3.0),
+            Err(MvdCutError::InvalidRange(_, _))
+        ));
+        assert!(matches!(
+            cut(&mut mvd(data.clone()), 20.0, 30.0),
+            Err(MvdCutError::StartBeyondEnd(_, _))
+        ));
+    }
+}
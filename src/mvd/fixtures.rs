@@ -0,0 +1,96 @@
+//! demo building helpers shared by the mvd tests
+
+#[cfg(feature = "trace")]
+use crate::protocol::message::trace::TraceOptions;
+use crate::protocol::types::*;
+#[cfg(feature = "ascii_strings")]
+use crate::utils::ascii_converter::AsciiConverter;
+
+use super::{Mvd, MvdFrame, MvdTarget, MvdWriter};
+
+pub fn mvd(data: Vec<u8>) -> Mvd {
+    Mvd::new(
+        data,
+        #[cfg(feature = "ascii_strings")]
+        None,
+        #[cfg(feature = "trace")]
+        TraceOptions::default(),
+    )
+    .unwrap()
+}
+
+/// parses every frame of the demo
+pub fn parse(data: Vec<u8>) -> Vec<MvdFrame> {
+    let mut mvd = mvd(data);
+    let mut frames = vec![];
+    while !mvd.finished {
+        frames.push(*mvd.parse_frame().unwrap());
+    }
+    frames
+}
+
+/// the string as the parser returns it
+#[cfg(feature = "ascii_strings")]
+pub fn string(s: &str) -> StringByte {
+    StringByte::new(s.as_bytes(), &AsciiConverter::new())
+}
+
+/// the string as the parser returns it
+#[cfg(not(feature = "ascii_strings"))]
+pub fn string(s: &str) -> StringByte {
+    StringByte::new(s.as_bytes())
+}
+
+pub fn target(command: DemoCommand, to: u32) -> MvdTarget {
+    MvdTarget { to, command }
+}
+
+pub fn all() -> MvdTarget {
+    target(DemoCommand::All, 0)
+}
+
+/// serverdata of a demo with hidden packets
+pub fn serverdata() -> Serverdata {
+    Serverdata {
+        protocol: ProtocolVersion::Standard,
+        servercount: 1,
+        gamedir: string("qw"),
+        map: string("dm2"),
+        mvd_protocol_extension: MvdProtocolExtensions::HIDDEN_MESSAGES,
+        ..Default::default()
+    }
+}
+
+pub fn modellist(models: &[&str]) -> ServerMessage {
+    ServerMessage::Modellist(Modellist {
+        start: 0,
+        models: models.iter().map(|m| string(m)).collect(),
+        offset: 0,
+    })
+}
+
+pub fn userinfo(player_number: u8, uid: u32, userinfo: &str) -> ServerMessage {
+    ServerMessage::Updateuserinfo(Updateuserinfo {
+        player_number,
+        uid,
+        userinfo: string(userinfo),
+    })
+}
+
+pub fn frags(player_number: u8, frags: i16) -> ServerMessage {
+    ServerMessage::Updatefrags(Updatefrags {
+        player_number,
+        frags,
+    })
+}
+
+/// a writer that already wrote the serverdata followed by `messages`
+pub fn writer(serverdata: Serverdata, messages: &[ServerMessage]) -> MvdWriter {
+    let mut writer = MvdWriter::default();
+    let mut prelude = vec![ServerMessage::Serverdata(serverdata)];
+    prelude.extend_from_slice(messages);
+    writer
+        .write_messages(0.0, &target(DemoCommand::Read, 0), &prelude)
+        .unwrap();
+    writer
+}
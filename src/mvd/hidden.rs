@@ -0,0 +1,393 @@
+use crate::protocol::errors::MvdParseError;
+use crate::protocol::message::Message;
+use crate::protocol::types::*;
+use num_enum::TryFromPrimitive;
+use serde::Serialize;
+
+/// type ids of the blocks in a hidden packet (a [`DemoCommand::Multiple`] packet sent to no one)
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Serialize, Clone, Copy)]
+#[repr(u16)]
+pub enum MvdHiddenType {
+    AntilagPosition = 0x0000,
+    Usercmd = 0x0001,
+    UsercmdWeapons = 0x0002,
+    Demoinfo = 0x0003,
+    CommentaryTrack = 0x0004,
+    CommentaryData = 0x0005,
+    CommentaryTextSegment = 0x0006,
+    DamageDone = 0x0007,
+    UsercmdWeaponsSs = 0x0008,
+    UsercmdWeaponInstruction = 0x0009,
+    PausedDuration = 0x000a,
+    Extended = 0xffff,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub struct MvdAntilagPosition {
+    pub player_number: u8,
+    pub flags: u8,
+    pub origin: CoordinateVector,
+}
+
+/// the positions the server rewound the other players to for the lag compensation of a player
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub struct MvdAntilagPositions {
+    pub player_number: u8,
+    pub incoming_sequence: u32,
+    pub server_time: f32,
+    pub target_time: f32,
+    pub positions: Vec<MvdAntilagPosition>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub struct MvdHiddenUsercmd {
+    pub player_number: u8,
+    pub drop_number: u8,
+    pub msec: u8,
+    pub angle: AngleVector,
+    pub forward: i16,
+    pub side: i16,
+    pub up: i16,
+    pub buttons: u8,
+    pub impulse: u8,
+}
+
+/// the weapon switch decision of the server for a player
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub struct MvdWeaponSwitch {
+    pub player_number: u8,
+    pub items: i32,
+    pub shells: u8,
+    pub nails: u8,
+    pub rockets: u8,
+    pub cells: u8,
+    pub choice: u8,
+    pub weapon_list: StringByte,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug, PartialOrd)]
+pub struct MvdDamageDone {
+    pub death_type: u16,
+    pub attacker: u16,
+    pub target: u16,
+    pub damage: u16,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub enum MvdHiddenMessage {
+    AntilagPositions(MvdAntilagPositions),
+    Usercmd(MvdHiddenUsercmd),
+    WeaponSwitch(MvdWeaponSwitch),
+    DamageDone(MvdDamageDone),
+    /// blocks we do not decode, kept as they are
+    Unknown {
+        type_id: u16,
+        data: Vec<u8>,
+    },
+}
+
+// every block is prefixed with its length (without the prefix) and type
+const HEADER_SIZE: usize = 6;
+
+impl MvdHiddenMessage {
+    pub fn type_id(&self) -> u16 {
+        match self {
+            MvdHiddenMessage::AntilagPositions(_) => MvdHiddenType::AntilagPosition as u16,
+            MvdHiddenMessage::Usercmd(_) => MvdHiddenType::Usercmd as u16,
+            MvdHiddenMessage::WeaponSwitch(_) => MvdHiddenType::UsercmdWeapons as u16,
+            MvdHiddenMessage::DamageDone(_) => MvdHiddenType::DamageDone as u16,
+            MvdHiddenMessage::Unknown { type_id, .. } => *type_id,
+        }
+    }
+
+    /// reads all blocks of a hidden packet of `size` bytes
+    pub fn read_packet(
+        message: &mut Message,
+        size: usize,
+    ) -> Result<Vec<MvdHiddenMessage>, MvdParseError> {
+        let stop = message.position + size;
+        if stop > message.length {
+            return Err(MvdParseError::ReadBeyondSize(
+                message.length,
+                message.position,
+                size,
+            ));
+        }
+
+        let mut messages = vec![];
+        while message.position + HEADER_SIZE <= stop {
+            let length = message.read_u32(false)? as usize;
+            let type_id = message.read_u16(false)?;
+            let start = message.position;
+            let block_stop = start + length;
+            // a malformed block ends the packet, the rest of the demo is still readable
+            if block_stop > stop {
+                break;
+            }
+
+            // limit the message to the block so a broken block cannot read into the next one
+            let message_length = message.length;
+            message.length = block_stop;
+            let m = Self::read_block(message, type_id, length);
+            message.length = message_length;
+            if let Ok(m) = m {
+                messages.push(m);
+            }
+            message.position = block_stop;
+        }
+        message.position = stop;
+        Ok(messages)
+    }
+
+    fn read_block(
+        message: &mut Message,
+        type_id: u16,
+        length: usize,
+    ) -> Result<MvdHiddenMessage, MvdParseError> {
+        let m = match MvdHiddenType::try_from(type_id) {
+            Ok(MvdHiddenType::AntilagPosition) => {
+                let player_number = message.read_u8(false)?;
+                let players = message.read_u8(false)?;
+                let incoming_sequence = message.read_u32(false)?;
+                let server_time = message.read_f32(false)?;
+                let target_time = message.read_f32(false)?;
+                let mut positions = vec![];
+                for _ in 0..players {
+                    let player_number = message.read_u8(false)?;
+                    let flags = message.read_u8(false)?;
+                    let origin = CoordinateVector {
+                        x: message.read_f32(false)?,
+                        y: message.read_f32(false)?,
+                        z: message.read_f32(false)?,
+                    };
+                    positions.push(MvdAntilagPosition {
+                        player_number,
+                        flags,
+                        origin,
+                    });
+                }
+                MvdHiddenMessage::AntilagPositions(MvdAntilagPositions {
+                    player_number,
+                    incoming_sequence,
+                    server_time,
+                    target_time,
+                    positions,
+                })
+            }
+            Ok(MvdHiddenType::Usercmd) => MvdHiddenMessage::Usercmd(MvdHiddenUsercmd {
+                player_number: message.read_u8(false)?,
+                drop_number: message.read_u8(false)?,
+                msec: message.read_u8(false)?,
+                angle: AngleVector {
+                    x: message.read_f32(false)?,
+                    y: message.read_f32(false)?,
+                    z: message.read_f32(false)?,
+                },
+                forward: message.read_i16(false)?,
+                side: message.read_i16(false)?,
+                up: message.read_i16(false)?,
+                buttons: message.read_u8(false)?,
+                impulse: message.read_u8(false)?,
+            }),
+            Ok(MvdHiddenType::UsercmdWeapons) => MvdHiddenMessage::WeaponSwitch(MvdWeaponSwitch {
+                player_number: message.read_u8(false)?,
+                items: message.read_i32(false)?,
+                shells: message.read_u8(false)?,
+                nails: message.read_u8(false)?,
+                rockets: message.read_u8(false)?,
+                cells: message.read_u8(false)?,
+                choice: message.read_u8(false)?,
+                weapon_list: message.read_stringbyte(false)?,
+            }),
+            Ok(MvdHiddenType::DamageDone) => MvdHiddenMessage::DamageDone(MvdDamageDone {
+                death_type: message.read_u16(false)?,
+                attacker: message.read_u16(false)?,
+                target: message.read_u16(false)?,
+                damage: message.read_u16(false)?,
+            }),
+            _ => {
+                let start = message.position;
+                MvdHiddenMessage::Unknown {
+                    type_id,
+                    data: message.buffer[start..start + length].to_vec(),
+                }
+            }
+        };
+        Ok(m)
+    }
+
+    /// writes the block including its length and type prefix
+    pub fn write(&self, message: &mut Message) -> usize {
+        let mut block = Message::empty();
+        match self {
+            MvdHiddenMessage::AntilagPositions(a) => {
+                block.write_u8(a.player_number);
+                block.write_u8(a.positions.len() as u8);
+                block.write_u32(a.incoming_sequence);
+                block.write_f32(a.server_time);
+                block.write_f32(a.target_time);
+                for p in &a.positions {
+                    block.write_u8(p.player_number);
+                    block.write_u8(p.flags);
+                    block.write_f32(p.origin.x);
+                    block.write_f32(p.origin.y);
+                    block.write_f32(p.origin.z);
+                }
+            }
+            MvdHiddenMessage::Usercmd(u) => {
+                block.write_u8(u.player_number);
+                block.write_u8(u.drop_number);
+                block.write_u8(u.msec);
+                block.write_f32(u.angle.x);
+                block.write_f32(u.angle.y);
+                block.write_f32(u.angle.z);
+                block.write_i16(u.forward);
+                block.write_i16(u.side);
+                block.write_i16(u.up);
+                block.write_u8(u.buttons);
+                block.write_u8(u.impulse);
+            }
+            MvdHiddenMessage::WeaponSwitch(w) => {
+                block.write_u8(w.player_number);
+                block.write_i32(w.items);
+                block.write_u8(w.shells);
+                block.write_u8(w.nails);
+                block.write_u8(w.rockets);
+                block.write_u8(w.cells);
+                block.write_u8(w.choice);
+                block.write_stringbyte(&w.weapon_list);
+            }
+            MvdHiddenMessage::DamageDone(d) => {
+                block.write_u16(d.death_type);
+                block.write_u16(d.attacker);
+                block.write_u16(d.target);
+                block.write_u16(d.damage);
+            }
+            MvdHiddenMessage::Unknown { data, .. } => {
+                block.buffer.extend_from_slice(data);
+            }
+        }
+        let block: Vec<u8> = block.into();
+        let mut size = message.write_u32(block.len() as u32);
+        size += message.write_u16(self.type_id());
+        message.buffer.extend_from_slice(&block);
+        message.position += block.len();
+        size + block.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write_hidden_packet() {
+        let messages = vec![
+            MvdHiddenMessage::AntilagPositions(MvdAntilagPositions {
+                player_number: 1,
+                incoming_sequence: 1234,
+                server_time: 10.5,
+                target_time: 10.45,
+                positions: vec![MvdAntilagPosition {
+                    player_number: 2,
+                    flags: 1,
+                    origin: CoordinateVector {
+                        x: 1.0,
+                        y: -2.0,
+                        z: 24.125,
+                    },
+                }],
+            }),
+            MvdHiddenMessage::Usercmd(MvdHiddenUsercmd {
+                player_number: 1,
+                drop_number: 0,
+                msec: 13,
+                angle: AngleVector {
+                    x: -10.0,
+                    y: 90.0,
+                    z: 0.0,
+                },
+                forward: 320,
+                side: -320,
+                up: 0,
+                buttons: 1,
+                impulse: 7,
+            }),
+            MvdHiddenMessage::WeaponSwitch(MvdWeaponSwitch {
+                player_number: 1,
+                items: 4097,
+                shells: 25,
+                nails: 0,
+                rockets: 5,
+                cells: 0,
+                choice: 7,
+                weapon_list: StringByte {
+                    bytes: b"78654321".to_vec(),
+                    ..Default::default()
+                },
+            }),
+            MvdHiddenMessage::DamageDone(MvdDamageDone {
+                death_type: 8,
+                attacker: 2,
+                target: 3,
+                damage: 110,
+            }),
+            MvdHiddenMessage::Unknown {
+                type_id: MvdHiddenType::PausedDuration as u16,
+                data: vec![1, 2, 3],
+            },
+        ];
+
+        let mut packet = Message::empty();
+        let mut size = 0;
+        for m in &messages {
+            size += m.write(&mut packet);
+        }
+        let data: Vec<u8> = packet.into();
+        assert_eq!(size, data.len());
+
+        let mut message = Message {
+            length: data.len(),
+            buffer: data.clone().into(),
+            ..Message::empty()
+        };
+        let mut read = MvdHiddenMessage::read_packet(&mut message, data.len()).unwrap();
+        assert_eq!(message.position, data.len());
+        // the string representation depends on the converter, only compare the bytes
+        if let MvdHiddenMessage::WeaponSwitch(w) = &mut read[2] {
+            w.weapon_list = StringByte {
+                bytes: w.weapon_list.bytes.clone(),
+                ..Default::default()
+            };
+        }
+        assert_eq!(read, messages);
+
+        let mut message = Message {
+            length: data.len() - 1,
+            buffer: data[..data.len() - 1].to_vec().into(),
+            ..Message::empty()
+        };
+        // the truncated last block is skipped
+        let read = MvdHiddenMessage::read_packet(&mut message, data.len() - 1).unwrap();
+        assert_eq!(read.len(), messages.len() - 1);
+        assert_eq!(message.position, data.len() - 1);
+
+        // a block that cannot be decoded is skipped
+        let mut broken = data.clone();
+        // shorten the weapon list so its string runs beyond the block
+        let end: usize = messages[..3]
+            .iter()
+            .map(|m| m.write(&mut Message::empty()))
+            .sum();
+        broken[end - 1] = b'1';
+        let mut message = Message {
+            length: broken.len(),
+            buffer: broken.clone().into(),
+            ..Message::empty()
+        };
+        let read = MvdHiddenMessage::read_packet(&mut message, broken.len()).unwrap();
+        assert_eq!(read.len(), messages.len() - 1);
+        assert_eq!(read[2], messages[3]);
+        assert_eq!(message.position, broken.len());
+    }
+}
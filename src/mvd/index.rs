@@ -0,0 +1,187 @@
+use std::io::{Read, Write};
+
+use crate::mvd::{Mvd, MvdFrameIndex};
+use crate::protocol::errors::MvdIndexError;
+use crate::protocol::message::MessageFlags;
+use crate::protocol::types::ProtocolVersion;
+use crate::state::State;
+use serde::{Deserialize, Serialize};
+
+/// the [`State`] before parsing the frame [`MvdKeyframe::entry`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MvdKeyframe {
+    pub entry: usize,
+    pub flags: MessageFlags,
+    pub state: State,
+}
+
+/// maps demo time to frame offsets, with [`State`] keyframes to start parsing from
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MvdIndex {
+    pub entries: Vec<MvdFrameIndex>,
+    pub keyframes: Vec<MvdKeyframe>,
+}
+
+impl MvdIndex {
+    /// indexes the demo from the current position of the [`Mvd`], a keyframe is stored every
+    /// `keyframe_interval` seconds of demo time. the [`Mvd`] is at the end of the demo afterwards.
+    pub fn build(mvd: &mut Mvd, keyframe_interval: f64) -> Result<MvdIndex, MvdIndexError> {
+        let position = mvd.message.position;
+        let last = mvd.last.clone();
+        let entries = mvd.get_frame_indexes()?;
+        mvd.message.position = position;
+        mvd.last = last;
+
+        let mut state = State::new();
+        let mut keyframes: Vec<MvdKeyframe> = vec![];
+        for (entry, index) in entries.iter().enumerate() {
+            let due = match keyframes.last() {
+                Some(k) => index.time >= entries[k.entry].time + keyframe_interval,
+                None => true,
+            };
+            if due {
+                keyframes.push(MvdKeyframe {
+                    entry,
+                    flags: mvd.message.flags,
+                    state: state.clone(),
+                });
+            }
+            let frame = mvd.parse_frame()?;
+            state.apply_messages_mvd(&frame.messages, &frame.last);
+        }
+        Ok(MvdIndex { entries, keyframes })
+    }
+
+    /// time of the frame at entry, the time before the frame plus its time difference
+    fn frame_time(&self, mvd: &Mvd, entry: usize) -> f64 {
+        let index = &self.entries[entry];
+        index.time + mvd.message.buffer[index.start] as f64 * 0.001
+    }
+
+    /// the index of the first frame starting at or after time, [`MvdFrameIndex::time`] is the
+    /// demo time before the frame
+    pub fn entry_at(&self, time: f64) -> Option<&MvdFrameIndex> {
+        let entry = self.entries.partition_point(|e| e.time < time);
+        self.entries.get(entry)
+    }
+
+    /// positions the [`Mvd`] at the first frame after `time` and returns the [`State`] with
+    /// all frames up to `time` applied
+    pub fn seek(&self, mvd: &mut Mvd, time: f64) -> Result<State, MvdIndexError> {
+        let keyframe = match self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|k| self.entries[k.entry].time <= time)
+            .or(self.keyframes.first())
+        {
+            Some(keyframe) => keyframe,
+            None => return Err(MvdIndexError::Empty),
+        };
+
+        let index = &self.entries[keyframe.entry];
+        mvd.message.position = index.start;
+        mvd.message.flags = keyframe.flags;
+        mvd.time = index.time;
+        mvd.frame = index.frame;
+        mvd.last = index.last.clone();
+        mvd.finished = false;
+        mvd.serverdata_read = keyframe.state.serverdata.protocol != ProtocolVersion::None;
+
+        let mut state = keyframe.state.clone();
+        let mut entry = keyframe.entry;
+        while entry < self.entries.len() && self.frame_time(mvd, entry) <= time {
+            let frame = mvd.parse_frame()?;
+            state.apply_messages_mvd(&frame.messages, &frame.last);
+            entry += 1;
+        }
+        Ok(state)
+    }
+
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), MvdIndexError> {
+        Ok(serde_json::to_writer(writer, self)?)
+    }
+
+    pub fn load<R: Read>(reader: R) -> Result<MvdIndex, MvdIndexError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvd::fixtures::{all, frags, mvd, serverdata, writer};
+    use crate::protocol::types::*;
+
+    // player 1 gains a frag every 100ms
+    fn demo() -> Vec<u8> {
+        let mut writer = writer(serverdata(), &[]);
+        for i in 1..=100 {
+            writer
+                .write_messages(i as f64 * 0.1, &all(), &[frags(1, i)])
+                .unwrap();
+        }
+        writer.write_end_of_demo(10.5).unwrap();
+        writer.into_data()
+    }
+
+    #[test]
+    fn seek() {
+        let data = demo();
+        let mut demo = mvd(data.clone());
+        let index = MvdIndex::build(&mut demo, 2.0).unwrap();
+        assert_eq!(index.keyframes.len(), 6);
+
+        let mut saved = vec![];
+        index.save(&mut saved).unwrap();
+        let index = MvdIndex::load(saved.as_slice()).unwrap();
+
+        let mut demo = mvd(data);
+        for (time, frags) in [
+            (5.05, 50),
+            (0.0, 0),
+            (9.9, 99),
+            (0.1, 1),
+            (3.04, 30),
+            (20.0, 100),
+        ] {
+            let state = index.seek(&mut demo, time).unwrap();
+            let player = state.players.get(&1).map(|p| p.frags).unwrap_or(0);
+            assert_eq!(player, frags, "frags at {}", time);
+            if frags < 100 {
+                // parsing continues with the next frame
+                let frame = demo.parse_frame().unwrap();
+                assert!(frame.time > time);
+                assert_eq!(
+                    frame.messages,
+                    vec![ServerMessage::Updatefrags(Updatefrags {
+                        player_number: 1,
+                        frags: frags + 1
+                    })]
+                );
+            }
+        }
+
+        let entry = index.entry_at(5.05).unwrap();
+        assert!(entry.time >= 5.05);
+        assert_eq!(entry.frame, 52);
+    }
+
+    #[test]
+    fn entry_at() {
+        let mut demo = mvd(demo());
+        let index = MvdIndex::build(&mut demo, 2.0).unwrap();
+
+        let start = &index.entries[30];
+        assert_eq!(index.entry_at(start.time), Some(start));
+        assert_eq!(index.entry_at(start.time - 0.0001), Some(start));
+        assert_eq!(
+            index.entry_at(start.time + 0.0001),
+            Some(&index.entries[31])
+        );
+        assert_eq!(index.entry_at(-1.0), index.entries.first());
+        let last = index.entries.last().unwrap();
+        assert_eq!(index.entry_at(last.time), Some(last));
+        assert_eq!(index.entry_at(last.time + 0.0001), None);
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+
+use crate::mvd::{Mvd, MvdTarget};
+use crate::protocol::errors::MvdParseError;
+use crate::protocol::types::*;
+use crate::utils::userinfo::{fullserverinfo, Userinfo};
+use serde::{Deserialize, Serialize};
+
+/// a player slot as it was at the end of the demo
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct MvdPlayerInfo {
+    pub player_number: u8,
+    pub uid: u32,
+    pub name: StringByte,
+    pub team: StringByte,
+    pub spectator: bool,
+    pub frags: i16,
+}
+
+/// summary of a demo, see [`MvdInfo::scan`]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct MvdInfo {
+    /// the level name from the [`Serverdata`]
+    pub map: StringByte,
+    pub gamedir: StringByte,
+    pub serverinfo: Vec<(StringByte, StringByte)>,
+    pub players: Vec<MvdPlayerInfo>,
+    /// teams of the players that are not spectating, sorted
+    pub teams: Vec<StringByte>,
+    /// demo time of the last frame in seconds
+    pub duration: f64,
+}
+
+// state collected while scanning
+struct Scan {
+    info: MvdInfo,
+    serverinfo: Userinfo,
+    players: BTreeMap<u8, (u32, Userinfo, i16)>,
+}
+
+impl MvdInfo {
+    /// reads the [`Mvd`] from its current position to the end. stats and hidden packets are
+    /// skipped as a whole, player and entity updates are read past without decoding them and of
+    /// the other messages only the ones needed for the summary are kept.
+    pub fn scan(mvd: &mut Mvd) -> Result<MvdInfo, MvdParseError> {
+        let mut scan = Scan {
+            info: MvdInfo::default(),
+            serverinfo: scan_userinfo(mvd),
+            players: BTreeMap::new(),
+        };
+        while !mvd.finished && mvd.message.position < mvd.message.length {
+            scan.frame(mvd)?;
+        }
+        Ok(scan.into_info(mvd.time))
+    }
+
+    /// the serverinfo value of key
+    pub fn serverinfo_value(&self, key: &[u8]) -> Option<&StringByte> {
+        self.serverinfo
+            .iter()
+            .rev()
+            .find(|(k, _)| k.bytes == key)
+            .map(|(_, v)| v)
+    }
+}
+
+#[cfg(feature = "ascii_strings")]
+fn scan_userinfo(mvd: &Mvd) -> Userinfo {
+    Userinfo::new_with_ascii_converter(mvd.message.ascii_converter.clone())
+}
+
+#[cfg(not(feature = "ascii_strings"))]
+fn scan_userinfo(_: &Mvd) -> Userinfo {
+    Userinfo::new()
+}
+
+impl Scan {
+    // same framing as Mvd::parse_frame, without building a frame
+    fn frame(&mut self, mvd: &mut Mvd) -> Result<(), MvdParseError> {
+        let demo_time = mvd.message.read_u8(false)?;
+        mvd.time += demo_time as f64 * 0.001;
+        mvd.frame += 1;
+
+        let cmd = mvd.message.read_u8(false)?;
+        let command = match DemoCommand::try_from(cmd & 7) {
+            Ok(command) => command,
+            Err(_) => return Err(MvdParseError::UnhandledCommand(cmd & 7)),
+        };
+        match command {
+            DemoCommand::Command => return Err(MvdParseError::QwdCommand),
+            DemoCommand::Set => {
+                // incoming and outgoing sequence
+                mvd.message.read_u32(false)?;
+                mvd.message.read_u32(false)?;
+                return Ok(());
+            }
+            DemoCommand::Multiple => {
+                let to = mvd.message.read_u32(false)?;
+                mvd.last = MvdTarget { to, command };
+            }
+            DemoCommand::Single | DemoCommand::Stats => {
+                let to = (cmd >> 3) as u32;
+                mvd.last = MvdTarget { to, command };
+            }
+            DemoCommand::All => {
+                mvd.last = MvdTarget { to: 0, command };
+            }
+            DemoCommand::Read | DemoCommand::Empty => {}
+        }
+
+        let size = mvd.message.read_u32(false)? as usize;
+        let stop = mvd.message.position + size;
+        if stop > mvd.message.length {
+            return Err(MvdParseError::ReadBeyondSize(
+                mvd.message.length,
+                mvd.message.position,
+                size,
+            ));
+        }
+        let skip = match mvd.last.command {
+            DemoCommand::Stats => true,
+            DemoCommand::Multiple => {
+                mvd.last.to == 0
+                    && mvd
+                        .message
+                        .flags
+                        .mvd_protocol_extension
+                        .contains(MvdProtocolExtensions::HIDDEN_MESSAGES)
+            }
+            _ => false,
+        };
+        if !skip {
+            self.packet(mvd, stop)?;
+        }
+        mvd.message.position = stop;
+        Ok(())
+    }
+
+    fn packet(&mut self, mvd: &mut Mvd, stop: usize) -> Result<(), MvdParseError> {
+        while mvd.message.position < stop {
+            let msg_cmd = mvd.message.read_u8(false)?;
+            if mvd.read_end_of_demo(msg_cmd)? {
+                return Ok(());
+            }
+            let cmd = match ServerClient::try_from(msg_cmd) {
+                Ok(cmd) => cmd,
+                Err(_) => return Err(MvdParseError::UnhandledCommand(msg_cmd)),
+            };
+            match cmd {
+                ServerClient::Serverdata
+                | ServerClient::Serverinfo
+                | ServerClient::Stufftext
+                | ServerClient::Updateuserinfo
+                | ServerClient::Setinfo
+                | ServerClient::Updatefrags => {
+                    let message = cmd.read_message(&mut mvd.message)?;
+                    self.message(mvd, message);
+                }
+                ServerClient::Playerinfo => Playerinfo::skip_demo(&mut mvd.message)?,
+                ServerClient::Packetentities => Packetentities::skip(&mut mvd.message)?,
+                ServerClient::Deltapacketentities => Deltapacketentities::skip(&mut mvd.message)?,
+                // messages have no size, the rest still has to be read to get past them
+                _ => {
+                    cmd.read_message(&mut mvd.message)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn message(&mut self, mvd: &mut Mvd, message: ServerMessage) {
+        match message {
+            ServerMessage::Serverdata(s) => {
+                mvd.serverdata_read = true;
+                mvd.message.flags.fte_protocol_extensions = s.fte_protocol_extension;
+                mvd.message.flags.fte_protocol_extensions_2 = s.fte_protocol_extension_2;
+                mvd.message.flags.mvd_protocol_extension = s.mvd_protocol_extension;
+                self.info.map = s.map;
+                self.info.gamedir = s.gamedir;
+            }
+            ServerMessage::Serverinfo(s) => {
+                self.serverinfo.update_key_value(&s.key, &s.value);
+            }
+            ServerMessage::Stufftext(s) => {
+                if let Some(info) = fullserverinfo(&s.text.bytes) {
+                    let info = StringByte {
+                        bytes: info.to_vec(),
+                        ..Default::default()
+                    };
+                    self.serverinfo.update(&info);
+                }
+            }
+            ServerMessage::Updateuserinfo(u) => {
+                if u.userinfo.bytes.is_empty() {
+                    self.players.remove(&u.player_number);
+                    return;
+                }
+                // the userinfo is sent complete, replace what we had
+                let mut userinfo = scan_userinfo(mvd);
+                userinfo.update(&u.userinfo);
+                let player =
+                    self.players
+                        .entry(u.player_number)
+                        .or_insert((u.uid, Userinfo::new(), 0));
+                player.0 = u.uid;
+                player.1 = userinfo;
+            }
+            ServerMessage::Setinfo(s) => {
+                if let Some(player) = self.players.get_mut(&s.player_number) {
+                    player.1.update_key_value(&s.key, &s.value);
+                }
+            }
+            ServerMessage::Updatefrags(u) => {
+                if let Some(player) = self.players.get_mut(&u.player_number) {
+                    player.2 = u.frags;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn into_info(self, duration: f64) -> MvdInfo {
+        let mut info = self.info;
+        info.duration = duration;
+        info.serverinfo = self.serverinfo.values().to_vec();
+        for (player_number, (uid, userinfo, frags)) in self.players {
+            let value = |key: &[u8]| userinfo.get(key).cloned().unwrap_or_default();
+            let spectator = value(b"*spectator");
+            info.players.push(MvdPlayerInfo {
+                player_number,
+                uid,
+                name: value(b"name"),
+                team: value(b"team"),
+                spectator: !spectator.bytes.is_empty() && spectator.bytes != b"0",
+                frags,
+            });
+        }
+        let mut teams: Vec<StringByte> = info
+            .players
+            .iter()
+            .filter(|p| !p.spectator && !p.team.bytes.is_empty())
+            .map(|p| p.team.clone())
+            .collect();
+        teams.sort_by(|a, b| a.bytes.cmp(&b.bytes));
+        teams.dedup_by(|a, b| a.bytes == b.bytes);
+        info.teams = teams;
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvd::fixtures::{all, frags, mvd, serverdata, string, target, userinfo, writer};
+    use crate::mvd::MvdHiddenMessage;
+
+    fn demo() -> Vec<u8> {
+        let mut writer = writer(
+            Serverdata {
+                map: string("The Abandoned Base"),
+                ..serverdata()
+            },
+            &[ServerMessage::Stufftext(Stufftext {
+                text: string("fullserverinfo \"\\map\\dm2\\deathmatch\\3\"\n"),
+            })],
+        );
+        writer
+            .write_messages(
+                0.0,
+                &all(),
+                &[
+                    userinfo(0, 1, "\\name\\alpha\\team\\red"),
+                    userinfo(1, 2, "\\name\\beta\\team\\blue"),
+                    userinfo(2, 3, "\\name\\gamma\\team\\red"),
+                    userinfo(3, 4, "\\name\\watcher\\*spectator\\1"),
+                    userinfo(4, 5, "\\name\\leaver\\team\\blue"),
+                ],
+            )
+            .unwrap();
+        for i in 1..=20 {
+            let time = i as f64 * 0.5;
+            writer
+                .write_messages(time, &all(), &[frags(0, i), frags(1, i * 2)])
+                .unwrap();
+            // messages after the entities are still read
+            writer
+                .write_messages(
+                    time,
+                    &all(),
+                    &[
+                        ServerMessage::Packetentities(Packetentities { entities: vec![] }),
+                        frags(2, 100),
+                    ],
+                )
+                .unwrap();
+            writer
+                .write_messages(
+                    time,
+                    &target(DemoCommand::Stats, 0),
+                    &[ServerMessage::Updatestatlong(Updatestatlong {
+                        stat: 0,
+                        value: 100,
+                    })],
+                )
+                .unwrap();
+            writer
+                .write_hidden_messages(
+                    time,
+                    &[MvdHiddenMessage::Unknown {
+                        type_id: 0x000a,
+                        data: vec![1, 2, 3, 4],
+                    }],
+                )
+                .unwrap();
+        }
+        writer
+            .write_messages(
+                10.0,
+                &all(),
+                &[
+                    ServerMessage::Playerinfo(Playerinfo::PlayerinfoMvdT(PlayerinfoMvd {
+                        player_number: 0,
+                        flags: DfTypes::empty(),
+                        frame: 0,
+                        origin: None,
+                        angle: None,
+                        model: None,
+                        skinnum: None,
+                        effects: None,
+                        weaponframe: None,
+                    })),
+                    userinfo(4, 5, ""),
+                    ServerMessage::Setinfo(Setinfo {
+                        player_number: 1,
+                        key: string("name"),
+                        value: string("beta2"),
+                    }),
+                    ServerMessage::Serverinfo(Serverinfo {
+                        key: string("status"),
+                        value: string("Standby"),
+                    }),
+                ],
+            )
+            .unwrap();
+        writer.write_end_of_demo(10.25).unwrap();
+        writer.into_data()
+    }
+
+    #[test]
+    fn scan() {
+        let mut demo = mvd(demo());
+        let info = MvdInfo::scan(&mut demo).unwrap();
+        assert!(demo.finished);
+
+        assert_eq!(info.map.bytes, b"The Abandoned Base");
+        assert_eq!(info.gamedir.bytes, b"qw");
+        assert_eq!(info.serverinfo_value(b"map").unwrap().bytes, b"dm2");
+        assert_eq!(info.serverinfo_value(b"deathmatch").unwrap().bytes, b"3");
+        assert_eq!(info.serverinfo_value(b"status").unwrap().bytes, b"Standby");
+        assert!((info.duration - 10.25).abs() < 0.0001);
+
+        let players: Vec<(&[u8], i16, bool)> = info
+            .players
+            .iter()
+            .map(|p| (p.name.bytes.as_slice(), p.frags, p.spectator))
+            .collect();
+        assert_eq!(
+            players,
+            vec![
+                (b"alpha".as_slice(), 20, false),
+                (b"beta2".as_slice(), 40, false),
+                (b"gamma".as_slice(), 100, false),
+                (b"watcher".as_slice(), 0, true),
+            ]
+        );
+        let teams: Vec<&[u8]> = info.teams.iter().map(|t| t.bytes.as_slice()).collect();
+        assert_eq!(teams, vec![b"blue".as_slice(), b"red".as_slice()]);
+
+        let json = serde_json::to_string(&info).unwrap();
+        let loaded: MvdInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, info);
+    }
+}
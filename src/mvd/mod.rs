@@ -4,23 +4,49 @@ use crate::protocol::errors::MvdParseError;
 #[cfg(feature = "trace")]
 use crate::protocol::message::trace::{MessageTrace, ToTraceValue, TraceOptions, TraceValue};
 use crate::protocol::message::Message;
+use crate::protocol::message::MessageBuffer;
 use crate::protocol::message::MessageFlags;
 use crate::protocol::message::MessageType;
 use crate::protocol::types::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "ascii_strings")]
 use crate::utils::ascii_converter::AsciiConverter;
 
 use crate::protocol::message::trace;
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug, PartialOrd, Default)]
+#[cfg(feature = "state")]
+pub mod cut;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod hidden;
+#[cfg(feature = "state")]
+pub mod index;
+pub mod info;
+pub mod stream;
+pub mod writer;
+#[cfg(feature = "state")]
+pub use cut::cut;
+pub use hidden::MvdHiddenMessage;
+#[cfg(feature = "state")]
+pub use index::MvdIndex;
+pub use info::MvdInfo;
+pub use stream::MvdStream;
+pub use writer::MvdWriter;
+
+/// position of a frame in the demo and the parser state needed to parse it on its own
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, PartialOrd, Default)]
 pub struct MvdFrameIndex {
-    start: usize,
-    stop: usize,
+    pub start: usize,
+    pub stop: usize,
+    pub frame: u32,
+    /// demo time before the frame
+    pub time: f64,
+    /// target of the previous frame, [`DemoCommand::Read`] frames reuse it
+    pub last: MvdTarget,
 }
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug, PartialOrd)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, PartialOrd)]
 pub struct MvdTarget {
     pub to: u32,
     pub command: DemoCommand,
@@ -51,6 +77,8 @@ pub struct Mvd {
 #[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
 pub struct MvdFrame {
     pub messages: Vec<ServerMessage>,
+    /// decoded blocks of a hidden packet, a [`DemoCommand::Multiple`] packet sent to no one
+    pub hidden_messages: Vec<MvdHiddenMessage>,
     pub frame: u32,
     pub time: f64,
     pub time_diff: f64,
@@ -61,6 +89,7 @@ impl MvdFrame {
     pub fn empty() -> MvdFrame {
         MvdFrame {
             messages: vec![],
+            hidden_messages: vec![],
             frame: 0,
             time: 0.0,
             time_diff: 0.0,
@@ -93,12 +122,12 @@ impl Mvd {
         #[cfg(feature = "ascii_strings")] maybe_ascii_converter: Option<AsciiConverter>,
         #[cfg(feature = "trace")] trace_options: TraceOptions,
     ) -> Result<Mvd, MvdParseError> {
-        let buffer_heap = Box::new(buffer.clone());
+        let size = buffer.len();
 
         let mut message = Message::new(
-            buffer_heap,
+            Box::new(buffer),
             0,
-            buffer.len(),
+            size,
             false,
             MessageFlags::new_empty(),
             #[cfg(feature = "ascii_strings")]
@@ -114,7 +143,7 @@ impl Mvd {
         }
 
         Ok(Mvd {
-            size: buffer.len(),
+            size,
             message,
             finished: false,
             last: MvdTarget {
@@ -128,54 +157,91 @@ impl Mvd {
         })
     }
 
+    /// parses the rest of the demo with `thread_count` threads, the frames are returned in
+    /// order. frames are parsed on the current thread until the serverdata is read, the
+    /// remaining frames are split at the boundaries found by [`Mvd::get_frame_indexes`].
+    /// the errors of all failed chunks are returned in [`MvdParseError::ChunkErrors`].
     pub fn parse_mutlithreaded(
         &mut self,
         thread_count: usize,
     ) -> Result<Vec<MvdFrame>, MvdParseError> {
         // read frames till we hit the protocol stuff
-        while !self.serverdata_read {
-            let _ = self.parse_frame();
+        let mut frames = vec![];
+        while !self.serverdata_read && !self.finished {
+            frames.push(*self.parse_frame()?);
+        }
+        if self.finished {
+            return Ok(frames);
         }
-        println!("got protocols on frame: {}", self.frame - 1);
-        let rval = vec![];
-        // get the indexes for the rest of the frames
-        let frame_indexes: Vec<_> = self.get_frame_indexes()?;
-        let chunk_size = frame_indexes.len() / thread_count;
-        let chunks: Vec<&[MvdFrameIndex]> = frame_indexes.chunks(chunk_size).collect();
-
-        let mut handles = vec![];
-        for (index, chunk) in chunks.iter().enumerate() {
-            // let c: Vec<MvdFrameIndex> = chunk.to_vec();
-            let mut mtf = MvdThreadData {
-                chunk: chunk.to_vec(),
-                data: self.message.buffer.clone(),
-                message_flags: self.message.flags,
-                index: index as u32,
-            };
-            let handle = thread::spawn(move || -> MvdThreadReturn { mtf.parse_frame_chunk() });
 
-            handles.push(handle);
+        // get the indexes for the rest of the frames
+        let frame_indexes = self.get_frame_indexes()?;
+        if frame_indexes.is_empty() {
+            return Ok(frames);
         }
-        for handle in handles {
-            if let Ok(v) = handle.join() {
-                println!("index: {} frames: {}", v.index, v.frames.len());
+        let chunk_size = frame_indexes.len().div_ceil(thread_count.max(1));
+
+        let message_flags = self.message.flags;
+        let thread_data = |chunk, message_flags| MvdThreadData {
+            chunk,
+            buffer: self.message.buffer.clone(),
+            message_flags,
+            #[cfg(feature = "ascii_strings")]
+            ascii_converter: self.message.ascii_converter.clone(),
+        };
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = frame_indexes
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let mtf = thread_data(chunk, message_flags);
+                    scope.spawn(move || mtf.parse_frame_chunk())
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join()).collect()
+        });
+
+        let mut flags = message_flags;
+        let mut errors = vec![];
+        for (index, (chunk, result)) in frame_indexes.chunks(chunk_size).zip(results).enumerate() {
+            // a serverdata in an earlier chunk changed the protocol flags, parse it again with them
+            let result = if flags == message_flags {
+                result
+            } else {
+                Ok(thread_data(chunk, flags).parse_frame_chunk())
             };
+            match result {
+                Ok(Ok((chunk_frames, chunk_flags))) => {
+                    frames.extend(chunk_frames);
+                    flags = chunk_flags;
+                }
+                Ok(Err(e)) => errors.push(MvdParseError::ChunkError(index, Box::new(e))),
+                Err(_) => errors.push(MvdParseError::ChunkPanic(index)),
+            }
         }
-        Ok(rval)
+        if !errors.is_empty() {
+            return Err(MvdParseError::ChunkErrors(errors));
+        }
+        self.message.flags = flags;
+        self.finished = true;
+        Ok(frames)
     }
 
+    /// indexes all frames from the current position to the end of the demo
     pub fn get_frame_indexes(&mut self) -> Result<Vec<MvdFrameIndex>, MvdParseError> {
         let mut rval: Vec<MvdFrameIndex> = vec![];
-        let start: usize = 0;
-        let stop: usize = 0;
-        let mut i = 0;
+        let mut time = self.time;
+        let mut frame = self.frame;
         while self.message.position < self.message.length {
-            i += 1;
             let mut f = MvdFrameIndex {
                 start: self.message.position,
                 stop: self.message.position,
+                frame,
+                time,
+                last: self.last.clone(),
             };
-            let _demo_time = self.message.read_u8(false)?;
+            frame += 1;
+            let demo_time = self.message.read_u8(false)?;
+            time += demo_time as f64 * 0.001;
             let cmd = self.message.read_u8(false)?;
             let msg_type_try = DemoCommand::try_from(cmd & 7);
             let msg_type = match msg_type_try {
@@ -183,63 +249,43 @@ impl Mvd {
                 Err(_) => return Err(MvdParseError::UnhandledCommand(cmd & 7)),
             };
 
-            if msg_type >= DemoCommand::Multiple && msg_type <= DemoCommand::All {
-                match msg_type {
-                    DemoCommand::Multiple => {
-                        self.last.to = self.message.read_u32(false)?;
-                        self.last.command = msg_type;
-                    }
-                    DemoCommand::Single => {
-                        self.last.to = (cmd >> 3) as u32;
-                        self.last.command = msg_type;
-                    }
-                    DemoCommand::All => {
-                        self.last.to = 0;
-                        self.last.command = msg_type;
-                    }
-                    DemoCommand::Stats => {
-                        self.last.to = (cmd >> 3) as u32;
-                        self.last.command = msg_type;
-                    }
-                    DemoCommand::Command => {}
-                    DemoCommand::Empty => {}
-                    DemoCommand::Set => {
-                        // incoming
-                        let _ = self.message.read_u32(false);
-                        // outgoing
-                        let _ = self.message.read_u32(false);
-                        f.stop = self.message.position;
-                        rval.push(f);
-                        break;
-                    }
-                    DemoCommand::Read => {}
+            match msg_type {
+                DemoCommand::Multiple => {
+                    self.last.to = self.message.read_u32(false)?;
+                    self.last.command = msg_type;
                 }
-            }
-            let _loop_read_packet = true;
-            let mut _p = 0;
-            let message_start = self.message.position;
-            loop {
-                _p += 1;
-                let size = self.message.read_u32(false)? as usize;
-                if size == 0 {
-                    f.stop = self.message.position;
-                    rval.push(f);
-                    break;
+                DemoCommand::Single | DemoCommand::Stats => {
+                    self.last.to = (cmd >> 3) as u32;
+                    self.last.command = msg_type;
                 }
-                self.message.position += size;
-
-                if self.last.command == DemoCommand::Multiple && self.last.to == 0 {
-                    f.stop = self.message.position;
-                    rval.push(f);
-                    break;
+                DemoCommand::All => {
+                    self.last.to = 0;
+                    self.last.command = msg_type;
                 }
-
-                if self.message.position >= message_start + size {
+                DemoCommand::Set => {
+                    // incoming
+                    self.message.read_u32(false)?;
+                    // outgoing
+                    self.message.read_u32(false)?;
                     f.stop = self.message.position;
                     rval.push(f);
-                    break;
+                    continue;
                 }
+                DemoCommand::Command => return Err(MvdParseError::QwdCommand),
+                DemoCommand::Empty | DemoCommand::Read => {}
             }
+
+            let size = self.message.read_u32(false)? as usize;
+            if self.message.position + size > self.message.length {
+                return Err(MvdParseError::ReadBeyondSize(
+                    self.message.length,
+                    self.message.position,
+                    size,
+                ));
+            }
+            self.message.position += size;
+            f.stop = self.message.position;
+            rval.push(f);
         }
         Ok(rval)
     }
@@ -285,10 +331,12 @@ impl Mvd {
                     trace::trace_annotate!(self.message, "last_to");
                     self.last.to = self.message.read_u32(false)?;
                     self.last.command = msg_type;
+                    frame.last.to = self.last.to;
                 }
                 DemoCommand::Single => {
                     self.last.to = (cmd >> 3) as u32;
                     self.last.command = msg_type;
+                    frame.last.to = self.last.to;
                 }
                 DemoCommand::All => {
                     self.last.to = 0;
@@ -297,6 +345,7 @@ impl Mvd {
                 DemoCommand::Stats => {
                     self.last.to = (cmd >> 3) as u32;
                     self.last.command = msg_type;
+                    frame.last.to = self.last.to;
                 }
                 DemoCommand::Command => {}
                 DemoCommand::Empty => {}
@@ -321,19 +370,28 @@ impl Mvd {
         Ok(frame)
     }
 
+    // the demo ends with the text EndOfDemo, its E was read as msg_cmd. sets finished if it is
+    // the end
+    pub(crate) fn read_end_of_demo(&mut self, msg_cmd: u8) -> Result<bool, MvdParseError> {
+        if msg_cmd != b'E' {
+            return Ok(false);
+        }
+        let s = self.message.read_stringbyte(true)?;
+        if s.bytes != b"ndOfDemo" {
+            return Ok(false);
+        }
+        self.finished = true;
+        Ok(true)
+    }
+
     pub fn read_svc(&mut self, frame: &mut Box<MvdFrame>) -> Result<bool, MvdParseError> {
         trace::trace_annotate!(self.message, "message_cmd");
         trace::trace_start!(self.message, false);
         let msg_cmd = self.message.read_u8(false)?;
 
-        // handle EndOfDemo
-        if msg_cmd == 69 {
-            let s = self.message.read_stringbyte(true)?;
-            if String::from_utf8_lossy(&s.bytes) == *"ndOfDemo" {
-                self.finished = true;
-                trace::trace_stop!(self.message);
-                return Ok(false);
-            }
+        if self.read_end_of_demo(msg_cmd)? {
+            trace::trace_stop!(self.message);
+            return Ok(false);
         }
         let cmd = match ServerClient::try_from(msg_cmd) {
             Ok(cmd) => cmd,
@@ -351,6 +409,7 @@ impl Mvd {
                 frame.messages.push(ServerMessage::Serverdata(r.clone()));
                 self.message.flags.fte_protocol_extensions = r.fte_protocol_extension;
                 self.message.flags.fte_protocol_extensions_2 = r.fte_protocol_extension_2;
+                self.message.flags.mvd_protocol_extension = r.mvd_protocol_extension;
             }
             _ => {
                 frame.messages.push(ret);
@@ -368,8 +427,17 @@ impl Mvd {
             return Ok(false);
         }
 
-        if self.last.command == DemoCommand::Multiple && self.last.to == 0 {
-            self.message.position += size;
+        if self.last.command == DemoCommand::Multiple
+            && self.last.to == 0
+            && self
+                .message
+                .flags
+                .mvd_protocol_extension
+                .contains(MvdProtocolExtensions::HIDDEN_MESSAGES)
+        {
+            trace::trace_annotate!(self.message, "hidden");
+            let hidden = MvdHiddenMessage::read_packet(&mut self.message, size)?;
+            frame.hidden_messages.extend(hidden);
             trace::trace_stop!(self.message);
             return Ok(false);
         }
@@ -382,14 +450,9 @@ impl Mvd {
             trace::trace_annotate!(self.message, "message_cmd");
             let msg_cmd = self.message.read_u8(false)?;
 
-            // handle EndOfDemo
-            if msg_cmd == 69 {
-                let s = self.message.read_stringbyte(true)?;
-                if String::from_utf8_lossy(&s.bytes) == *"ndOfDemo" {
-                    self.finished = true;
-                    trace::trace_stop!(self.message);
-                    return Ok(false);
-                }
+            if self.read_end_of_demo(msg_cmd)? {
+                trace::trace_stop!(self.message);
+                return Ok(false);
             }
             let cmd = match ServerClient::try_from(msg_cmd) {
                 Ok(cmd) => cmd,
@@ -408,6 +471,7 @@ impl Mvd {
                     frame.messages.push(ServerMessage::Serverdata(r.clone()));
                     self.message.flags.fte_protocol_extensions = r.fte_protocol_extension;
                     self.message.flags.fte_protocol_extensions_2 = r.fte_protocol_extension_2;
+                    self.message.flags.mvd_protocol_extension = r.mvd_protocol_extension;
                 }
                 _ => {
                     frame.messages.push(ret);
@@ -424,34 +488,193 @@ impl Mvd {
     }
 }
 
-struct MvdThreadData {
-    chunk: Vec<MvdFrameIndex>,
-    data: Box<Vec<u8>>,
+struct MvdThreadData<'a> {
+    chunk: &'a [MvdFrameIndex],
+    buffer: MessageBuffer,
     message_flags: MessageFlags,
-    index: u32,
+    #[cfg(feature = "ascii_strings")]
+    ascii_converter: AsciiConverter,
 }
 
-#[derive(Debug)]
-struct MvdThreadReturn {
-    frames: Vec<MvdFrame>,
-    index: u32,
+impl MvdThreadData<'_> {
+    // the buffer is shared with the other threads, returns the flags after the last frame
+    pub fn parse_frame_chunk(self) -> Result<(Vec<MvdFrame>, MessageFlags), MvdParseError> {
+        let first = match self.chunk.first() {
+            Some(first) => first,
+            None => return Ok((vec![], self.message_flags)),
+        };
+        let size = self.buffer.len();
+        let mut message = Message::new(
+            self.buffer,
+            0,
+            size,
+            false,
+            self.message_flags,
+            #[cfg(feature = "ascii_strings")]
+            Some(self.ascii_converter),
+            MessageType::Mvd,
+            #[cfg(feature = "trace")]
+            MessageTrace::default(),
+        );
+        message.position = first.start;
+        let mut mvd = Mvd {
+            size,
+            message,
+            finished: false,
+            last: first.last.clone(),
+            frame: first.frame,
+            time: first.time,
+            serverdata_read: true,
+            #[cfg(feature = "trace")]
+            trace_options: TraceOptions::default(),
+        };
+
+        let mut frames = Vec::with_capacity(self.chunk.len());
+        for index in self.chunk {
+            mvd.message.position = index.start;
+            frames.push(*mvd.parse_frame()?);
+        }
+        Ok((frames, mvd.message.flags))
+    }
 }
-impl MvdThreadData {
-    pub fn parse_frame_chunk(&mut self) -> MvdThreadReturn {
-        let mut frames: Vec<MvdFrame> = vec![];
-        #[cfg(feature = "trace")]
-        let mut mvd = Mvd::new(*self.data.clone(), None, TraceOptions::default()).unwrap();
-        #[cfg(not(feature = "trace"))]
-        let mut mvd = Mvd::new(*self.data.clone(), None).unwrap();
-        mvd.message.flags = self.message_flags;
-        for frame in self.chunk.clone() {
-            mvd.message.position = frame.start;
-            let f = mvd.parse_frame().unwrap();
-            frames.push(*f);
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{mvd, parse, serverdata, target, writer};
+    use super::hidden::MvdDamageDone;
+    use super::*;
+
+    fn damage(damage: u16) -> MvdHiddenMessage {
+        MvdHiddenMessage::DamageDone(MvdDamageDone {
+            death_type: 1,
+            attacker: 2,
+            target: 3,
+            damage,
+        })
+    }
+
+    fn demo() -> Vec<u8> {
+        let mut writer = writer(serverdata(), &[]);
+        for i in 0..100_u32 {
+            let time = 0.1 + i as f64 * 0.013;
+            let stat = ServerMessage::Updatestat(Updatestat {
+                stat: (i % 32) as u8,
+                value: i as i8,
+            });
+            match i % 5 {
+                0 => writer.write_messages(time, &target(DemoCommand::All, 0), &[stat]),
+                1 => writer.write_messages(time, &target(DemoCommand::Stats, i % 32), &[stat]),
+                // reuses the target of the previous frame
+                2 => writer.write_messages(time, &target(DemoCommand::Read, 0), &[stat]),
+                3 => writer.write_hidden_messages(time, &[damage(i as u16)]),
+                _ => writer.write_messages(time, &target(DemoCommand::Multiple, 6), &[stat]),
+            }
+            .unwrap();
         }
-        MvdThreadReturn {
-            frames,
-            index: self.index,
+        writer.write_end_of_demo(2.0).unwrap();
+        writer.into_data()
+    }
+
+    #[test]
+    fn parse_multithreaded() {
+        let data = demo();
+        let frames = parse(data.clone());
+        for thread_count in [0, 1, 3, 8, 1000] {
+            let parsed = mvd(data.clone()).parse_mutlithreaded(thread_count).unwrap();
+            assert_eq!(parsed, frames);
+        }
+    }
+
+    #[test]
+    fn parse_multiple_without_hidden_messages() {
+        let stat = ServerMessage::Updatestat(Updatestat { stat: 1, value: 2 });
+        let serverdata = Serverdata {
+            mvd_protocol_extension: MvdProtocolExtensions::empty(),
+            ..serverdata()
+        };
+        let mut writer = writer(serverdata, &[]);
+        writer
+            .write_messages(0.1, &target(DemoCommand::Multiple, 0), &[stat.clone()])
+            .unwrap();
+        writer.write_end_of_demo(0.2).unwrap();
+
+        let frames = parse(writer.into_data());
+        let frame = frames.iter().find(|f| f.time == 0.1).unwrap();
+        assert_eq!(frame.messages, vec![stat]);
+        assert!(frame.hidden_messages.is_empty());
+    }
+
+    #[test]
+    fn parse_multithreaded_chunk_errors() {
+        let mut data = demo();
+        let mut indexer = mvd(data.clone());
+        indexer.parse_frame().unwrap();
+        let indexes = indexer.get_frame_indexes().unwrap();
+        // corrupt the message type of the first and the last stat update sent to player 6
+        let to_six: Vec<_> = indexes
+            .iter()
+            .filter(|i| data[i.start + 1] == DemoCommand::Multiple as u8 && data[i.start + 2] == 6)
+            .collect();
+        for index in [to_six[0].start, to_six[to_six.len() - 1].start] {
+            data[index + 10] = 200;
+        }
+        match mvd(data).parse_mutlithreaded(4) {
+            Err(MvdParseError::ChunkErrors(errors)) => {
+                assert_eq!(errors.len(), 2);
+                for (error, chunk) in errors.iter().zip([0, 3]) {
+                    match error {
+                        MvdParseError::ChunkError(index, e) => {
+                            assert_eq!(*index, chunk);
+                            assert!(matches!(**e, MvdParseError::UnhandledCommand(200)));
+                        }
+                        e => panic!("expected a chunk error, got {}", e),
+                    }
+                }
+            }
+            r => panic!(
+                "expected errors in two chunks, got {:?}",
+                r.map(|f| f.len())
+            ),
+        }
+    }
+
+    #[test]
+    fn parse_multithreaded_serverdata() {
+        // the second serverdata enables hidden packets for the later chunks
+        let mut writer = writer(
+            Serverdata {
+                mvd_protocol_extension: MvdProtocolExtensions::empty(),
+                ..serverdata()
+            },
+            &[],
+        );
+        for i in 0..100_u32 {
+            let time = 0.1 + i as f64 * 0.013;
+            match i {
+                0..=9 => writer.write_messages(
+                    time,
+                    &target(DemoCommand::All, 0),
+                    &[ServerMessage::Updatestat(Updatestat {
+                        stat: 1,
+                        value: i as i8,
+                    })],
+                ),
+                10 => writer.write_messages(
+                    time,
+                    &target(DemoCommand::All, 0),
+                    &[ServerMessage::Serverdata(serverdata())],
+                ),
+                _ => writer.write_hidden_messages(time, &[damage(i as u16)]),
+            }
+            .unwrap();
+        }
+        writer.write_end_of_demo(2.0).unwrap();
+        let data = writer.into_data();
+
+        let frames = parse(data.clone());
+        for thread_count in [1, 4, 50] {
+            let parsed = mvd(data.clone()).parse_mutlithreaded(thread_count).unwrap();
+            assert_eq!(parsed, frames);
         }
     }
 }
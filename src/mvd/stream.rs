@@ -0,0 +1,240 @@
+use std::io::{ErrorKind, Read};
+
+use crate::mvd::{Mvd, MvdFrame};
+use crate::protocol::errors::MvdParseError;
+#[cfg(feature = "trace")]
+use crate::protocol::message::trace::TraceOptions;
+use crate::protocol::types::DemoCommand;
+
+#[cfg(feature = "ascii_strings")]
+use crate::utils::ascii_converter::AsciiConverter;
+
+/// bytes requested from the reader at once
+const READ_SIZE: usize = 8192;
+
+#[derive(Debug)]
+pub enum MvdStreamStatus {
+    Frame(Box<MvdFrame>),
+    /// the reader has no more data right now, call again once it has
+    NeedMoreData,
+    /// the end of the demo was parsed
+    Finished,
+}
+
+/// parses an mvd from a [`Read`] frame by frame, only the bytes of the frame currently being
+/// read are kept in memory
+pub struct MvdStream<R: Read> {
+    reader: R,
+    pub mvd: Mvd,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> MvdStream<R> {
+    pub fn new(
+        reader: R,
+        #[cfg(feature = "ascii_strings")] maybe_ascii_converter: Option<AsciiConverter>,
+        #[cfg(feature = "trace")] trace_options: TraceOptions,
+    ) -> Result<MvdStream<R>, MvdParseError> {
+        let mvd = Mvd::new(
+            vec![],
+            #[cfg(feature = "ascii_strings")]
+            maybe_ascii_converter,
+            #[cfg(feature = "trace")]
+            trace_options,
+        )?;
+        Ok(MvdStream {
+            reader,
+            mvd,
+            buffer: vec![],
+        })
+    }
+
+    /// bytes read that are not part of a parsed frame yet
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn parse_frame(&mut self) -> Result<MvdStreamStatus, MvdParseError> {
+        if self.mvd.finished {
+            return Ok(MvdStreamStatus::Finished);
+        }
+        let size = loop {
+            if let Some(size) = frame_size(&self.buffer)? {
+                break size;
+            }
+            if !self.fill()? {
+                return Ok(MvdStreamStatus::NeedMoreData);
+            }
+        };
+
+        // the message only ever holds the frame that is being parsed
+        let message = &mut self.mvd.message;
+        message.buffer.clear();
+        message.buffer.extend(self.buffer.drain(..size));
+        message.position = 0;
+        message.length = size;
+        self.mvd.size += size;
+        let frame = self.mvd.parse_frame()?;
+        Ok(MvdStreamStatus::Frame(frame))
+    }
+
+    // returns false if the reader had nothing to give
+    fn fill(&mut self) -> Result<bool, MvdParseError> {
+        let start = self.buffer.len();
+        self.buffer.resize(start + READ_SIZE, 0);
+        let read = loop {
+            match self.reader.read(&mut self.buffer[start..]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break 0,
+                Err(e) => {
+                    self.buffer.truncate(start);
+                    return Err(e.into());
+                }
+            }
+        };
+        self.buffer.truncate(start + read);
+        Ok(read > 0)
+    }
+}
+
+/// size of the frame at the start of data, None if data does not hold all of it yet
+pub fn frame_size(data: &[u8]) -> Result<Option<usize>, MvdParseError> {
+    let u32_at = |position: usize| -> Option<usize> {
+        let bytes = data.get(position..position + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let cmd = match data.get(1) {
+        Some(cmd) => *cmd,
+        None => return Ok(None),
+    };
+    // time and command
+    let mut size = 2;
+    match DemoCommand::try_from(cmd & 7) {
+        Ok(DemoCommand::Command) => return Err(MvdParseError::QwdCommand),
+        Ok(DemoCommand::Set) => return Ok(Some(size + 8).filter(|s| *s <= data.len())),
+        Ok(DemoCommand::Multiple) => size += 4,
+        Ok(_) => {}
+        Err(_) => return Err(MvdParseError::UnhandledCommand(cmd & 7)),
+    }
+    let packet_size = match u32_at(size) {
+        Some(packet_size) => packet_size,
+        None => return Ok(None),
+    };
+    size += 4 + packet_size;
+    if size > data.len() {
+        return Ok(None);
+    }
+    Ok(Some(size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvd::fixtures::{frags, parse, serverdata, target, writer};
+    use crate::protocol::types::*;
+
+    // hands out at most `step` of the bytes that have been made available
+    struct Trickle {
+        data: Vec<u8>,
+        available: usize,
+        position: usize,
+        step: usize,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let stop = self
+                .available
+                .min(self.position + self.step)
+                .min(self.position + buf.len());
+            let read = stop - self.position;
+            buf[..read].copy_from_slice(&self.data[self.position..stop]);
+            self.position = stop;
+            Ok(read)
+        }
+    }
+
+    fn stream(reader: Trickle) -> MvdStream<Trickle> {
+        MvdStream::new(
+            reader,
+            #[cfg(feature = "ascii_strings")]
+            None,
+            #[cfg(feature = "trace")]
+            TraceOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn demo() -> Vec<u8> {
+        let mut writer = writer(serverdata(), &[]);
+        for i in 0..50 {
+            writer
+                .write_messages(
+                    0.1 + i as f64 * 0.1,
+                    &target(DemoCommand::Multiple, 1 << (i % 3)),
+                    &[frags(1, i)],
+                )
+                .unwrap();
+        }
+        writer.write_end_of_demo(6.0).unwrap();
+        writer.into_data()
+    }
+
+    #[test]
+    fn stream_frames() {
+        let data = demo();
+        let expected = parse(data.clone());
+
+        let available = data.len();
+        let mut stream = stream(Trickle {
+            data,
+            available,
+            position: 0,
+            step: 3,
+        });
+        let mut frames = vec![];
+        loop {
+            match stream.parse_frame().unwrap() {
+                MvdStreamStatus::Frame(f) => frames.push(*f),
+                MvdStreamStatus::NeedMoreData => panic!("all data is available"),
+                MvdStreamStatus::Finished => break,
+            }
+        }
+        assert_eq!(frames, expected);
+        assert_eq!(stream.pending(), 0);
+    }
+
+    #[test]
+    fn stream_needs_more_data() {
+        let data = demo();
+        let size = data.len();
+        let expected = parse(data.clone()).len();
+        let mut stream = stream(Trickle {
+            data,
+            available: 0,
+            position: 0,
+            step: READ_SIZE,
+        });
+        let mut frames = 0;
+        let mut available = 0;
+        loop {
+            match stream.parse_frame().unwrap() {
+                MvdStreamStatus::Frame(_) => frames += 1,
+                MvdStreamStatus::NeedMoreData => {
+                    // the demo is still being written, 7 bytes at a time
+                    assert!(available < size);
+                    available = (available + 7).min(size);
+                    stream.reader.available = available;
+                }
+                MvdStreamStatus::Finished => break,
+            }
+            assert!(stream.pending() < 128);
+        }
+        assert_eq!(frames, expected);
+    }
+}
@@ -0,0 +1,654 @@
+use crate::mvd::{MvdFrame, MvdHiddenMessage, MvdTarget};
+use crate::protocol::errors::MvdWriteError;
+use crate::protocol::message::{Message, MessageFlags, MessageType};
+use crate::protocol::types::*;
+#[cfg(feature = "state")]
+use crate::state::State;
+
+/// maximum size of a single demo packet, same as MAX_MSGLEN in mvdsv
+pub const MAX_PACKET_SIZE: usize = 1450;
+/// strings per sound/modellist message will be split at this size
+const MAX_LIST_SIZE: usize = 1024;
+
+/// writes [`MvdFrame`]s into the mvd format that [`crate::mvd::Mvd`] parses
+#[derive(Debug, Clone)]
+pub struct MvdWriter {
+    pub message: Message,
+    /// demo time written so far in milliseconds
+    time: u64,
+}
+
+impl Default for MvdWriter {
+    fn default() -> Self {
+        MvdWriter::new(MessageFlags::new_empty())
+    }
+}
+
+impl MvdWriter {
+    pub fn new(flags: MessageFlags) -> MvdWriter {
+        let mut message = Message::empty();
+        message.r#type = MessageType::Mvd;
+        message.flags = flags;
+        MvdWriter { message, time: 0 }
+    }
+
+    /// demo time of the last written frame
+    pub fn time(&self) -> f64 {
+        self.time as f64 * 0.001
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.message.buffer
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.message.into()
+    }
+
+    /// writes the frame at [`MvdFrame::time`] to [`MvdFrame::last`], messages that do not fit
+    /// into a single packet are split into multiple frames with the same time. a multiple frame
+    /// to no player is a hidden packet if the serverdata enabled them
+    pub fn write_frame(&mut self, frame: &MvdFrame) -> Result<usize, MvdWriteError> {
+        if frame.last.command == DemoCommand::Multiple
+            && frame.last.to == 0
+            && self
+                .message
+                .flags
+                .mvd_protocol_extension
+                .contains(MvdProtocolExtensions::HIDDEN_MESSAGES)
+        {
+            return self.write_hidden_messages(frame.time, &frame.hidden_messages);
+        }
+        self.write_messages(frame.time, &frame.last, &frame.messages)
+    }
+
+    /// writes the messages into a single hidden packet
+    pub fn write_hidden_messages(
+        &mut self,
+        time: f64,
+        messages: &[MvdHiddenMessage],
+    ) -> Result<usize, MvdWriteError> {
+        let mut packet = Message::empty();
+        for m in messages {
+            m.write(&mut packet);
+        }
+        let target = MvdTarget {
+            to: 0,
+            command: DemoCommand::Multiple,
+        };
+        let mut size = self.write_time(time)?;
+        size += self.write_packet(self.pending_time_diff(time), &target, &packet.buffer)?;
+        self.time = (time * 1000.0).round() as u64;
+        Ok(size)
+    }
+
+    pub fn write_messages(
+        &mut self,
+        time: f64,
+        target: &MvdTarget,
+        messages: &[ServerMessage],
+    ) -> Result<usize, MvdWriteError> {
+        let mut packets = self.packets(messages)?;
+        let mut size = self.write_time(time)?;
+        if packets.is_empty() {
+            packets.push(vec![]);
+        }
+        let mut time_diff = self.pending_time_diff(time);
+        for packet in packets {
+            size += self.write_packet(time_diff, target, &packet)?;
+            time_diff = 0;
+        }
+        self.time = (time * 1000.0).round() as u64;
+        Ok(size)
+    }
+
+    /// writes the serverdata, sound/modellist, static entities/sounds and baselines of the
+    /// [`State`], time of the frames will be the current writer time
+    #[cfg(feature = "state")]
+    pub fn write_prelude(&mut self, state: &State) -> Result<usize, MvdWriteError> {
+        let time = self.time();
+        let target = MvdTarget {
+            to: 0,
+            command: DemoCommand::Read,
+        };
+        let mut messages = vec![ServerMessage::Serverdata(state.serverdata.clone())];
+
+        let sounds: Vec<StringByte> = state.sounds.to_vec();
+        messages.extend(list_messages(
+            &sounds,
+            "sounds",
+            |start, sounds, offset| {
+                ServerMessage::Soundlist(Soundlist {
+                    start,
+                    sounds,
+                    offset,
+                })
+            },
+        )?);
+
+        // State::new keeps a placeholder for model index 0
+        let models: Vec<StringByte> = match state.models.first() {
+            Some(m) if m.bytes == [0] => state.models[1..].to_vec(),
+            _ => state.models.to_vec(),
+        };
+        messages.extend(list_messages(
+            &models,
+            "models",
+            |start, models, offset| {
+                ServerMessage::Modellist(Modellist {
+                    start,
+                    models,
+                    offset,
+                })
+            },
+        )?);
+
+        for s in &state.static_entities {
+            messages.push(ServerMessage::Spawnstatic(*s));
+        }
+        for s in &state.static_sounds {
+            messages.push(ServerMessage::Spawnstaticsound(s.clone()));
+        }
+
+        let mut baselines: Vec<_> = state.baseline_entities.iter().collect();
+        baselines.sort_by_key(|(index, _)| **index);
+        for (index, e) in baselines {
+            let model_index = u8::try_from(e.model)
+                .map_err(|_| MvdWriteError::OutOfRange("baseline model", e.model as usize))?;
+            messages.push(ServerMessage::Spawnbaseline(Spawnbaseline {
+                index: *index,
+                model_index,
+                model_frame: e.frame,
+                colormap: e.colormap,
+                skinnum: e.skinnum,
+                origin: e.origin,
+                angle: e.angle,
+            }));
+        }
+        self.write_messages(time, &target, &messages)
+    }
+
+    /// writes the players, their stats and the entities of the [`State`] as the demo would
+    /// update them, time of the frames will be the current writer time
+    #[cfg(feature = "state")]
+    pub fn write_state(&mut self, state: &State) -> Result<usize, MvdWriteError> {
+        let time = self.time();
+        let all = MvdTarget {
+            to: 0,
+            command: DemoCommand::All,
+        };
+        let mut players: Vec<_> = state.players.iter().collect();
+        players.sort_by_key(|(index, _)| **index);
+
+        let mut messages = vec![];
+        for (index, p) in &players {
+            let player_number = u8::try_from(**index)
+                .map_err(|_| MvdWriteError::OutOfRange("player number", **index as usize))?;
+            messages.push(ServerMessage::Updateuserinfo(Updateuserinfo {
+                player_number,
+                uid: p.uid,
+                userinfo: StringByte {
+                    bytes: p.userinfo.as_bytes(),
+                    ..Default::default()
+                },
+            }));
+            messages.push(ServerMessage::Updatefrags(Updatefrags {
+                player_number,
+                frags: p.frags,
+            }));
+            messages.push(ServerMessage::Updateping(Updateping {
+                player_number,
+                ping: p.ping,
+            }));
+            messages.push(ServerMessage::Updatepl(Updatepl {
+                player_number,
+                pl: p.pl,
+            }));
+            messages.push(ServerMessage::Updateentertime(Updateentertime {
+                player_number,
+                entertime: p.entertime,
+            }));
+            // spectators have no entity in the demo
+            if p.spectator {
+                continue;
+            }
+            let mut flags = DfTypes::empty();
+            flags.set(DfTypes::DEAD, p.dead);
+            flags.set(DfTypes::GIB, p.gib);
+            messages.push(ServerMessage::Playerinfo(Playerinfo::PlayerinfoMvdT(
+                PlayerinfoMvd {
+                    player_number,
+                    flags,
+                    frame: p.frame,
+                    origin: Some(CoordinateVectorOption {
+                        x: Some(p.origin.x),
+                        y: Some(p.origin.y),
+                        z: Some(p.origin.z),
+                    }),
+                    angle: Some(AngleVectorOption {
+                        x: Some(p.angle.x),
+                        y: Some(p.angle.y),
+                        z: Some(p.angle.z),
+                    }),
+                    model: Some(p.model),
+                    skinnum: Some(p.skinnum),
+                    effects: Some(p.effects),
+                    weaponframe: Some(p.weaponframe),
+                },
+            )));
+        }
+
+        let mut entities: Vec<_> = state.entities.values().collect();
+        entities.sort_by_key(|e| e.index);
+        let entities = entities
+            .into_iter()
+            .map(|e| match state.baseline_entities.get(&e.index) {
+                Some(baseline) => e.delta_from(baseline),
+                None => e.delta_from(&Default::default()),
+            })
+            .collect();
+        messages.push(ServerMessage::Packetentities(Packetentities { entities }));
+        let mut size = self.write_messages(time, &all, &messages)?;
+
+        // stats are sent to the player they belong to
+        for (index, p) in &players {
+            let messages: Vec<_> = p
+                .stats
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| **value != 0)
+                .map(|(stat, value)| match i8::try_from(*value) {
+                    Ok(value) => ServerMessage::Updatestat(Updatestat {
+                        stat: stat as u8,
+                        value,
+                    }),
+                    Err(_) => ServerMessage::Updatestatlong(Updatestatlong {
+                        stat: stat as u8,
+                        value: *value,
+                    }),
+                })
+                .collect();
+            if messages.is_empty() {
+                continue;
+            }
+            let target = MvdTarget {
+                to: **index as u32,
+                command: DemoCommand::Stats,
+            };
+            size += self.write_messages(time, &target, &messages)?;
+        }
+
+        // lets the client know it has everything it needs
+        let skins = ServerMessage::Stufftext(Stufftext {
+            text: StringByte {
+                bytes: b"skins\n".to_vec(),
+                ..Default::default()
+            },
+        });
+        size += self.write_messages(time, &all, &[skins])?;
+        Ok(size)
+    }
+
+    /// writes the disconnect mvdsv ends its demos with
+    pub fn write_end_of_demo(&mut self, time: f64) -> Result<usize, MvdWriteError> {
+        let target = MvdTarget {
+            to: 0,
+            command: DemoCommand::All,
+        };
+        let mut size = self.write_time(time)?;
+        let mut packet = self.scratch();
+        ServerMessage::Disconnect(Disconnect {}).write(&mut packet)?;
+        packet.write_stringbyte("EndOfDemo");
+        let time_diff = self.pending_time_diff(time);
+        size += self.write_packet(time_diff, &target, &packet.buffer)?;
+        self.time = (time * 1000.0).round() as u64;
+        Ok(size)
+    }
+
+    fn scratch(&self) -> Message {
+        let mut message = Message::empty();
+        message.r#type = MessageType::Mvd;
+        message.flags = self.message.flags;
+        message
+    }
+
+    // splits the messages into packets no bigger than MAX_PACKET_SIZE
+    fn packets(&mut self, messages: &[ServerMessage]) -> Result<Vec<Vec<u8>>, MvdWriteError> {
+        let mut packets = vec![];
+        let mut packet: Vec<u8> = vec![];
+        for m in messages {
+            let mut scratch = self.scratch();
+            m.write(&mut scratch)?;
+            if let ServerMessage::Serverdata(s) = m {
+                self.message.flags.fte_protocol_extensions = s.fte_protocol_extension;
+                self.message.flags.fte_protocol_extensions_2 = s.fte_protocol_extension_2;
+                self.message.flags.mvd_protocol_extension = s.mvd_protocol_extension;
+            }
+            if !packet.is_empty() && packet.len() + scratch.buffer.len() > MAX_PACKET_SIZE {
+                packets.push(packet);
+                packet = vec![];
+            }
+            packet.extend_from_slice(&scratch.buffer);
+        }
+        if !packet.is_empty() {
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+
+    // only valid after write_time, which leaves less than 255ms to be written
+    fn pending_time_diff(&self, time: f64) -> u8 {
+        ((time * 1000.0).round() as u64 - self.time) as u8
+    }
+
+    // the time difference of a frame is a single byte, bigger gaps are filled with empty frames
+    fn write_time(&mut self, time: f64) -> Result<usize, MvdWriteError> {
+        let target_time = (time * 1000.0).round();
+        if target_time < self.time as f64 {
+            return Err(MvdWriteError::TimeOutOfOrder(time, self.time()));
+        }
+        let mut diff = target_time as u64 - self.time;
+        let mut size = 0;
+        let target = MvdTarget {
+            to: 0,
+            command: DemoCommand::All,
+        };
+        while diff >= 255 {
+            size += self.write_packet(255, &target, &[])?;
+            self.time += 255;
+            diff -= 255;
+        }
+        Ok(size)
+    }
+
+    fn write_packet(
+        &mut self,
+        time_diff: u8,
+        target: &MvdTarget,
+        data: &[u8],
+    ) -> Result<usize, MvdWriteError> {
+        let mut size = self.message.write_u8(time_diff);
+        match target.command {
+            DemoCommand::Multiple => {
+                size += self.message.write_u8(DemoCommand::Multiple as u8);
+                size += self.message.write_u32(target.to);
+            }
+            DemoCommand::Single | DemoCommand::Stats => {
+                if target.to > 31 {
+                    return Err(MvdWriteError::TargetOutOfRange(target.to, target.command));
+                }
+                size += self
+                    .message
+                    .write_u8(target.command as u8 | (target.to as u8) << 3);
+            }
+            DemoCommand::All | DemoCommand::Read => {
+                size += self.message.write_u8(target.command as u8);
+            }
+            DemoCommand::Command | DemoCommand::Set | DemoCommand::Empty => {
+                return Err(MvdWriteError::UnhandledCommand(target.command));
+            }
+        }
+        size += self.message.write_u32(data.len() as u32);
+        self.message.buffer.extend_from_slice(data);
+        self.message.position += data.len();
+        Ok(size + data.len())
+    }
+}
+
+// splits sound/model lists into messages, start is the amount of strings already sent and
+// offset the start of the next message or 0 when the list is complete
+fn list_messages(
+    list: &[StringByte],
+    name: &'static str,
+    create: impl Fn(u8, StringVector, u8) -> ServerMessage,
+) -> Result<Vec<ServerMessage>, MvdWriteError> {
+    if list.len() > 255 {
+        return Err(MvdWriteError::OutOfRange(name, list.len()));
+    }
+    let mut messages = vec![];
+    let mut start = 0;
+    let mut chunk: StringVector = vec![];
+    let mut chunk_size = 0;
+    for (i, s) in list.iter().enumerate() {
+        if !chunk.is_empty() && chunk_size + s.bytes.len() + 1 > MAX_LIST_SIZE {
+            messages.push(create(start as u8, chunk, i as u8));
+            chunk = vec![];
+            chunk_size = 0;
+            start = i;
+        }
+        chunk_size += s.bytes.len() + 1;
+        chunk.push(s.clone());
+    }
+    messages.push(create(start as u8, chunk, 0));
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mvd::fixtures::{parse, serverdata, string, writer};
+
+    #[test]
+    fn write_frames() {
+        let print = ServerMessage::Print(Print {
+            from: 2,
+            message: string("hello\n"),
+        });
+        let stat = ServerMessage::Updatestat(Updatestat { stat: 1, value: 5 });
+        let frames = vec![
+            (
+                0.0,
+                MvdTarget {
+                    to: 0,
+                    command: DemoCommand::Read,
+                },
+                vec![ServerMessage::Serverdata(serverdata())],
+            ),
+            (
+                0.013,
+                MvdTarget {
+                    to: 0,
+                    command: DemoCommand::All,
+                },
+                vec![print.clone(); 200],
+            ),
+            (
+                1.5,
+                MvdTarget {
+                    to: 3,
+                    command: DemoCommand::Stats,
+                },
+                vec![stat.clone()],
+            ),
+            (
+                1.6,
+                MvdTarget {
+                    to: 5,
+                    command: DemoCommand::Single,
+                },
+                vec![print.clone()],
+            ),
+            (
+                1.7,
+                MvdTarget {
+                    to: 1 << 4 | 1 << 1,
+                    command: DemoCommand::Multiple,
+                },
+                vec![print.clone()],
+            ),
+        ];
+        let mut writer = MvdWriter::default();
+        for (time, target, messages) in &frames {
+            writer.write_messages(*time, target, messages).unwrap();
+        }
+        writer.write_end_of_demo(2.0).unwrap();
+
+        let parsed = parse(writer.into_data());
+        let parsed: Vec<_> = parsed
+            .into_iter()
+            .filter(|f| !f.messages.is_empty())
+            .collect();
+
+        // the 200 prints do not fit a single packet
+        assert_eq!(parsed.len(), frames.len() + 2);
+        let prints: usize = parsed[1..3].iter().map(|f| f.messages.len()).sum();
+        assert_eq!(prints, 200);
+        assert!((parsed[2].time - 0.013).abs() < 0.0001);
+        for (frame, (time, target, messages)) in parsed[3..].iter().zip(&frames[2..]) {
+            assert!((frame.time - time).abs() < 0.0001);
+            assert_eq!(&frame.last, target);
+            assert_eq!(&frame.messages, messages);
+        }
+    }
+
+    #[cfg(feature = "state")]
+    #[test]
+    fn write_prelude() {
+        let mut state = State::new();
+        let mut messages = vec![ServerMessage::Serverdata(serverdata())];
+        let sounds: Vec<_> = (0..100)
+            .map(|i| string(&format!("misc/some_long_sound_name_{}.wav", i)))
+            .collect();
+        let models = vec![string("maps/dm2.bsp"), string("progs/player.mdl")];
+        messages.push(ServerMessage::Soundlist(Soundlist {
+            start: 0,
+            sounds: sounds.clone(),
+            offset: 0,
+        }));
+        messages.push(ServerMessage::Modellist(Modellist {
+            start: 0,
+            models: models.clone(),
+            offset: 0,
+        }));
+        messages.push(ServerMessage::Spawnbaseline(Spawnbaseline {
+            index: 12,
+            model_index: 2,
+            origin: CoordinateVector {
+                x: 8.0,
+                y: -16.0,
+                z: 24.5,
+            },
+            ..Default::default()
+        }));
+        state.apply_messages(&messages);
+
+        let mut writer = MvdWriter::default();
+        writer.write_prelude(&state).unwrap();
+        writer.write_end_of_demo(0.1).unwrap();
+
+        let mut parsed_state = State::new();
+        for frame in parse(writer.into_data()) {
+            parsed_state.apply_messages(&frame.messages);
+        }
+        assert_eq!(parsed_state.serverdata, state.serverdata);
+        assert_eq!(parsed_state.sounds, sounds);
+        assert_eq!(parsed_state.models, state.models);
+        let baseline = parsed_state.baseline_entities.get(&12).unwrap();
+        assert_eq!(baseline.model, 2);
+        assert_eq!(baseline.origin, state.baseline_entities[&12].origin);
+    }
+
+    #[cfg(feature = "state")]
+    #[test]
+    fn write_state_players() {
+        use crate::state::Player;
+
+        let mut state = State::new();
+        state.players.insert(
+            1,
+            Player {
+                frame: 5,
+                dead: true,
+                gib: true,
+                ..Default::default()
+            },
+        );
+        state.players.insert(
+            2,
+            Player {
+                spectator: true,
+                ..Default::default()
+            },
+        );
+
+        let mut writer = writer(serverdata(), &[]);
+        writer.write_state(&state).unwrap();
+        writer.write_end_of_demo(0.1).unwrap();
+
+        let playerinfos: Vec<_> = parse(writer.into_data())
+            .into_iter()
+            .flat_map(|f| f.messages)
+            .filter_map(|m| match m {
+                ServerMessage::Playerinfo(Playerinfo::PlayerinfoMvdT(p)) => Some(p),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(playerinfos.len(), 1);
+        assert_eq!(playerinfos[0].player_number, 1);
+        assert_eq!(playerinfos[0].frame, 5);
+        assert!(playerinfos[0].flags.contains(DfTypes::DEAD | DfTypes::GIB));
+    }
+
+    #[test]
+    fn write_hidden_frames() {
+        use crate::mvd::hidden::MvdDamageDone;
+
+        let damage = MvdHiddenMessage::DamageDone(MvdDamageDone {
+            death_type: 1,
+            attacker: 2,
+            target: 3,
+            damage: 42,
+        });
+        let mut frame = MvdFrame::empty();
+        frame.time = 0.5;
+        frame.last = MvdTarget {
+            to: 0,
+            command: DemoCommand::Multiple,
+        };
+        frame.hidden_messages = vec![damage.clone(), damage.clone()];
+
+        let mut writer = writer(serverdata(), &[]);
+        writer.write_frame(&frame).unwrap();
+        writer.write_end_of_demo(1.0).unwrap();
+
+        let parsed = parse(writer.into_data());
+        let hidden: Vec<_> = parsed
+            .iter()
+            .filter(|f| !f.hidden_messages.is_empty())
+            .collect();
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].time, 0.5);
+        assert_eq!(hidden[0].hidden_messages, vec![damage.clone(), damage]);
+        assert!(hidden[0].messages.is_empty());
+    }
+
+    #[test]
+    fn write_multiple_without_hidden_messages() {
+        let print = ServerMessage::Print(Print {
+            from: 2,
+            message: string("hello\n"),
+        });
+        let mut frame = MvdFrame::empty();
+        frame.time = 0.5;
+        frame.last = MvdTarget {
+            to: 0,
+            command: DemoCommand::Multiple,
+        };
+        frame.messages = vec![print.clone()];
+
+        let serverdata = Serverdata {
+            mvd_protocol_extension: MvdProtocolExtensions::empty(),
+            ..serverdata()
+        };
+        let mut writer = writer(serverdata, &[]);
+        writer.write_frame(&frame).unwrap();
+        writer.write_end_of_demo(1.0).unwrap();
+
+        let parsed = parse(writer.into_data());
+        let frames: Vec<_> = parsed.iter().filter(|f| f.time == 0.5).collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].last, frame.last);
+        assert_eq!(frames[0].messages, vec![print]);
+        assert!(frames[0].hidden_messages.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::network::channel::Channel;
+use crate::protocol::errors::InfostringError;
 #[cfg(feature = "trace")]
 use crate::protocol::message::trace::MessageTrace;
 use crate::protocol::message::Message;
@@ -7,6 +8,7 @@ use crate::protocol::message::MessageType;
 use crate::protocol::types::{
     ClientServer, DeltaUserCommand, Packet, ProtocolVersion, ServerMessage, Serverdata,
 };
+use crate::utils::infostring::quote;
 use crate::utils::userinfo::Userinfo;
 use thiserror::Error;
 
@@ -68,6 +70,14 @@ pub fn print_seq(out: bool, first: u32, second: u32) {
     );
 }
 
+fn setinfo_command(key: &[u8], value: &[u8]) -> Result<Vec<u8>, InfostringError> {
+    let mut command = b"setinfo ".to_vec();
+    command.extend(quote(key)?);
+    command.push(b' ');
+    command.extend(quote(value)?);
+    Ok(command)
+}
+
 impl Client {
     pub fn new(ip: String, ascii_converter: AsciiConverter) -> Client {
         let mut userinfo = Userinfo::new_with_ascii_converter(ascii_converter);
@@ -79,6 +89,17 @@ impl Client {
         }
     }
 
+    /// sets a key in the userinfo and returns the `setinfo` command telling the server about it
+    pub fn setinfo(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, InfostringError> {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        self.userinfo.set(key, value)?;
+        setinfo_command(key, value)
+    }
+
     // setup connection and return challenge packet
     pub fn connect(&mut self, port: u16) -> Vec<u8> {
         self.local_port = port;
@@ -136,7 +157,7 @@ impl Client {
         message.write_u16(self.local_port);
         self.write_empty_move_cmd(&mut message)?;
         Ok(ClientStatus {
-            response: Some(*message.buffer.clone()),
+            response: Some(message.buffer.to_vec()),
             packet: None,
         })
     }
@@ -223,7 +244,7 @@ impl Client {
                 message.write_u8(ClientServer::Nop as u8);
                 self.write_empty_move_cmd(&mut message)?;
                 return Ok(ClientStatus {
-                    response: Some(*message.buffer.clone()),
+                    response: Some(message.buffer.to_vec()),
                     packet: None,
                 });
             }
@@ -263,14 +284,15 @@ impl Client {
                                     self.serverdata.servercount, self.map_crc
                                 ));
 
-                                message.write_client_command_string(format!(
-                                    "setinfo pmodel {}",
-                                    3316
-                                ));
-                                message.write_client_command_string(format!(
-                                    "setinfo emodel {}",
-                                    6967
-                                ));
+                                // the server checks the userinfo size itself, a full local
+                                // userinfo must not stop the handshake
+                                for (key, value) in [("pmodel", "3316"), ("emodel", "6967")] {
+                                    self.userinfo.update_from_string(key, value);
+                                    message.write_client_command_string_vec(setinfo_command(
+                                        key.as_bytes(),
+                                        value.as_bytes(),
+                                    )?);
+                                }
                                 self.prespawn_send = true;
                             }
                         }
@@ -355,7 +377,7 @@ impl Client {
                 }
                 self.write_empty_move_cmd(&mut message)?;
                 return Ok(ClientStatus {
-                    response: Some(*message.buffer.clone()),
+                    response: Some(message.buffer.to_vec()),
                     packet: Some(Packet::Connected(p)),
                 });
             }
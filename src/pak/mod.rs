@@ -1,13 +1,15 @@
 use std::io::prelude::*;
+use std::io::BufReader;
 use std::io::Cursor;
 use std::io::SeekFrom;
 
 use protocol_macros::DataTypeBoundCheckDerive;
 use serde::Serialize;
 
+use crate::datatypes::common::DirectoryEntry;
 use crate::datatypes::pak;
-use crate::datatypes::reader::DataTypeSize;
 use crate::datatypes::reader::{DataTypeBoundCheck, DataTypeRead, DataTypeReader};
+use crate::datatypes::writer::{DataTypeWrite, DataTypeWriter};
 
 mod error;
 pub use error::{Error, Result};
@@ -55,32 +57,32 @@ impl<'a> Pak {
     ) -> Result<Pak> {
         let name = name.into();
         let data = data.into();
-        let mut datatypereader = DataTypeReader::new(
-            data.clone(),
-            #[cfg(feature = "trace")]
-            trace.clone(),
-        );
-        let header = <pak::Header as DataTypeRead>::read(&mut datatypereader)?;
-        header.check_bounds(&mut datatypereader)?;
-
-        if header.version != HEADER_MAGIC {
-            return Err(Error::HeaderMismatch(header.version, HEADER_MAGIC));
-        }
-
-        let file_count = header.directory_offset.size / (NAME_LENGTH + 4 * 2);
-
-        datatypereader
-            .cursor
-            .seek(SeekFrom::Start((header.directory_offset.offset).into()))?;
-        let mut files = Vec::new();
-        for _ in 0..file_count {
-            let f = <pak::File as DataTypeRead>::read(&mut datatypereader)?;
-            files.push(f);
-        }
-        let p = Pak { name, data, files };
-        p.check_bounds(&mut datatypereader)?;
+        let files = {
+            let mut datatypereader = DataTypeReader::from_slice(
+                &data,
+                #[cfg(feature = "trace")]
+                trace.clone(),
+            );
+            let header = <pak::Header as DataTypeRead>::read(&mut datatypereader)?;
+            header.check_bounds(&mut datatypereader)?;
+
+            if header.version != HEADER_MAGIC {
+                return Err(Error::HeaderMismatch(header.version, HEADER_MAGIC));
+            }
+
+            let file_count = header.directory_offset.size / (NAME_LENGTH + 4 * 2);
+
+            datatypereader.set_position(header.directory_offset.offset.into());
+            let mut files = Vec::new();
+            for _ in 0..file_count {
+                let f = <pak::File as DataTypeRead>::read(&mut datatypereader)?;
+                files.push(f);
+            }
+            files.check_bounds(&mut datatypereader)?;
+            files
+        };
 
-        Ok(p)
+        Ok(Pak { name, data, files })
     }
 
     pub fn get_data(&self, file: &pak::File) -> Result<Vec<u8>> {
@@ -102,46 +104,28 @@ pub struct PakOnDisk {
 impl<'a> PakOnDisk {
     pub fn load(
         name: impl Into<String>,
-        mut file: std::fs::File,
+        file: std::fs::File,
         #[cfg(feature = "trace")] trace: Option<Trace>,
     ) -> Result<Self> {
         let name = name.into();
-        let header_size = <pak::HeaderLight>::datatype_size();
-        let mut header_data: Vec<u8> = vec![0; header_size];
-        file.read_exact(&mut header_data)?;
-
-        #[cfg(feature = "trace")]
-        let trace = match trace {
-            Some(v) => Some(v.clone()),
-            None => None,
-        };
-
-        let mut dtr_header = DataTypeReader::new(
-            header_data.clone(),
+        // only the header and the directory are read
+        let mut datatypereader = DataTypeReader::from_reader(
+            BufReader::new(file),
             #[cfg(feature = "trace")]
-            trace.clone(),
-        );
-        let header = <pak::HeaderLight as DataTypeRead>::read(&mut dtr_header)?;
+            trace,
+        )?;
+        let header = <pak::HeaderLight as DataTypeRead>::read(&mut datatypereader)?;
         if header.version != HEADER_MAGIC {
             return Err(Error::HeaderMismatch(header.version, HEADER_MAGIC));
         }
-
-        file.seek(SeekFrom::Start(header.directory_offset.offset as u64))?;
-
-        let mut file_data: Vec<u8> = vec![0; header.directory_offset.size as usize];
-        file.read_exact(&mut file_data)?;
+        header.directory_offset.check_bounds(&mut datatypereader)?;
 
         let file_count = header.directory_offset.size / (NAME_LENGTH + 4 * 2);
 
-        let mut dtr_file = DataTypeReader::new(
-            file_data,
-            #[cfg(feature = "trace")]
-            trace.clone(),
-        );
-
+        datatypereader.set_position(header.directory_offset.offset.into());
         let mut files = Vec::new();
         for _ in 0..file_count {
-            let f = <pak::File as DataTypeRead>::read(&mut dtr_file)?;
+            let f = <pak::File as DataTypeRead>::read(&mut datatypereader)?;
             files.push(f);
         }
 
@@ -204,26 +188,31 @@ impl PakWriter {
     }
 
     pub fn write_data(self) -> Result<Vec<u8>> {
-        let mut buffer: Vec<u8> = Vec::new();
-        let mut c = Cursor::new(&mut buffer);
-        c.write_all(&HEADER_MAGIC.to_le_bytes())?;
+        let mut datawriter = DataTypeWriter::new();
         let dir_offset: u32 = 4 * 3;
-        c.write_all(&dir_offset.to_le_bytes())?;
-        let dir_size: u32 = (self.files.len() * (MAX_NAME_LENGTH + 1 + 8)) as u32;
-        c.write_all(&dir_size.to_le_bytes())?;
+        let dir_size: u32 = self.files.len() as u32 * (NAME_LENGTH + 4 * 2);
+        pak::Header {
+            version: HEADER_MAGIC,
+            directory_offset: DirectoryEntry {
+                offset: dir_offset,
+                size: dir_size,
+            },
+        }
+        .write(&mut datawriter)?;
         let mut file_position = dir_offset + dir_size;
         for file in &self.files {
-            let mut name_buffer: [u8; NAME_LENGTH as usize] = [0; NAME_LENGTH as usize];
-            name_buffer[..file.name.len()].copy_from_slice(&file.name);
-            c.write_all(&name_buffer)?;
-            c.write_all(&file_position.to_le_bytes())?;
-            c.write_all(&(file.data.len() as u32).to_le_bytes())?;
+            pak::File {
+                name: file.name.clone(),
+                offset: file_position,
+                size: file.data.len() as u32,
+            }
+            .write(&mut datawriter)?;
             file_position += file.data.len() as u32;
         }
         for file in &self.files {
-            c.write_all(&file.data)?;
+            datawriter.write_bytes(&file.data);
         }
-        Ok(buffer)
+        Ok(datawriter.into_data())
     }
 }
 
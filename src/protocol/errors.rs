@@ -1,4 +1,5 @@
 use crate::protocol::message::errors::MessageError;
+use crate::protocol::types::DemoCommand;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,6 +10,12 @@ pub enum MvdParseError {
     UnhandledCommand(u8),
     #[error("cannot handle qwd command")]
     QwdCommand,
+    #[error("chunk ({0}) failed: {1}")]
+    ChunkError(usize, Box<MvdParseError>),
+    #[error("thread parsing chunk ({0}) panicked")]
+    ChunkPanic(usize),
+    #[error("{} chunks failed, first: {}", .0.len(), .0[0])]
+    ChunkErrors(Vec<MvdParseError>),
     #[error("read error {0}")]
     MessageError(MessageError),
     #[error("io error {0}")]
@@ -20,3 +27,104 @@ impl From<MessageError> for MvdParseError {
         MvdParseError::MessageError(err)
     }
 }
+
+#[derive(Error, Debug)]
+pub enum MvdIndexError {
+    #[error("index has no keyframes")]
+    Empty,
+    #[error("parse error {0}")]
+    ParseError(#[from] MvdParseError),
+    #[error("serialization error {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum MvdCutError {
+    #[error("invalid cut range ({0} - {1})")]
+    InvalidRange(f64, f64),
+    #[error("cut start ({0}) is beyond the end of the demo ({1})")]
+    StartBeyondEnd(f64, f64),
+    #[error("parse error {0}")]
+    ParseError(#[from] MvdParseError),
+    #[error("write error {0}")]
+    WriteError(#[from] MvdWriteError),
+}
+
+#[derive(Error, Debug)]
+pub enum MvdWriteError {
+    #[error("cannot write demo command ({0})")]
+    UnhandledCommand(DemoCommand),
+    #[error("target ({0}) out of range for demo command ({1})")]
+    TargetOutOfRange(u32, DemoCommand),
+    #[error("frame time ({0}) is before the last written time ({1})")]
+    TimeOutOfOrder(f64, f64),
+    #[error("{0} ({1}) does not fit the mvd format")]
+    OutOfRange(&'static str, usize),
+    #[error("write error {0}")]
+    MessageError(MessageError),
+}
+
+impl From<MessageError> for MvdWriteError {
+    fn from(err: MessageError) -> MvdWriteError {
+        MvdWriteError::MessageError(err)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum QwdParseError {
+    #[error("unhandled command ({0})")]
+    UnhandledCommand(u8),
+    #[error("packet size ({0}) exceeds demo size({1}) at position({2})")]
+    PacketSize(usize, usize, usize),
+    #[error("read error {0}")]
+    MessageError(MessageError),
+}
+
+impl From<MessageError> for QwdParseError {
+    fn from(err: MessageError) -> QwdParseError {
+        QwdParseError::MessageError(err)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DemParseError {
+    #[error("missing cd track header")]
+    CdTrack,
+    #[error("block size ({0}) exceeds demo size({1}) at position({2})")]
+    BlockSize(usize, usize, usize),
+    #[error("unknown message type ({0})")]
+    UnknownType(u8),
+    #[error("read error {0}")]
+    MessageError(MessageError),
+}
+
+impl From<MessageError> for DemParseError {
+    fn from(err: MessageError) -> DemParseError {
+        DemParseError::MessageError(err)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FragfileError {
+    #[error("line ({0}): {1}")]
+    Syntax(usize, String),
+    #[error("line ({0}): unknown weapon class ({1})")]
+    UnknownWeaponClass(usize, u32),
+    #[cfg(feature = "vfs")]
+    #[error("vfs error {0}")]
+    VfsError(#[from] crate::vfs::Error),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum InfostringError {
+    #[error("empty key")]
+    EmptyKey,
+    #[error("({0}) contains \\ or \"")]
+    InvalidCharacter(String),
+    #[error("({0}) has to be shorter than {1}")]
+    TooLong(String, usize),
+    #[error("({0}) is read only")]
+    ReadOnlyKey(String),
+    #[error("infostring would exceed {0} bytes")]
+    SizeExceeded(usize),
+}
@@ -14,6 +14,8 @@ pub enum MessageError {
     StringError(String),
     #[error("Bad read")]
     BadRead,
+    #[error("Bad write")]
+    BadWrite,
 }
 
 impl From<String> for MessageError {
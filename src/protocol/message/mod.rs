@@ -1,6 +1,8 @@
 use crate::protocol::message::errors::MessageError;
 use paste::paste;
-use serde::Serialize;
+use serde::{Deserialize, Serialize, Serializer};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 #[cfg(feature = "ascii_strings")]
 use crate::utils::ascii_converter::AsciiConverter;
@@ -19,9 +21,10 @@ pub enum MessageType {
     None,
     Connection,
     Mvd,
+    NetQuake,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
 pub struct MessageFlags {
     pub protocol: u32,
     pub fte_protocol_extensions: FteProtocolExtensions,
@@ -51,12 +54,60 @@ impl MessageFlags {
     }
 }
 
+/// message data, clones share the data until one of them is written to
+#[derive(Clone, Default)]
+pub struct MessageBuffer(Arc<Vec<u8>>);
+
+impl Deref for MessageBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for MessageBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl std::fmt::Debug for MessageBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for MessageBuffer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl From<Vec<u8>> for MessageBuffer {
+    fn from(buffer: Vec<u8>) -> MessageBuffer {
+        MessageBuffer(Arc::new(buffer))
+    }
+}
+
+impl From<Box<Vec<u8>>> for MessageBuffer {
+    fn from(buffer: Box<Vec<u8>>) -> MessageBuffer {
+        MessageBuffer(Arc::new(*buffer))
+    }
+}
+
+impl From<MessageBuffer> for Vec<u8> {
+    fn from(buffer: MessageBuffer) -> Vec<u8> {
+        Arc::unwrap_or_clone(buffer.0)
+    }
+}
+
 #[derive(Serialize, Clone, Default, Debug)]
 pub struct Message {
     pub start: usize,    // starting position in the buffer
     pub length: usize,   // length of the message
     pub position: usize, // current read position after start
-    pub buffer: Box<Vec<u8>>,
+    pub buffer: MessageBuffer,
     pub bigendian: bool,
     pub flags: MessageFlags,
     #[cfg(feature = "ascii_strings")]
@@ -136,19 +187,19 @@ macro_rules! function {
 
 impl From<Message> for Vec<u8> {
     fn from(m: Message) -> Vec<u8> {
-        *m.buffer
+        m.buffer.into()
     }
 }
 
 impl From<&Message> for Vec<u8> {
     fn from(m: &Message) -> Vec<u8> {
-        *m.buffer.clone()
+        m.buffer.to_vec()
     }
 }
 
 impl From<&mut Message> for Vec<u8> {
     fn from(m: &mut Message) -> Vec<u8> {
-        *m.buffer.clone()
+        m.buffer.to_vec()
     }
 }
 
@@ -170,15 +221,22 @@ impl Message {
         Ok(buf)
     }
 
-    pub fn write_stringbyte(&mut self, string: String) -> usize {
+    pub fn write_stringbyte(&mut self, string: impl Into<Vec<u8>>) -> usize {
         let mut size: usize = 0;
-        let s = string.as_bytes();
+        let s = string.into();
         for c in s {
-            self.buffer.push(*c);
-            size += 1;
+            size += self.write_u8(c);
         }
-        self.buffer.push(0_u8);
-        size += 1;
+        size += self.write_u8(0);
+        size
+    }
+
+    pub fn write_stringvector(&mut self, strings: impl AsRef<[StringByte]>) -> usize {
+        let mut size: usize = 0;
+        for s in strings.as_ref() {
+            size += self.write_stringbyte(s);
+        }
+        size += self.write_u8(0);
         size
     }
 
@@ -206,7 +264,39 @@ impl Message {
     }
 
     pub fn write_angle16(&mut self, angle: f32) -> usize {
-        self.write_u16((angle * 65536.0 / 360.0) as u16)
+        self.write_u16(((angle * 65536.0 / 360.0).round() as i32 & 65535) as u16)
+    }
+
+    pub fn write_angle(&mut self, angle: Angle) -> usize {
+        if self
+            .flags
+            .fte_protocol_extensions
+            .contains(FteProtocolExtensions::FLOATCOORDS)
+        {
+            return self.write_angle16(angle);
+        }
+        self.write_u8(((angle * 256.0 / 360.0).round() as i32 & 255) as u8)
+    }
+
+    pub fn write_anglevector(&mut self, angle: AngleVector) -> usize {
+        self.write_angle(angle.x) + self.write_angle(angle.y) + self.write_angle(angle.z)
+    }
+
+    pub fn write_coordinate(&mut self, coordinate: Coordinate) -> usize {
+        if self
+            .flags
+            .fte_protocol_extensions
+            .contains(FteProtocolExtensions::FLOATCOORDS)
+        {
+            return self.write_f32(coordinate);
+        }
+        self.write_i16((coordinate * 8.0).round() as i16)
+    }
+
+    pub fn write_coordinatevector(&mut self, coordinate: CoordinateVector) -> usize {
+        self.write_coordinate(coordinate.x)
+            + self.write_coordinate(coordinate.y)
+            + self.write_coordinate(coordinate.z)
     }
 
     pub fn write_delta_usercommand(&mut self, delta_usercommand: DeltaUserCommand) -> usize {
@@ -381,7 +471,7 @@ impl Message {
     pub fn read_angle16(&mut self, readahead: bool) -> Result<Angle, MessageError> {
         trace::trace_start!(self, readahead);
         let s = self.read_u16(readahead)? as f32;
-        let v = s * (360.0 / 65535.0);
+        let v = s * (360.0 / 65536.0);
         trace::trace_stop!(self, v);
         Ok(v)
     }
@@ -400,7 +490,7 @@ impl Message {
             start: 0,
             length: 0,
             position: 0,
-            buffer: Box::<Vec<u8>>::default(),
+            buffer: MessageBuffer::default(),
             bigendian: false,
             flags: MessageFlags::new_empty(),
             #[cfg(feature = "ascii_strings")]
@@ -421,7 +511,7 @@ impl Message {
 
     #[cfg(feature = "ascii_strings")]
     pub fn new(
-        buffer: Box<Vec<u8>>,
+        buffer: impl Into<MessageBuffer>,
         start: usize,
         length: usize,
         bigendian: bool,
@@ -441,7 +531,7 @@ impl Message {
             start,
             length,
             position: 0,
-            buffer,
+            buffer: buffer.into(),
             bigendian,
             flags,
             ascii_converter,
@@ -453,7 +543,7 @@ impl Message {
 
     #[cfg(not(feature = "ascii_strings"))]
     pub fn new(
-        buffer: Box<Vec<u8>>,
+        buffer: impl Into<MessageBuffer>,
         start: usize,
         length: usize,
         bigendian: bool,
@@ -464,7 +554,7 @@ impl Message {
             start,
             length,
             position: 0,
-            buffer,
+            buffer: buffer.into(),
             bigendian,
             flags,
             r#type,
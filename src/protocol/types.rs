@@ -1,7 +1,7 @@
 use bitflags::bitflags;
 use num_enum::TryFromPrimitive;
 use paste::paste;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::hash::BuildHasherDefault;
 use std::ops::Index;
@@ -71,15 +71,15 @@ impl DeltaUserCommand {
             }
             if flags.contains(UserCommandFlags::FORWARD) {
                 trace::trace_annotate!(message, "forward");
-                forward = Some((message.read_i8(false)? << 3) as i16);
+                forward = Some((message.read_i8(false)? as i16) << 3);
             }
             if flags.contains(UserCommandFlags::SIDE) {
                 trace::trace_annotate!(message, "side");
-                side = Some((message.read_i8(false)? << 3) as i16);
+                side = Some((message.read_i8(false)? as i16) << 3);
             }
             if flags.contains(UserCommandFlags::UP) {
                 trace::trace_annotate!(message, "up");
-                up = Some((message.read_i8(false)? << 3) as i16);
+                up = Some((message.read_i8(false)? as i16) << 3);
             }
         } else {
             if flags.contains(UserCommandFlags::ANGLE1) {
@@ -145,9 +145,92 @@ impl DeltaUserCommand {
         trace::trace_stop!(message, r);
         Ok(r)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut bits = UserCommandFlags::empty();
+        let legacy = message.flags.protocol <= 26;
+        if self.angle.x.is_some() {
+            bits |= UserCommandFlags::ANGLE1;
+        }
+        if self.angle.z.is_some() {
+            bits |= UserCommandFlags::ANGLE3;
+        }
+        if self.forward.is_some() {
+            bits |= UserCommandFlags::FORWARD;
+        }
+        if self.side.is_some() {
+            bits |= UserCommandFlags::SIDE;
+        }
+        if self.up.is_some() {
+            bits |= UserCommandFlags::UP;
+        }
+        if self.buttons.is_some() {
+            bits |= UserCommandFlags::BUTTONS;
+        }
+        if self.impulse.is_some() {
+            bits |= UserCommandFlags::IMPULSE;
+        }
+        // ANGLE2 is CM_MSEC in the original protocol
+        if (legacy && self.msec.is_some()) || (!legacy && self.angle.y.is_some()) {
+            bits |= UserCommandFlags::ANGLE2;
+        }
+
+        let mut size = message.write_u8(bits.bits());
+        if legacy {
+            if let Some(x) = self.angle.x {
+                size += message.write_angle16(x);
+            }
+            size += message.write_angle16(self.angle.y.unwrap_or_default());
+            if let Some(z) = self.angle.z {
+                size += message.write_angle16(z);
+            }
+            if let Some(forward) = self.forward {
+                size += message.write_i8((forward >> 3) as i8);
+            }
+            if let Some(side) = self.side {
+                size += message.write_i8((side >> 3) as i8);
+            }
+            if let Some(up) = self.up {
+                size += message.write_i8((up >> 3) as i8);
+            }
+        } else {
+            if let Some(x) = self.angle.x {
+                size += message.write_angle16(x);
+            }
+            if let Some(y) = self.angle.y {
+                size += message.write_angle16(y);
+            }
+            if let Some(z) = self.angle.z {
+                size += message.write_angle16(z);
+            }
+            if let Some(forward) = self.forward {
+                size += message.write_i16(forward);
+            }
+            if let Some(side) = self.side {
+                size += message.write_i16(side);
+            }
+            if let Some(up) = self.up {
+                size += message.write_i16(up);
+            }
+        }
+        if let Some(buttons) = self.buttons {
+            size += message.write_u8(buttons);
+        }
+        if let Some(impulse) = self.impulse {
+            size += message.write_u8(impulse);
+        }
+        if legacy {
+            if let Some(msec) = self.msec {
+                size += message.write_u8(msec);
+            }
+        } else {
+            size += message.write_u8(self.msec.unwrap_or_default());
+        }
+        Ok(size)
+    }
 }
 
-#[derive(PartialOrd, PartialEq, Eq, Clone, Debug, Serialize, Default)]
+#[derive(PartialOrd, PartialEq, Eq, Clone, Debug, Serialize, Deserialize, Default)]
 pub struct StringByte {
     pub bytes: Vec<u8>,
     #[cfg(feature = "ascii_strings")]
@@ -191,10 +274,22 @@ impl StringByte {
     }
 }
 
+impl From<StringByte> for Vec<u8> {
+    fn from(s: StringByte) -> Vec<u8> {
+        s.bytes
+    }
+}
+
+impl From<&StringByte> for Vec<u8> {
+    fn from(s: &StringByte) -> Vec<u8> {
+        s.bytes.clone()
+    }
+}
+
 pub type StringVector = Vec<StringByte>;
 
 pub type Coordinate = f32;
-#[derive(Debug, PartialEq, PartialOrd, Default, Serialize, Clone, Copy)]
+#[derive(Debug, PartialEq, PartialOrd, Default, Serialize, Deserialize, Clone, Copy)]
 pub struct CoordinateVector {
     pub x: Coordinate,
     pub y: Coordinate,
@@ -245,7 +340,7 @@ impl CoordinateVectorOption {
 }
 
 pub type Angle = f32;
-#[derive(Debug, PartialEq, PartialOrd, Serialize, Default, Copy, Clone)]
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Default, Copy, Clone)]
 pub struct AngleVector {
     pub x: Angle,
     pub y: Angle,
@@ -311,7 +406,17 @@ impl AngleVectorOption {
 }
 
 #[derive(
-    Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Copy, Clone, Serialize,
+    Debug,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    TryFromPrimitive,
+    Display,
+    Copy,
+    Clone,
+    Serialize,
+    Deserialize,
 )]
 #[repr(u8)]
 pub enum DemoCommand {
@@ -326,7 +431,17 @@ pub enum DemoCommand {
 }
 
 #[derive(
-    Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Clone, Serialize, Default,
+    Debug,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    TryFromPrimitive,
+    Display,
+    Clone,
+    Serialize,
+    Deserialize,
+    Default,
 )]
 #[repr(u32)]
 pub enum ProtocolVersion {
@@ -385,7 +500,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Serialize, Default)]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Serialize, Deserialize, Default)]
 pub struct Serverdata {
     pub protocol: ProtocolVersion,
     pub fte_protocol_extension: FteProtocolExtensions,
@@ -484,6 +599,43 @@ impl Serverdata {
         trace::trace_stop!(message, r);
         Ok(r)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = 0;
+        if !self.fte_protocol_extension.is_empty() {
+            size += message.write_u32(ProtocolVersion::Fte as u32);
+            size += message.write_u32(self.fte_protocol_extension.bits());
+        }
+        if !self.fte_protocol_extension_2.is_empty() {
+            size += message.write_u32(ProtocolVersion::Fte2 as u32);
+            size += message.write_u32(self.fte_protocol_extension_2.bits());
+        }
+        if !self.mvd_protocol_extension.is_empty() {
+            size += message.write_u32(ProtocolVersion::Mvd1 as u32);
+            size += message.write_u32(self.mvd_protocol_extension.bits());
+        }
+        size += message.write_u32(ProtocolVersion::Standard as u32);
+        size += message.write_u32(self.servercount);
+        size += message.write_stringbyte(&self.gamedir);
+        match message.r#type {
+            MessageType::Connection => {
+                size += message.write_u8(self.player_number);
+            }
+            MessageType::Mvd => {
+                size += message.write_f32(self.demotime);
+            }
+            _ => {
+                return Err(MessageError::StringError(
+                    "MessageType None should never happen, probable writer error".to_string(),
+                ))
+            }
+        }
+        size += message.write_stringbyte(&self.map);
+        for mv in self.movevars {
+            size += message.write_f32(mv);
+        }
+        Ok(size)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
@@ -558,6 +710,19 @@ impl Baseentity {
         trace::trace_stop!(message, v);
         Ok(v)
     }
+
+    fn write(&self, message: &mut Message) -> usize {
+        message.write_u8(self.model_index)
+            + message.write_u8(self.model_frame)
+            + message.write_u8(self.colormap)
+            + message.write_u8(self.skinnum)
+            + message.write_coordinate(self.origin.x)
+            + message.write_angle(self.angle.x)
+            + message.write_coordinate(self.origin.y)
+            + message.write_angle(self.angle.y)
+            + message.write_coordinate(self.origin.z)
+            + message.write_angle(self.angle.z)
+    }
 }
 
 impl Into<Spawnstatic> for Baseentity {
@@ -587,7 +752,33 @@ impl Into<Spawnbaseline> for Baseentity {
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Serialize, Default, Copy, Clone)]
+impl From<&Spawnstatic> for Baseentity {
+    fn from(s: &Spawnstatic) -> Baseentity {
+        Baseentity {
+            model_index: s.model_index,
+            model_frame: s.model_frame,
+            colormap: s.colormap,
+            skinnum: s.skinnum,
+            origin: s.origin,
+            angle: s.angle,
+        }
+    }
+}
+
+impl From<&Spawnbaseline> for Baseentity {
+    fn from(s: &Spawnbaseline) -> Baseentity {
+        Baseentity {
+            model_index: s.model_index,
+            model_frame: s.model_frame,
+            colormap: s.colormap,
+            skinnum: s.skinnum,
+            origin: s.origin,
+            angle: s.angle,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Default, Copy, Clone)]
 pub struct Spawnstatic {
     pub model_index: u8,
     pub model_frame: u8,
@@ -602,6 +793,10 @@ impl Spawnstatic {
         let base = Baseentity::read(message)?;
         Ok(ServerMessage::Spawnstatic(base.into()))
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        Ok(Baseentity::from(self).write(message))
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Default, Clone)]
@@ -621,9 +816,14 @@ impl Spawnbaseline {
         base.index = index;
         Ok(ServerMessage::Spawnbaseline(base))
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let size = message.write_u16(self.index);
+        Ok(size + Baseentity::from(self).write(message))
+    }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, ParseMessage, Serialize, Clone, Default)]
+#[derive(Debug, PartialEq, PartialOrd, ParseMessage, Serialize, Deserialize, Clone, Default)]
 pub struct Spawnstaticsound {
     pub origin: CoordinateVector,
     pub index: u8,
@@ -705,6 +905,7 @@ bitflags! {
         const WEAPONFRAME = 1 << 8;
         const DEAD = 1 << 9;
         const GIB = 1 << 10;
+        const PMC = 7 << 11;
         const ONGROUND = 1 << 14;
         const SOLID = 1 << 15;
         const TRANS = 1 << 17;
@@ -971,6 +1172,124 @@ fn playerinfo_read_connection(message: &mut Message) -> Result<ServerMessage, Me
     ))
 }
 
+fn playerinfo_write_demo(message: &mut Message, p: &PlayerinfoMvd) -> usize {
+    let mut flags = p.flags & (DfTypes::DEAD | DfTypes::GIB);
+    if let Some(origin) = p.origin {
+        if origin.x.is_some() {
+            flags |= DfTypes::ORIGIN;
+        }
+        if origin.y.is_some() {
+            flags |= DfTypes::ORIGIN2;
+        }
+        if origin.z.is_some() {
+            flags |= DfTypes::ORIGIN3;
+        }
+    }
+    if let Some(angle) = p.angle {
+        if angle.x.is_some() {
+            flags |= DfTypes::ANGLE;
+        }
+        if angle.y.is_some() {
+            flags |= DfTypes::ANGLE2;
+        }
+        if angle.z.is_some() {
+            flags |= DfTypes::ANGLE3;
+        }
+    }
+    if p.model.is_some() {
+        flags |= DfTypes::MODEL;
+    }
+    if p.skinnum.is_some() {
+        flags |= DfTypes::SKINNUM;
+    }
+    if p.effects.is_some() {
+        flags |= DfTypes::EFFECTS;
+    }
+    if p.weaponframe.is_some() {
+        flags |= DfTypes::WEAPONFRAME;
+    }
+
+    let mut size = message.write_u8(p.player_number);
+    size += message.write_u16(flags.bits());
+    size += message.write_u8(p.frame);
+    if let Some(origin) = p.origin {
+        for v in [origin.x, origin.y, origin.z].into_iter().flatten() {
+            size += message.write_coordinate(v);
+        }
+    }
+    if let Some(angle) = p.angle {
+        for v in [angle.x, angle.y, angle.z].into_iter().flatten() {
+            size += message.write_angle16(v);
+        }
+    }
+    for v in [p.model, p.skinnum, p.effects, p.weaponframe]
+        .into_iter()
+        .flatten()
+    {
+        size += message.write_u8(v);
+    }
+    size
+}
+
+fn playerinfo_write_connection(
+    message: &mut Message,
+    p: &PlayerinfoConnection,
+) -> Result<usize, MessageError> {
+    let mut flags = p.flags
+        & (PFTypes::DEAD | PFTypes::GIB | PFTypes::PMC | PFTypes::ONGROUND | PFTypes::SOLID);
+    if p.msec.is_some() {
+        flags |= PFTypes::MSEC;
+    }
+    if p.command.is_some() {
+        flags |= PFTypes::COMMAND;
+    }
+    if p.velocity.x.is_some() {
+        flags |= PFTypes::VELOCITY1;
+    }
+    if p.velocity.y.is_some() {
+        flags |= PFTypes::VELOCITY2;
+    }
+    if p.velocity.z.is_some() {
+        flags |= PFTypes::VELOCITY3;
+    }
+    if p.model.is_some() {
+        flags |= PFTypes::MODEL;
+    }
+    if p.skinnum.is_some() {
+        flags |= PFTypes::SKINNUM;
+    }
+    if p.effects.is_some() {
+        flags |= PFTypes::EFFECTS;
+    }
+    if p.weaponframe.is_some() {
+        flags |= PFTypes::WEAPONFRAME;
+    }
+
+    let mut size = message.write_u8(p.player_number);
+    size += message.write_u16(flags.bits() as u16);
+    size += message.write_coordinatevector(p.origin);
+    size += message.write_u8(p.frame);
+    if let Some(msec) = p.msec {
+        size += message.write_u8(msec);
+    }
+    if let Some(command) = &p.command {
+        size += command.write(message)?;
+    }
+    for v in [p.velocity.x, p.velocity.y, p.velocity.z]
+        .into_iter()
+        .flatten()
+    {
+        size += message.write_i16(v);
+    }
+    for v in [p.model, p.skinnum, p.effects, p.weaponframe]
+        .into_iter()
+        .flatten()
+    {
+        size += message.write_u8(v);
+    }
+    Ok(size)
+}
+
 impl Playerinfo {
     pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
         if message.r#type == MessageType::Connection {
@@ -979,36 +1298,104 @@ impl Playerinfo {
             playerinfo_read_demo(message)
         }
     }
+
+    /// reads past a demo playerinfo without building it
+    pub fn skip_demo(message: &mut Message) -> Result<(), MessageError> {
+        message.read_u8(false)?;
+        let flags = DfTypes::from_bits_truncate(message.read_u16(false)?);
+        message.read_u8(false)?;
+        for f in [DfTypes::ORIGIN, DfTypes::ORIGIN2, DfTypes::ORIGIN3] {
+            if flags.contains(f) {
+                message.read_coordinate(false)?;
+            }
+        }
+        for f in [DfTypes::ANGLE, DfTypes::ANGLE2, DfTypes::ANGLE3] {
+            if flags.contains(f) {
+                message.read_angle16(false)?;
+            }
+        }
+        for f in [
+            DfTypes::MODEL,
+            DfTypes::SKINNUM,
+            DfTypes::EFFECTS,
+            DfTypes::WEAPONFRAME,
+        ] {
+            if flags.contains(f) {
+                message.read_u8(false)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        match self {
+            Playerinfo::PlayerinfoMvdT(p) => Ok(playerinfo_write_demo(message, p)),
+            Playerinfo::PlayerinfoConnectionT(p) => playerinfo_write_connection(message, p),
+        }
+    }
 }
 
 bitflags! {
-#[derive(Serialize, Default)]
+#[derive(Serialize, Deserialize, Default)]
     pub struct FteProtocolExtensions: u32 {
+    const SETVIEW           = 0x00000001;
+    const SCALE             = 0x00000002;
+    const LIGHTSTYLECOL     = 0x00000004;
     const TRANS             = 0x00000008; // .alpha support
+    const VIEW2             = 0x00000010;
     const ACCURATETIMINGS   = 0x00000040;
+    const SOUNDDBL          = 0x00000080;
+    const FATNESS           = 0x00000100;
     const HLBSP             = 0x00000200; //stops fte servers from complaining
+    const TE_BULLET         = 0x00000400;
+    const HULLSIZE          = 0x00000800;
     const MODELDBL          = 0x00001000;
     const ENTITYDBL         = 0x00002000; //max =of 1024 ents instead of 512
     const ENTITYDBL2        = 0x00004000; //max =of 1024 ents instead of 512
     const FLOATCOORDS       = 0x00008000; //supports =floating point origins.
+    const Q2BSP             = 0x00020000;
+    const Q3BSP             = 0x00040000;
+    const COLOURMOD         = 0x00080000;
+    const SPLITSCREEN       = 0x00100000;
+    const HEXEN2            = 0x00200000;
     const SPAWNSTATIC2      = 0x00400000; //Sends =an entity delta instead of a baseline.
+    const CUSTOMTEMPEFFECTS = 0x00800000;
     const PACKETENTITIES_256 = 0x01000000; //Client =can recieve 256 packet entities.
+    const SHOWPIC           = 0x04000000;
+    const SETATTACHMENT     = 0x08000000;
     const CHUNKEDDOWNLOADS  = 0x20000000; //alternate =file download method. Hopefully it'll give quadroupled download speed, especially on higher pings.
+    const CSQC              = 0x40000000;
+    const DPFLAGS           = 0x80000000;
     }
 }
 
 bitflags! {
-#[derive(Serialize, Default)]
+#[derive(Serialize, Deserialize, Default)]
     pub struct FteProtocolExtensions2: u32 {
+        const PRYDONCURSOR = 0x00000001;
         const FTE_PEXT2_VOICECHAT = 0x00000002;
+        const SETANGLEDELTA = 0x00000004;
+        const REPLACEMENTDELTAS = 0x00000008;
+        const MAXPLAYERS = 0x00000010;
+        const PREDINFO = 0x00000020;
+        const NEWSIZEENCODING = 0x00000040;
+        const INFOBLOBS = 0x00000080;
+        const STUNAWARE = 0x00000100;
+        const VRINPUTS = 0x00000200;
+        const LERPTIME = 0x00000400;
     }
 }
 
 bitflags! {
-#[derive(Serialize,Default)]
+#[derive(Serialize, Deserialize,Default)]
     pub struct MvdProtocolExtensions: u32 {
         const FLOATCOORDS = 0x00000001; // FTE_PEXT_FLOATCOORDS but for entity/player coords only
         const HIGHLAGTELEPORT = 0x00000002; // Adjust movement direction for frames following teleport
+        const SERVERSIDEWEAPON = 0x00000004; // Server-side weapon selection
+        const DEBUG_WEAPON = 0x00000008; // Server-side weapon debugging
+        const DEBUG_ANTILAG = 0x00000010; // Antilag debugging
+        const HIDDEN_MESSAGES = 0x00000020; // dem_multiple(0) packets are in hidden message format
+        const SERVERSIDEWEAPON2 = 0x00000040; // Server-side weapon selection, extended
     }
 }
 
@@ -1050,12 +1437,186 @@ pub struct Packetentity {
     pub transparency: Option<u8>,
 }
 
+impl Packetentity {
+    // writes the delta the way the readers expect it, fte decides if the
+    // FTE_EXT (evenmore) bits and their payload are encoded
+    fn write(&self, message: &mut Message, fte: bool) -> Result<usize, MessageError> {
+        let mut bits = UpdateTypes::from_bits_truncate(self.bits) & UpdateTypes::SOLID;
+        if !fte {
+            bits |= UpdateTypes::from_bits_truncate(self.bits) & UpdateTypes::FTE_EXT;
+        }
+        if self.remove {
+            bits |= UpdateTypes::REMOVE;
+        }
+        if self.model.is_some() {
+            bits |= UpdateTypes::MODEL;
+        }
+        if self.frame.is_some() {
+            bits |= UpdateTypes::FRAME;
+        }
+        if self.colormap.is_some() {
+            bits |= UpdateTypes::COLORMAP;
+        }
+        if self.skin.is_some() {
+            bits |= UpdateTypes::SKIN;
+        }
+        if self.effects.is_some() {
+            bits |= UpdateTypes::EFFECTS;
+        }
+        let origin = self.origin.unwrap_or_default();
+        let angle = self.angle.unwrap_or_default();
+        if origin.x.is_some() {
+            bits |= UpdateTypes::ORIGIN1;
+        }
+        if origin.y.is_some() {
+            bits |= UpdateTypes::ORIGIN2;
+        }
+        if origin.z.is_some() {
+            bits |= UpdateTypes::ORIGIN3;
+        }
+        if angle.x.is_some() {
+            bits |= UpdateTypes::ANGLE1;
+        }
+        if angle.y.is_some() {
+            bits |= UpdateTypes::ANGLE2;
+        }
+        if angle.z.is_some() {
+            bits |= UpdateTypes::ANGLE3;
+        }
+
+        let mut model = self.model;
+        let mut ftebits = FteDeltaExtension::empty();
+        if fte {
+            ftebits = self.ftebits
+                - (FteDeltaExtension::TRANS
+                    | FteDeltaExtension::MODELDOUBLE
+                    | FteDeltaExtension::ENTITYDOUBLE
+                    | FteDeltaExtension::ENTITYDOUBLE2
+                    | FteDeltaExtension::YETMORE);
+            if self.transparency.is_some() {
+                ftebits |= FteDeltaExtension::TRANS;
+            }
+            if self.entity_index & 512 != 0 {
+                ftebits |= FteDeltaExtension::ENTITYDOUBLE;
+            }
+            if self.entity_index & 1024 != 0 {
+                ftebits |= FteDeltaExtension::ENTITYDOUBLE2;
+            }
+            if let Some(m) = model {
+                if m >= 512 {
+                    ftebits |= FteDeltaExtension::MODELDOUBLE;
+                    model = Some(m - 512);
+                }
+            }
+            if ftebits.bits() & 0xff00 != 0 {
+                ftebits |= FteDeltaExtension::YETMORE;
+            }
+            if !ftebits.is_empty() {
+                bits |= UpdateTypes::FTE_EXT;
+            }
+        }
+        if bits.bits() & 0xff != 0 {
+            bits |= UpdateTypes::MOREBITS;
+        }
+
+        let mut size = message.write_u16((bits.bits() & !511) | (self.entity_index & 511));
+        if bits.contains(UpdateTypes::MOREBITS) {
+            size += message.write_u8((bits.bits() & 0xff) as u8);
+        }
+        if fte && bits.contains(UpdateTypes::FTE_EXT) {
+            size += message.write_u8((ftebits.bits() & 0xff) as u8);
+            if ftebits.contains(FteDeltaExtension::YETMORE) {
+                size += message.write_u8((ftebits.bits() >> 8) as u8);
+            }
+        }
+        if let Some(m) = model {
+            let m = u8::try_from(m).map_err(|_| {
+                MessageError::StringError(format!("model index ({}) out of range", m))
+            })?;
+            size += message.write_u8(m);
+        }
+        for v in [self.frame, self.colormap, self.skin, self.effects]
+            .into_iter()
+            .flatten()
+        {
+            size += message.write_u8(v);
+        }
+        if let Some(x) = origin.x {
+            size += message.write_coordinate(x);
+        }
+        if let Some(x) = angle.x {
+            size += message.write_angle(x);
+        }
+        if let Some(y) = origin.y {
+            size += message.write_coordinate(y);
+        }
+        if let Some(y) = angle.y {
+            size += message.write_angle(y);
+        }
+        if let Some(z) = origin.z {
+            size += message.write_coordinate(z);
+        }
+        if let Some(z) = angle.z {
+            size += message.write_angle(z);
+        }
+        if fte {
+            if let Some(transparency) = self.transparency {
+                size += message.write_u8(transparency);
+            }
+        }
+        Ok(size)
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
 pub struct Packetentities {
     pub entities: Vec<Packetentity>,
 }
 
+// reads past the entity updates up to the terminating 0 without building them
+fn packetentities_skip(message: &mut Message) -> Result<(), MessageError> {
+    loop {
+        let mut bits = message.read_u16(false)?;
+        if bits == 0 {
+            return Ok(());
+        }
+        bits &= !511;
+        if UpdateTypes::from_bits_truncate(bits).contains(UpdateTypes::MOREBITS) {
+            bits |= message.read_u8(false)? as u16;
+        }
+        let flags = UpdateTypes::from_bits_truncate(bits);
+        for f in [
+            UpdateTypes::MODEL,
+            UpdateTypes::FRAME,
+            UpdateTypes::COLORMAP,
+            UpdateTypes::SKIN,
+            UpdateTypes::EFFECTS,
+        ] {
+            if flags.contains(f) {
+                message.read_u8(false)?;
+            }
+        }
+        for (origin, angle) in [
+            (UpdateTypes::ORIGIN1, UpdateTypes::ANGLE1),
+            (UpdateTypes::ORIGIN2, UpdateTypes::ANGLE2),
+            (UpdateTypes::ORIGIN3, UpdateTypes::ANGLE3),
+        ] {
+            if flags.contains(origin) {
+                message.read_coordinate(false)?;
+            }
+            if flags.contains(angle) {
+                message.read_angle(false)?;
+            }
+        }
+    }
+}
+
 impl Packetentities {
+    /// reads past the message without building the entities
+    pub fn skip(message: &mut Message) -> Result<(), MessageError> {
+        packetentities_skip(message)
+    }
+
     pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
         trace::trace_start!(message, false);
         let mut entities = Vec::new();
@@ -1187,6 +1748,15 @@ impl Packetentities {
         trace::trace_stop!(message, r);
         Ok(r)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = 0;
+        for entity in &self.entities {
+            size += entity.write(message, false)?;
+        }
+        size += message.write_u16(0_u16);
+        Ok(size)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Serialize, Clone)]
@@ -1195,6 +1765,10 @@ impl Bad {
     pub fn read(_message: &mut Message) -> Result<ServerMessage, MessageError> {
         Err(MessageError::BadRead)
     }
+
+    pub fn write(&self, _message: &mut Message) -> Result<usize, MessageError> {
+        Err(MessageError::BadWrite)
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
@@ -1375,10 +1949,14 @@ impl FteSpawnbaseline2 {
             transparency,
         };
 
-        let v = ServerMessage::SpawnstaticFte2(SpawnstaticFte2 { from, entity: p });
+        let v = ServerMessage::FteSpawnbaseline2(FteSpawnbaseline2 { from, entity: p });
         trace::trace_stop!(message, v);
         Ok(v)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        self.entity.write(message, true)
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
@@ -1546,6 +2124,10 @@ impl SpawnstaticFte2 {
         trace::trace_stop!(message, v);
         Ok(v)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        self.entity.write(message, true)
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
@@ -1555,6 +2137,12 @@ pub struct Deltapacketentities {
 }
 
 impl Deltapacketentities {
+    /// reads past the message without building the entities
+    pub fn skip(message: &mut Message) -> Result<(), MessageError> {
+        message.read_u8(false)?;
+        packetentities_skip(message)
+    }
+
     pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
         trace::trace_start!(message, false);
         let mut entities = Vec::new();
@@ -1576,10 +2164,6 @@ impl Deltapacketentities {
                 flags = UpdateTypes::from_bits_truncate(bits);
             }
 
-            if bits == 0 {
-                break;
-            }
-
             let mut remove = false;
             if flags.contains(UpdateTypes::REMOVE) {
                 remove = true;
@@ -1695,6 +2279,15 @@ impl Deltapacketentities {
         trace::trace_stop!(message, v);
         Ok(v)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = message.write_u8(self.from);
+        for entity in &self.entities {
+            size += entity.write(message, false)?;
+        }
+        size += message.write_u16(0_u16);
+        Ok(size)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
@@ -1766,6 +2359,26 @@ impl Sound {
         trace::trace_stop!(message, r);
         Ok(r)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut channel = (self.channel & !(0xc000 | (1023 << 3))) | ((self.entity & 1023) << 3);
+        if self.volume.is_some() {
+            channel |= 1 << 15;
+        }
+        if self.attenuation.is_some() {
+            channel |= 1 << 14;
+        }
+        let mut size = message.write_u16(channel);
+        if let Some(volume) = self.volume {
+            size += message.write_u8(volume);
+        }
+        if let Some(attenuation) = self.attenuation {
+            size += message.write_u8(attenuation);
+        }
+        size += message.write_u8(self.index);
+        size += message.write_coordinatevector(self.origin);
+        Ok(size)
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, ParseMessage, Serialize, Clone)]
@@ -1811,7 +2424,9 @@ pub struct Intermission {
 #[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
 pub struct Disconnect {}
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize, Clone)]
+#[derive(
+    Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize, Deserialize, Clone,
+)]
 #[repr(u8)]
 pub enum TempEntityType {
     Spike = 0,
@@ -1830,7 +2445,7 @@ pub enum TempEntityType {
     LightningBlood,
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Deserialize, Clone)]
 pub struct Tempentity {
     pub r#type: TempEntityType,
     pub origin: CoordinateVector,
@@ -1891,59 +2506,696 @@ impl Tempentity {
         trace::trace_stop!(message, r);
         Ok(r)
     }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = message.write_u8(self.r#type.clone() as u8);
+        if self.r#type == TempEntityType::Gunshot || self.r#type == TempEntityType::Blood {
+            size += message.write_i8(self.count);
+        }
+        if self.r#type == TempEntityType::Lightning1
+            || self.r#type == TempEntityType::Lightning2
+            || self.r#type == TempEntityType::Lightning3
+        {
+            size += message.write_u16(self.entity);
+            size += message.write_coordinatevector(self.start);
+        }
+        size += message.write_coordinatevector(self.origin);
+        Ok(size)
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize)]
-#[repr(u8)]
-pub enum ClientServer {
-    Bad = 0,
-    Nop = 1,
-    DoubleMove = 2,
-    Move = 3,          // [[usercmd_t]
-    StringCommand = 4, // [string] message
-    Delta = 5,
-    TMove = 6,
-    Upload = 7,
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Nop {}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Stopsound {
+    pub channel: u16,
 }
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize)]
-#[repr(u8)]
-pub enum ServerClient {
-    Bad = 0,
-    Nop = 1,
-    Disconnect = 2,
-    Updatestat = 3,      // [byte] [byte]
-    NQVersion = 4,       // [long] server version
-    Setview = 5,         // [short] entity number
-    Sound = 6,           // <see code>
-    NQTime = 7,          // [float] server time
-    Print = 8,           // [byte] id [string] null terminated string
-    Stufftext = 9,       // [string] stuffed into client's console buffer
-    Setangle = 10,       // [angle3] set the view angle to this absolute value
-    Serverdata = 11,     // [long] protocol ...
-    Lightstyle = 12,     // [byte] [string]
-    NQUpdatename = 13,   // [byte] [string]
-    Updatefrags = 14,    // [byte] [short]
-    NQClientdata = 15,   // <shortbits + data>
-    Stopsound = 16,      // <see code>
-    NQUpdatecolors = 17, // [byte] [byte] [byte]
-    NQParticle = 18,     // [vec3] <variable>
-    Damage = 19,
-    Spawnstatic = 20,
-    SpawnstaticFte2 = 21,
-    Spawnbaseline = 22,
-    Tempentity = 23,  // variable
-    Setpause = 24,    // [byte] on / off
-    NQSignonnum = 25, // [byte]  used for the signon sequence
-    Centerprint = 26, // [string] to put in center of the screen
-    Killedmonster = 27,
-    Foundsecret = 28,
-    Spawnstaticsound = 29, // [coord3] [byte] samp [byte] vol [byte] aten
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Setpause {
+    pub paused: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Killedmonster {}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Foundsecret {}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Finale {
+    pub text: StringByte,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct Sellscreen {}
+
+// NetQuake (protocol 15) only messages
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQVersion {
+    pub version: u32,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQTime {
+    pub time: f32,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQUpdatename {
+    pub player_number: u8,
+    pub name: StringByte,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQUpdatecolors {
+    pub player_number: u8,
+    pub colors: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQSignonnum {
+    pub signon: u8,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
+pub struct NQParticle {
+    pub origin: CoordinateVector,
+    pub direction: CoordinateVector,
+    pub count: u8,
+    pub color: u8,
+}
+
+impl NQParticle {
+    pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
+        trace::trace_start!(message, false);
+        trace::trace_annotate!(message, "origin");
+        let origin = message.read_coordinatevector(false)?;
+        trace::trace_annotate!(message, "direction.x");
+        let x = message.read_i8(false)? as f32 / 16.0;
+        trace::trace_annotate!(message, "direction.y");
+        let y = message.read_i8(false)? as f32 / 16.0;
+        trace::trace_annotate!(message, "direction.z");
+        let z = message.read_i8(false)? as f32 / 16.0;
+        let direction = CoordinateVector { x, y, z };
+        trace::trace_annotate!(message, "count");
+        let count = message.read_u8(false)?;
+        trace::trace_annotate!(message, "color");
+        let color = message.read_u8(false)?;
+        let r = ServerMessage::NQParticle(NQParticle {
+            origin,
+            direction,
+            count,
+            color,
+        });
+        trace::trace_stop!(message, r);
+        Ok(r)
+    }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = message.write_coordinatevector(self.origin);
+        for d in [self.direction.x, self.direction.y, self.direction.z] {
+            size += message.write_i8((d * 16.0).round().clamp(-128.0, 127.0) as i8);
+        }
+        size += message.write_u8(self.count);
+        size += message.write_u8(self.color);
+        Ok(size)
+    }
+}
+
+bitflags! {
+#[derive(Serialize, Default)]
+    pub struct NQClientdataTypes: u16 {
+        const VIEWHEIGHT = 1 << 0;
+        const IDEALPITCH = 1 << 1;
+        const PUNCH1 = 1 << 2;
+        const PUNCH2 = 1 << 3;
+        const PUNCH3 = 1 << 4;
+        const VELOCITY1 = 1 << 5;
+        const VELOCITY2 = 1 << 6;
+        const VELOCITY3 = 1 << 7;
+        const AIMENT = 1 << 8;
+        const ITEMS = 1 << 9;
+        const ONGROUND = 1 << 10; // no data follows, the bits is it
+        const INWATER = 1 << 11; // no data follows, the bits is it
+        const WEAPONFRAME = 1 << 12;
+        const ARMOR = 1 << 13;
+        const WEAPON = 1 << 14;
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Clone, Default)]
+pub struct NQClientdata {
+    pub bits: NQClientdataTypes,
+    pub view_height: Option<i8>,
+    pub ideal_pitch: Option<i8>,
+    pub punch_angle: [Option<i8>; 3],
+    pub velocity: VelocityVectorOption,
+    pub items: u32,
+    pub weaponframe: Option<u8>,
+    pub armor: Option<u8>,
+    pub weapon: Option<u8>,
+    pub health: i16,
+    pub ammo: u8,
+    pub shells: u8,
+    pub nails: u8,
+    pub rockets: u8,
+    pub cells: u8,
+    pub active_weapon: u8,
+}
+
+impl NQClientdata {
+    pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
+        trace::trace_start!(message, false);
+        trace::trace_annotate!(message, "bits");
+        let bits = NQClientdataTypes::from_bits_truncate(message.read_u16(false)?);
+        let mut c = NQClientdata {
+            bits,
+            ..Default::default()
+        };
+        if bits.contains(NQClientdataTypes::VIEWHEIGHT) {
+            trace::trace_annotate!(message, "view_height");
+            c.view_height = Some(message.read_i8(false)?);
+        }
+        if bits.contains(NQClientdataTypes::IDEALPITCH) {
+            trace::trace_annotate!(message, "ideal_pitch");
+            c.ideal_pitch = Some(message.read_i8(false)?);
+        }
+        let mut velocity = [None; 3];
+        for (i, v) in velocity.iter_mut().enumerate() {
+            if bits.bits() & (NQClientdataTypes::PUNCH1.bits() << i) != 0 {
+                trace::trace_annotate!(message, "punch_angle");
+                c.punch_angle[i] = Some(message.read_i8(false)?);
+            }
+            if bits.bits() & (NQClientdataTypes::VELOCITY1.bits() << i) != 0 {
+                trace::trace_annotate!(message, "velocity");
+                *v = Some(message.read_i8(false)? as i16 * 16);
+            }
+        }
+        c.velocity = VelocityVectorOption {
+            x: velocity[0],
+            y: velocity[1],
+            z: velocity[2],
+        };
+        // the items are always sent
+        trace::trace_annotate!(message, "items");
+        c.items = message.read_u32(false)?;
+        if bits.contains(NQClientdataTypes::WEAPONFRAME) {
+            trace::trace_annotate!(message, "weaponframe");
+            c.weaponframe = Some(message.read_u8(false)?);
+        }
+        if bits.contains(NQClientdataTypes::ARMOR) {
+            trace::trace_annotate!(message, "armor");
+            c.armor = Some(message.read_u8(false)?);
+        }
+        if bits.contains(NQClientdataTypes::WEAPON) {
+            trace::trace_annotate!(message, "weapon");
+            c.weapon = Some(message.read_u8(false)?);
+        }
+        c.health = read_field!(message, "health", i16);
+        c.ammo = read_field!(message, "ammo", u8);
+        c.shells = read_field!(message, "shells", u8);
+        c.nails = read_field!(message, "nails", u8);
+        c.rockets = read_field!(message, "rockets", u8);
+        c.cells = read_field!(message, "cells", u8);
+        c.active_weapon = read_field!(message, "active_weapon", u8);
+        let r = ServerMessage::NQClientdata(c);
+        trace::trace_stop!(message, r);
+        Ok(r)
+    }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut bits = self.bits
+            & (NQClientdataTypes::AIMENT
+                | NQClientdataTypes::ITEMS
+                | NQClientdataTypes::ONGROUND
+                | NQClientdataTypes::INWATER);
+        if self.view_height.is_some() {
+            bits |= NQClientdataTypes::VIEWHEIGHT;
+        }
+        if self.ideal_pitch.is_some() {
+            bits |= NQClientdataTypes::IDEALPITCH;
+        }
+        let velocity = [self.velocity.x, self.velocity.y, self.velocity.z];
+        for (i, v) in velocity.iter().enumerate() {
+            if self.punch_angle[i].is_some() {
+                bits |=
+                    NQClientdataTypes::from_bits_truncate(NQClientdataTypes::PUNCH1.bits() << i);
+            }
+            if v.is_some() {
+                bits |=
+                    NQClientdataTypes::from_bits_truncate(NQClientdataTypes::VELOCITY1.bits() << i);
+            }
+        }
+        if self.weaponframe.is_some() {
+            bits |= NQClientdataTypes::WEAPONFRAME;
+        }
+        if self.armor.is_some() {
+            bits |= NQClientdataTypes::ARMOR;
+        }
+        if self.weapon.is_some() {
+            bits |= NQClientdataTypes::WEAPON;
+        }
+
+        let mut size = message.write_u16(bits.bits());
+        for v in [self.view_height, self.ideal_pitch].into_iter().flatten() {
+            size += message.write_i8(v);
+        }
+        for (p, v) in self.punch_angle.iter().zip(velocity) {
+            if let Some(p) = *p {
+                size += message.write_i8(p);
+            }
+            if let Some(v) = v {
+                size += message.write_i8((v / 16) as i8);
+            }
+        }
+        size += message.write_u32(self.items);
+        for v in [self.weaponframe, self.armor, self.weapon]
+            .into_iter()
+            .flatten()
+        {
+            size += message.write_u8(v);
+        }
+        size += message.write_i16(self.health);
+        for v in [
+            self.ammo,
+            self.shells,
+            self.nails,
+            self.rockets,
+            self.cells,
+            self.active_weapon,
+        ] {
+            size += message.write_u8(v);
+        }
+        Ok(size)
+    }
+}
+
+/// svc_serverinfo, shares the opcode with [`ServerClient::Serverdata`]
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQServerinfo {
+    pub protocol: u32,
+    pub maxclients: u8,
+    pub gametype: u8,
+    pub levelname: StringByte,
+    pub models: StringVector,
+    pub sounds: StringVector,
+}
+
+/// svc_print without the print level
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQPrint {
+    pub message: StringByte,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQUpdatestat {
+    pub stat: u8,
+    pub value: i32,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQCdtrack {
+    pub track: u8,
+    pub loop_track: u8,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQSetangle {
+    pub angle: AngleVector,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQIntermission {}
+
+/// svc_cutscene, shares the opcode with [`ServerClient::Smallkick`]
+#[derive(Debug, PartialEq, Eq, PartialOrd, ParseMessage, Serialize, Clone)]
+pub struct NQCutscene {
+    pub text: StringByte,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
+pub struct NQSound {
+    pub channel: u16,
+    pub entity: u16,
+    pub index: u8,
+    pub volume: Option<u8>,
+    pub attenuation: Option<u8>,
+    pub origin: CoordinateVector,
+}
+
+impl NQSound {
+    pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
+        trace::trace_start!(message, false);
+        trace::trace_annotate!(message, "field_mask");
+        let field_mask = message.read_u8(false)?;
+        let mut volume = None;
+        if field_mask & 1 != 0 {
+            trace::trace_annotate!(message, "volume");
+            volume = Some(message.read_u8(false)?);
+        }
+        let mut attenuation = None;
+        if field_mask & 2 != 0 {
+            trace::trace_annotate!(message, "attenuation");
+            attenuation = Some(message.read_u8(false)?);
+        }
+        trace::trace_annotate!(message, "channel");
+        let channel = message.read_u16(false)?;
+        trace::trace_annotate!(message, "index");
+        let index = message.read_u8(false)?;
+        trace::trace_annotate!(message, "origin");
+        let origin = message.read_coordinatevector(false)?;
+        let r = ServerMessage::NQSound(NQSound {
+            channel: channel & 7,
+            entity: channel >> 3,
+            index,
+            volume,
+            attenuation,
+            origin,
+        });
+        trace::trace_stop!(message, r);
+        Ok(r)
+    }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut field_mask = 0_u8;
+        if self.volume.is_some() {
+            field_mask |= 1;
+        }
+        if self.attenuation.is_some() {
+            field_mask |= 2;
+        }
+        let mut size = message.write_u8(field_mask);
+        for v in [self.volume, self.attenuation].into_iter().flatten() {
+            size += message.write_u8(v);
+        }
+        size += message.write_u16((self.channel & 7) | (self.entity << 3));
+        size += message.write_u8(self.index);
+        size += message.write_coordinatevector(self.origin);
+        Ok(size)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize, Clone)]
+#[repr(u8)]
+pub enum NQTempEntityType {
+    Spike = 0,
+    SuperSpike,
+    Gunshot,
+    Explosion,
+    Tarexplosion,
+    Lightning1,
+    Lightning2,
+    Wizspike,
+    Knightspike,
+    Lightning3,
+    LavaSplash,
+    Teleport,
+    Explosion2,
+    Beam,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Clone)]
+pub struct NQTempentity {
+    pub r#type: NQTempEntityType,
+    pub origin: CoordinateVector,
+    pub start: CoordinateVector,
+    pub entity: u16,
+    pub color_start: u8,
+    pub color_length: u8,
+}
+
+impl NQTempentity {
+    fn has_start(&self) -> bool {
+        matches!(
+            self.r#type,
+            NQTempEntityType::Lightning1
+                | NQTempEntityType::Lightning2
+                | NQTempEntityType::Lightning3
+                | NQTempEntityType::Beam
+        )
+    }
+
+    pub fn read(message: &mut Message) -> Result<ServerMessage, MessageError> {
+        trace::trace_start!(message, false);
+        trace::trace_annotate!(message, "entity type");
+        let t = message.read_u8(false)?;
+        let mut te = NQTempentity {
+            r#type: NQTempEntityType::try_from(t)?,
+            origin: CoordinateVector::default(),
+            start: CoordinateVector::default(),
+            entity: 0,
+            color_start: 0,
+            color_length: 0,
+        };
+        if te.has_start() {
+            trace::trace_annotate!(message, "entity");
+            te.entity = message.read_u16(false)?;
+            trace::trace_annotate!(message, "start");
+            te.start = message.read_coordinatevector(false)?;
+        }
+        trace::trace_annotate!(message, "origin");
+        te.origin = message.read_coordinatevector(false)?;
+        if te.r#type == NQTempEntityType::Explosion2 {
+            te.color_start = read_field!(message, "color_start", u8);
+            te.color_length = read_field!(message, "color_length", u8);
+        }
+        let r = ServerMessage::NQTempentity(te);
+        trace::trace_stop!(message, r);
+        Ok(r)
+    }
+
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = message.write_u8(self.r#type.clone() as u8);
+        if self.has_start() {
+            size += message.write_u16(self.entity);
+            size += message.write_coordinatevector(self.start);
+        }
+        size += message.write_coordinatevector(self.origin);
+        if self.r#type == NQTempEntityType::Explosion2 {
+            size += message.write_u8(self.color_start);
+            size += message.write_u8(self.color_length);
+        }
+        Ok(size)
+    }
+}
+
+bitflags! {
+#[derive(Serialize, Default)]
+    pub struct NQUpdateTypes: u16 {
+        const MOREBITS = 1 << 0;
+        const ORIGIN1 = 1 << 1;
+        const ORIGIN2 = 1 << 2;
+        const ORIGIN3 = 1 << 3;
+        const ANGLE2 = 1 << 4;
+        const NOLERP = 1 << 5;
+        const FRAME = 1 << 6;
+        const SIGNAL = 1 << 7;
+        const ANGLE1 = 1 << 8;
+        const ANGLE3 = 1 << 9;
+        const MODEL = 1 << 10;
+        const COLORMAP = 1 << 11;
+        const SKIN = 1 << 12;
+        const EFFECTS = 1 << 13;
+        const LONGENTITY = 1 << 14;
+    }
+}
+
+/// fast entity update, any message byte with [`NQUpdateTypes::SIGNAL`] set
+#[derive(Debug, PartialEq, PartialOrd, Serialize, Clone, Default)]
+pub struct NQEntityUpdate {
+    pub bits: NQUpdateTypes,
+    pub entity_index: u16,
+    pub model: Option<u8>,
+    pub frame: Option<u8>,
+    pub colormap: Option<u8>,
+    pub skin: Option<u8>,
+    pub effects: Option<u8>,
+    pub origin: CoordinateVectorOption,
+    pub angle: AngleVectorOption,
+}
+
+impl NQEntityUpdate {
+    /// cmd is the already read message byte, it holds the low bits
+    pub fn read(message: &mut Message, cmd: u8) -> Result<ServerMessage, MessageError> {
+        trace::trace_start!(message, false);
+        let mut bits = (cmd & 127) as u16;
+        if bits & NQUpdateTypes::MOREBITS.bits() != 0 {
+            trace::trace_annotate!(message, "morebits");
+            bits |= (message.read_u8(false)? as u16) << 8;
+        }
+        let bits = NQUpdateTypes::from_bits_truncate(bits) | NQUpdateTypes::SIGNAL;
+        let mut e = NQEntityUpdate {
+            bits,
+            ..Default::default()
+        };
+        trace::trace_annotate!(message, "entity_index");
+        if bits.contains(NQUpdateTypes::LONGENTITY) {
+            e.entity_index = message.read_u16(false)?;
+        } else {
+            e.entity_index = message.read_u8(false)? as u16;
+        }
+        if bits.contains(NQUpdateTypes::MODEL) {
+            e.model = Some(read_field!(message, "model", u8));
+        }
+        if bits.contains(NQUpdateTypes::FRAME) {
+            e.frame = Some(read_field!(message, "frame", u8));
+        }
+        if bits.contains(NQUpdateTypes::COLORMAP) {
+            e.colormap = Some(read_field!(message, "colormap", u8));
+        }
+        if bits.contains(NQUpdateTypes::SKIN) {
+            e.skin = Some(read_field!(message, "skin", u8));
+        }
+        if bits.contains(NQUpdateTypes::EFFECTS) {
+            e.effects = Some(read_field!(message, "effects", u8));
+        }
+        if bits.contains(NQUpdateTypes::ORIGIN1) {
+            e.origin.x = Some(read_field!(message, "origin.x", coordinate));
+        }
+        if bits.contains(NQUpdateTypes::ANGLE1) {
+            e.angle.x = Some(read_field!(message, "angle.x", angle));
+        }
+        if bits.contains(NQUpdateTypes::ORIGIN2) {
+            e.origin.y = Some(read_field!(message, "origin.y", coordinate));
+        }
+        if bits.contains(NQUpdateTypes::ANGLE2) {
+            e.angle.y = Some(read_field!(message, "angle.y", angle));
+        }
+        if bits.contains(NQUpdateTypes::ORIGIN3) {
+            e.origin.z = Some(read_field!(message, "origin.z", coordinate));
+        }
+        if bits.contains(NQUpdateTypes::ANGLE3) {
+            e.angle.z = Some(read_field!(message, "angle.z", angle));
+        }
+        let r = ServerMessage::NQEntityUpdate(e);
+        trace::trace_stop!(message, r);
+        Ok(r)
+    }
+
+    /// writes the message byte as well, as it carries the update bits
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut bits = (self.bits & NQUpdateTypes::NOLERP) | NQUpdateTypes::SIGNAL;
+        if self.entity_index > 255 {
+            bits |= NQUpdateTypes::LONGENTITY;
+        }
+        for (set, flag) in [
+            (self.model.is_some(), NQUpdateTypes::MODEL),
+            (self.frame.is_some(), NQUpdateTypes::FRAME),
+            (self.colormap.is_some(), NQUpdateTypes::COLORMAP),
+            (self.skin.is_some(), NQUpdateTypes::SKIN),
+            (self.effects.is_some(), NQUpdateTypes::EFFECTS),
+            (self.origin.x.is_some(), NQUpdateTypes::ORIGIN1),
+            (self.origin.y.is_some(), NQUpdateTypes::ORIGIN2),
+            (self.origin.z.is_some(), NQUpdateTypes::ORIGIN3),
+            (self.angle.x.is_some(), NQUpdateTypes::ANGLE1),
+            (self.angle.y.is_some(), NQUpdateTypes::ANGLE2),
+            (self.angle.z.is_some(), NQUpdateTypes::ANGLE3),
+        ] {
+            if set {
+                bits |= flag;
+            }
+        }
+        if bits.bits() & 0xff00 != 0 {
+            bits |= NQUpdateTypes::MOREBITS;
+        }
+
+        let mut size = message.write_u8((bits.bits() & 0xff) as u8);
+        if bits.contains(NQUpdateTypes::MOREBITS) {
+            size += message.write_u8((bits.bits() >> 8) as u8);
+        }
+        if bits.contains(NQUpdateTypes::LONGENTITY) {
+            size += message.write_u16(self.entity_index);
+        } else {
+            size += message.write_u8(self.entity_index as u8);
+        }
+        for v in [
+            self.model,
+            self.frame,
+            self.colormap,
+            self.skin,
+            self.effects,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            size += message.write_u8(v);
+        }
+        if let Some(x) = self.origin.x {
+            size += message.write_coordinate(x);
+        }
+        if let Some(x) = self.angle.x {
+            size += message.write_angle(x);
+        }
+        if let Some(y) = self.origin.y {
+            size += message.write_coordinate(y);
+        }
+        if let Some(y) = self.angle.y {
+            size += message.write_angle(y);
+        }
+        if let Some(z) = self.origin.z {
+            size += message.write_coordinate(z);
+        }
+        if let Some(z) = self.angle.z {
+            size += message.write_angle(z);
+        }
+        Ok(size)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize)]
+#[repr(u8)]
+pub enum ClientServer {
+    Bad = 0,
+    Nop = 1,
+    DoubleMove = 2,
+    Move = 3,          // [[usercmd_t]
+    StringCommand = 4, // [string] message
+    Delta = 5,
+    TMove = 6,
+    Upload = 7,
+}
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, TryFromPrimitive, Display, Serialize)]
+#[repr(u8)]
+pub enum ServerClient {
+    Bad = 0,
+    Nop = 1,
+    Disconnect = 2,
+    Updatestat = 3,      // [byte] [byte]
+    NQVersion = 4,       // [long] server version
+    Setview = 5,         // [short] entity number
+    Sound = 6,           // <see code>
+    NQTime = 7,          // [float] server time
+    Print = 8,           // [byte] id [string] null terminated string
+    Stufftext = 9,       // [string] stuffed into client's console buffer
+    Setangle = 10,       // [angle3] set the view angle to this absolute value
+    Serverdata = 11,     // [long] protocol ...
+    Lightstyle = 12,     // [byte] [string]
+    NQUpdatename = 13,   // [byte] [string]
+    Updatefrags = 14,    // [byte] [short]
+    NQClientdata = 15,   // <shortbits + data>
+    Stopsound = 16,      // <see code>
+    NQUpdatecolors = 17, // [byte] [byte] [byte]
+    NQParticle = 18,     // [vec3] <variable>
+    Damage = 19,
+    Spawnstatic = 20,
+    SpawnstaticFte2 = 21,
+    Spawnbaseline = 22,
+    Tempentity = 23,  // variable
+    Setpause = 24,    // [byte] on / off
+    NQSignonnum = 25, // [byte]  used for the signon sequence
+    Centerprint = 26, // [string] to put in center of the screen
+    Killedmonster = 27,
+    Foundsecret = 28,
+    Spawnstaticsound = 29, // [coord3] [byte] samp [byte] vol [byte] aten
     Intermission = 30,     // [vec3_t] origin [vec3_t] angle
     Finale = 31,           // [string] text
     Cdtrack = 32,          // [byte] track
     Sellscreen = 33,
-    //NQCutscene         = 34, // same as svc_smallkick
+    // NetQuake svc_cutscene is 34 as well, see NQCutscene
     Smallkick = 34,           // set client punchangle to 2
     Bigkick = 35,             // set client punchangle to 4
     Updateping = 36,          // [byte] [short]
@@ -1990,6 +3242,17 @@ pub struct Connected {
     pub messages: Vec<ServerMessage>,
 }
 
+impl Connected {
+    pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
+        let mut size = message.write_u32(self.sequence);
+        size += message.write_u32(self.sequence_ack);
+        for m in &self.messages {
+            size += m.write(message)?;
+        }
+        Ok(size)
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Display, Serialize, Clone)]
 pub enum Packet {
     Error,
@@ -1999,70 +3262,672 @@ pub enum Packet {
 }
 
 macro_rules! initialize_message_type {
-    ($($ty:ident), *) => {
-        paste! {
-            #[derive(Debug, PartialEq, PartialOrd, Display, Serialize, Clone)]
-            #[non_exhaustive]
-            pub enum ServerMessage {
-                $(
-                [< $ty >]([< $ty >]),
-                )*
-            }
+    // types read by their ServerClient, NetQuake only types (including the ones sharing a
+    // ServerClient with a different layout) and types that write their own message byte
+    ([$($ty:ident), *], [$($nq:ident => $nq_svc:ident), *], [$($raw:ident), *]) => {
+        #[derive(Debug, PartialEq, PartialOrd, Display, Serialize, Clone)]
+        #[non_exhaustive]
+        pub enum ServerMessage {
+            $(
+            $ty($ty),
+            )*
+            $(
+            $nq($nq),
+            )*
+            $(
+            $raw($raw),
+            )*
+        }
+
         impl ServerClient {
-            pub fn  read_message  (self,  message: &mut Message) ->  Result<ServerMessage, MessageError> {
+            pub fn read_message(self, message: &mut Message) -> Result<ServerMessage, MessageError> {
+                if message.r#type == MessageType::NetQuake {
+                    match self {
+                        $(
+                            ServerClient::$nq_svc => return $nq::read(message),
+                        )*
+                        _ => {}
+                    }
+                }
+                match self {
+                    $(
+                        ServerClient::$ty => $ty::read(message),
+                    )*
+                    _ => Err(MessageError::UnhandledType(self)),
+                }
+            }
+        }
+
+        impl ServerMessage {
+            pub fn write(&self, message: &mut Message) -> Result<usize, MessageError> {
                 match self {
                     $(
-                        ServerClient::[<$ty>] => {
-                            let v = [< $ty >]::read(message);
-                            return v;
+                        ServerMessage::$ty(v) => {
+                            let size = message.write_u8(ServerClient::$ty as u8);
+                            Ok(size + v.write(message)?)
                         }
-                     )*
-                        _ => {
-                            return Err(MessageError::UnhandledType(self))
+                    )*
+                    $(
+                        ServerMessage::$nq(v) => {
+                            let size = message.write_u8(ServerClient::$nq_svc as u8);
+                            Ok(size + v.write(message)?)
                         }
+                    )*
+                    $(
+                        ServerMessage::$raw(v) => v.write(message),
+                    )*
                 }
             }
         }
-        }
-    }
+    };
 }
 
 initialize_message_type!(
-    Serverdata,
-    Soundlist,
-    Modellist,
-    Cdtrack,
-    Stufftext,
-    Spawnstatic,
-    Spawnbaseline,
-    Spawnstaticsound,
-    Updatefrags,
-    Updateping,
-    Updatepl,
-    Updateentertime,
-    Updateuserinfo,
-    Playerinfo,
-    Updatestatlong,
-    Updatestat,
-    Lightstyle,
-    Serverinfo,
-    Centerprint,
-    Packetentities,
-    Deltapacketentities,
-    Tempentity,
-    Setinfo,
-    Print,
-    Sound,
-    Damage,
-    Setangle,
-    Smallkick,
-    Bigkick,
-    Muzzleflash,
-    Chokecount,
-    Intermission,
-    Disconnect,
-    Setview,
-    SpawnstaticFte2,
-    Bad,
-    FteSpawnbaseline2
+    [
+        Serverdata,
+        Soundlist,
+        Modellist,
+        Cdtrack,
+        Stufftext,
+        Spawnstatic,
+        Spawnbaseline,
+        Spawnstaticsound,
+        Updatefrags,
+        Updateping,
+        Updatepl,
+        Updateentertime,
+        Updateuserinfo,
+        Playerinfo,
+        Updatestatlong,
+        Updatestat,
+        Lightstyle,
+        Serverinfo,
+        Centerprint,
+        Packetentities,
+        Deltapacketentities,
+        Tempentity,
+        Setinfo,
+        Print,
+        Sound,
+        Damage,
+        Setangle,
+        Smallkick,
+        Bigkick,
+        Muzzleflash,
+        Chokecount,
+        Intermission,
+        Disconnect,
+        Setview,
+        SpawnstaticFte2,
+        Bad,
+        FteSpawnbaseline2,
+        Nop,
+        Stopsound,
+        Setpause,
+        Killedmonster,
+        Foundsecret,
+        Finale,
+        Sellscreen
+    ],
+    [
+        NQVersion => NQVersion,
+        NQTime => NQTime,
+        NQUpdatename => NQUpdatename,
+        NQClientdata => NQClientdata,
+        NQUpdatecolors => NQUpdatecolors,
+        NQParticle => NQParticle,
+        NQSignonnum => NQSignonnum,
+        NQServerinfo => Serverdata,
+        NQPrint => Print,
+        NQSound => Sound,
+        NQUpdatestat => Updatestat,
+        NQCdtrack => Cdtrack,
+        NQSetangle => Setangle,
+        NQIntermission => Intermission,
+        NQCutscene => Smallkick,
+        NQTempentity => Tempentity
+    ],
+    [NQEntityUpdate]
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(bytes: &[u8], flags: MessageFlags, r#type: MessageType) -> Message {
+        Message {
+            buffer: bytes.to_vec().into(),
+            length: bytes.len(),
+            flags,
+            r#type,
+            ..Message::empty()
+        }
+    }
+
+    fn writer(flags: MessageFlags, r#type: MessageType) -> Message {
+        Message {
+            flags,
+            r#type,
+            ..Message::empty()
+        }
+    }
+
+    // reads every message in bytes, writes them back and checks that the
+    // produced bytes and the reparsed messages are identical
+    fn round_trip(bytes: &[u8], flags: MessageFlags, r#type: MessageType) {
+        let mut message = reader(bytes, flags, r#type);
+        let mut messages = vec![];
+        while message.position < message.length {
+            messages.push(read(&mut message));
+        }
+
+        let mut out = writer(flags, r#type);
+        let mut size = 0;
+        for m in &messages {
+            size += m.write(&mut out).unwrap();
+        }
+        let written: Vec<u8> = out.into();
+        assert_eq!(size, written.len());
+        assert_eq!(written, bytes);
+
+        let mut message = reader(&written, flags, r#type);
+        for m in &messages {
+            assert_eq!(&read(&mut message), m);
+        }
+    }
+
+    fn read(message: &mut Message) -> ServerMessage {
+        let t = message.read_u8(false).unwrap();
+        if message.r#type == MessageType::NetQuake && t & NQUpdateTypes::SIGNAL.bits() as u8 != 0 {
+            return NQEntityUpdate::read(message, t).unwrap();
+        }
+        let cmd = ServerClient::try_from(t).unwrap();
+        cmd.read_message(message).unwrap()
+    }
+
+    struct Bytes(Vec<u8>);
+    impl Bytes {
+        fn u8(mut self, v: u8) -> Self {
+            self.0.push(v);
+            self
+        }
+        fn u16(mut self, v: u16) -> Self {
+            self.0.extend_from_slice(&v.to_le_bytes());
+            self
+        }
+        fn u32(mut self, v: u32) -> Self {
+            self.0.extend_from_slice(&v.to_le_bytes());
+            self
+        }
+        fn f32(mut self, v: f32) -> Self {
+            self.0.extend_from_slice(&v.to_le_bytes());
+            self
+        }
+        fn string(mut self, v: &str) -> Self {
+            self.0.extend_from_slice(v.as_bytes());
+            self.0.push(0);
+            self
+        }
+    }
+
+    #[test]
+    fn round_trip_connection_messages() {
+        let flags = MessageFlags {
+            protocol: 28,
+            ..Default::default()
+        };
+        let mut b = Bytes(vec![])
+            .u8(ServerClient::Serverdata as u8)
+            .u32(ProtocolVersion::Standard as u32)
+            .u32(3)
+            .string("qw")
+            .u8(4)
+            .string("dm3");
+        for i in 0..10 {
+            b = b.f32(i as f32 * 10.0);
+        }
+        let b = b
+            .u8(ServerClient::Soundlist as u8)
+            .u8(0)
+            .string("player/death1.wav")
+            .string("weapons/ax1.wav")
+            .u8(0)
+            .u8(0)
+            .u8(ServerClient::Modellist as u8)
+            .u8(0)
+            .string("maps/dm3.bsp")
+            .u8(0)
+            .u8(0)
+            .u8(ServerClient::Stufftext as u8)
+            .string("fullserverinfo \"\\maxclients\\8\"\n")
+            .u8(ServerClient::Spawnbaseline as u8)
+            .u16(12)
+            .u8(3)
+            .u8(1)
+            .u8(0)
+            .u8(2)
+            .u16(0xfff0)
+            .u8(64)
+            .u16(0x0010)
+            .u8(128)
+            .u16(0x0200)
+            .u8(255)
+            .u8(ServerClient::Spawnstatic as u8)
+            .u8(5)
+            .u8(0)
+            .u8(0)
+            .u8(0)
+            .u16(8)
+            .u8(0)
+            .u16(16)
+            .u8(0)
+            .u16(24)
+            .u8(0)
+            .u8(ServerClient::Updatefrags as u8)
+            .u8(2)
+            .u16((-3_i16) as u16)
+            .u8(ServerClient::Playerinfo as u8)
+            .u8(1)
+            .u16(
+                (PFTypes::MSEC | PFTypes::COMMAND | PFTypes::VELOCITY3 | PFTypes::ONGROUND).bits()
+                    as u16,
+            )
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u8(6)
+            .u8(13)
+            .u8(
+                (UserCommandFlags::ANGLE2 | UserCommandFlags::FORWARD | UserCommandFlags::BUTTONS)
+                    .bits(),
+            )
+            .u16(0x4000)
+            .u16(400)
+            .u8(1)
+            .u8(13)
+            .u16((-270_i16) as u16)
+            .u8(ServerClient::Packetentities as u8)
+            .u16(UpdateTypes::ORIGIN1.bits() | UpdateTypes::MOREBITS.bits() | 33)
+            .u8((UpdateTypes::MODEL | UpdateTypes::SOLID).bits() as u8)
+            .u8(7)
+            .u16(100)
+            .u16(UpdateTypes::REMOVE.bits() | 34)
+            .u16(0)
+            .u8(ServerClient::Deltapacketentities as u8)
+            .u8(17)
+            .u16(UpdateTypes::FRAME.bits() | UpdateTypes::ANGLE2.bits() | 40)
+            .u8(2)
+            .u8(64)
+            .u16(0)
+            .u8(ServerClient::Sound as u8)
+            .u16((1 << 15) | (33 << 3) | 2)
+            .u8(200)
+            .u8(4)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u8(ServerClient::Tempentity as u8)
+            .u8(TempEntityType::Gunshot as u8)
+            .u8(3)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u8(ServerClient::Tempentity as u8)
+            .u8(TempEntityType::Lightning2 as u8)
+            .u16(3)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u16(80)
+            .u16(160)
+            .u16(240)
+            .u8(ServerClient::Print as u8)
+            .u8(2)
+            .string("player1 rides player2's rocket\n")
+            .u8(ServerClient::Setangle as u8)
+            .u8(1)
+            .u8(0)
+            .u8(64)
+            .u8(0)
+            .u8(ServerClient::Intermission as u8)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u8(10)
+            .u8(20)
+            .u8(30)
+            .u8(ServerClient::Smallkick as u8);
+        round_trip(&b.0, flags, MessageType::Connection);
+    }
+
+    #[test]
+    fn round_trip_netquake_messages() {
+        let flags = MessageFlags {
+            protocol: 15,
+            ..Default::default()
+        };
+        let mut b = Bytes(vec![])
+            .u8(ServerClient::Serverdata as u8)
+            .u32(15)
+            .u8(16)
+            .u8(1)
+            .string("the Necropolis")
+            .string("maps/e1m3.bsp")
+            .string("progs/player.mdl")
+            .u8(0)
+            .string("weapons/ric1.wav")
+            .u8(0)
+            .u8(ServerClient::NQVersion as u8)
+            .u32(15)
+            .u8(ServerClient::NQSignonnum as u8)
+            .u8(2)
+            .u8(ServerClient::NQTime as u8)
+            .f32(12.25)
+            .u8(ServerClient::Print as u8)
+            .string("hello")
+            .u8(ServerClient::NQUpdatename as u8)
+            .u8(1)
+            .string("player")
+            .u8(ServerClient::NQUpdatecolors as u8)
+            .u8(1)
+            .u8(0x4d)
+            .u8(ServerClient::Updatestat as u8)
+            .u8(6)
+            .u32(300)
+            .u8(ServerClient::Cdtrack as u8)
+            .u8(3)
+            .u8(3)
+            .u8(ServerClient::Setangle as u8)
+            .u8(0)
+            .u8(64)
+            .u8(0)
+            .u8(ServerClient::Sound as u8)
+            .u8(3)
+            .u8(255)
+            .u8(64)
+            .u16(7 << 3 | 2)
+            .u8(4)
+            .u16(8)
+            .u16(16)
+            .u16(0xfff8)
+            .u8(ServerClient::NQParticle as u8)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u8(16)
+            .u8(0xf0)
+            .u8(0)
+            .u8(20)
+            .u8(73)
+            .u8(ServerClient::Tempentity as u8)
+            .u8(NQTempEntityType::Lightning2 as u8)
+            .u16(5)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u16(32)
+            .u16(40)
+            .u16(48)
+            .u8(ServerClient::Tempentity as u8)
+            .u8(NQTempEntityType::Explosion2 as u8)
+            .u16(8)
+            .u16(16)
+            .u16(24)
+            .u8(80)
+            .u8(8)
+            .u8(ServerClient::NQClientdata as u8)
+            .u16(
+                (NQClientdataTypes::VIEWHEIGHT
+                    | NQClientdataTypes::PUNCH2
+                    | NQClientdataTypes::VELOCITY1
+                    | NQClientdataTypes::VELOCITY3
+                    | NQClientdataTypes::ITEMS
+                    | NQClientdataTypes::ONGROUND
+                    | NQClientdataTypes::ARMOR
+                    | NQClientdataTypes::WEAPON)
+                    .bits(),
+            )
+            .u8(22)
+            .u8(20)
+            .u8(0xfe)
+            .u8(0xf0)
+            .u32(4097)
+            .u8(100)
+            .u8(2)
+            .u16(100)
+            .u8(25)
+            .u8(25)
+            .u8(0)
+            .u8(0)
+            .u8(0)
+            .u8(1);
+        let bits = NQUpdateTypes::SIGNAL
+            | NQUpdateTypes::MOREBITS
+            | NQUpdateTypes::ORIGIN2
+            | NQUpdateTypes::FRAME
+            | NQUpdateTypes::ANGLE1
+            | NQUpdateTypes::MODEL
+            | NQUpdateTypes::SKIN;
+        b = b
+            .u8((bits.bits() & 0xff) as u8)
+            .u8((bits.bits() >> 8) as u8)
+            .u8(12)
+            .u8(3)
+            .u8(9)
+            .u8(1)
+            .u8(64)
+            .u16(0xff00);
+        let b = b
+            .u8(ServerClient::Intermission as u8)
+            .u8(ServerClient::Finale as u8)
+            .string("the end")
+            .u8(ServerClient::Smallkick as u8)
+            .string("the cutscene")
+            .u8(ServerClient::Killedmonster as u8)
+            .u8(ServerClient::Foundsecret as u8)
+            .u8(ServerClient::Stopsound as u8)
+            .u16(7 << 3 | 1)
+            .u8(ServerClient::Setpause as u8)
+            .u8(1)
+            .u8(ServerClient::Nop as u8)
+            .u8(ServerClient::Disconnect as u8);
+        round_trip(&b.0, flags, MessageType::NetQuake);
+
+        let mut message = reader(&b.0, flags, MessageType::NetQuake);
+        let mut messages = vec![];
+        while message.position < message.length {
+            messages.push(read(&mut message));
+        }
+        assert!(matches!(&messages[0], ServerMessage::NQServerinfo(s) if s.models.len() == 2));
+        assert!(matches!(&messages[4], ServerMessage::NQPrint(_)));
+        assert_eq!(
+            messages[7],
+            ServerMessage::NQUpdatestat(NQUpdatestat {
+                stat: 6,
+                value: 300
+            })
+        );
+        match &messages[10] {
+            ServerMessage::NQSound(s) => {
+                assert_eq!((s.entity, s.channel), (7, 2));
+                assert_eq!(s.origin.z, -1.0);
+            }
+            m => panic!("expected a sound, got {:?}", m),
+        }
+        match &messages[11] {
+            ServerMessage::NQParticle(p) => {
+                assert_eq!(p.direction.x, 1.0);
+                assert_eq!(p.direction.y, -1.0);
+            }
+            m => panic!("expected a particle, got {:?}", m),
+        }
+        match &messages[14] {
+            ServerMessage::NQClientdata(c) => {
+                assert_eq!(c.view_height, Some(22));
+                assert_eq!(c.punch_angle, [None, Some(-2), None]);
+                assert_eq!(c.velocity.x, Some(320));
+                assert_eq!(c.velocity.z, Some(-256));
+                assert_eq!(c.items, 4097);
+                assert_eq!(c.health, 100);
+            }
+            m => panic!("expected clientdata, got {:?}", m),
+        }
+        match &messages[15] {
+            ServerMessage::NQEntityUpdate(e) => {
+                assert_eq!(e.entity_index, 12);
+                assert_eq!((e.model, e.frame, e.skin), (Some(3), Some(9), Some(1)));
+                assert_eq!(e.origin.y, Some(-32.0));
+            }
+            m => panic!("expected an entity update, got {:?}", m),
+        }
+        assert!(
+            matches!(&messages[18], ServerMessage::NQCutscene(c) if c.text.bytes == b"the cutscene")
+        );
+    }
+
+    #[test]
+    fn netquake_only_messages() {
+        // the NetQuake opcodes mean something else or nothing in qw and mvd streams
+        for t in [4, 7, 13, 15, 17, 18, 25] {
+            for r#type in [MessageType::Connection, MessageType::Mvd] {
+                let mut message = reader(&[0; 16], MessageFlags::default(), r#type);
+                let cmd = ServerClient::try_from(t).unwrap();
+                assert!(matches!(
+                    cmd.read_message(&mut message),
+                    Err(MessageError::UnhandledType(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn netquake_cutscene() {
+        let b = Bytes(vec![])
+            .u8(ServerClient::Smallkick as u8)
+            .string("x")
+            .u8(ServerClient::Nop as u8);
+        let mut message = reader(&b.0, MessageFlags::default(), MessageType::NetQuake);
+        assert!(matches!(read(&mut message), ServerMessage::NQCutscene(_)));
+        assert!(matches!(read(&mut message), ServerMessage::Nop(_)));
+
+        // a qw smallkick has no payload
+        let mut message = reader(&b.0, MessageFlags::default(), MessageType::Connection);
+        assert!(matches!(read(&mut message), ServerMessage::Smallkick(_)));
+        assert_eq!(message.position, 1);
+    }
+
+    #[test]
+    fn round_trip_mvd_fte_messages() {
+        let flags = MessageFlags {
+            protocol: 28,
+            fte_protocol_extensions: FteProtocolExtensions::TRANS
+                | FteProtocolExtensions::FLOATCOORDS
+                | FteProtocolExtensions::SPAWNSTATIC2,
+            mvd_protocol_extension: MvdProtocolExtensions::FLOATCOORDS,
+            ..Default::default()
+        };
+        let mut b = Bytes(vec![])
+            .u8(ServerClient::Serverdata as u8)
+            .u32(ProtocolVersion::Fte as u32)
+            .u32(flags.fte_protocol_extensions.bits())
+            .u32(ProtocolVersion::Mvd1 as u32)
+            .u32(flags.mvd_protocol_extension.bits())
+            .u32(ProtocolVersion::Standard as u32)
+            .u32(3)
+            .string("qw")
+            .f32(12.5)
+            .string("dm3");
+        for i in 0..10 {
+            b = b.f32(i as f32);
+        }
+        let b = b
+            .u8(ServerClient::Playerinfo as u8)
+            .u8(3)
+            .u16(
+                (DfTypes::ORIGIN
+                    | DfTypes::ORIGIN3
+                    | DfTypes::ANGLE2
+                    | DfTypes::DEAD
+                    | DfTypes::MODEL)
+                    .bits(),
+            )
+            .u8(41)
+            .f32(-512.25)
+            .f32(24.5)
+            .u16(0x8000)
+            .u8(9)
+            .u8(ServerClient::SpawnstaticFte2 as u8)
+            .u16(UpdateTypes::MOREBITS.bits() | UpdateTypes::ORIGIN2.bits() | 5)
+            .u8((UpdateTypes::MODEL | UpdateTypes::FTE_EXT).bits() as u8)
+            .u8((FteDeltaExtension::TRANS
+                | FteDeltaExtension::ENTITYDOUBLE
+                | FteDeltaExtension::MODELDOUBLE)
+                .bits() as u8)
+            .u8(2)
+            .f32(100.125)
+            .u8(128)
+            .u8(ServerClient::FteSpawnbaseline2 as u8)
+            .u16(UpdateTypes::FRAME.bits() | 9)
+            .u8(3)
+            .u8(ServerClient::Damage as u8)
+            .u8(10)
+            .u8(20)
+            .f32(1.5)
+            .f32(2.5)
+            .f32(3.5);
+        round_trip(&b.0, flags, MessageType::Mvd);
+    }
+
+    #[test]
+    fn skip_messages() {
+        let entities = |b: Bytes| {
+            b.u16(UpdateTypes::MOREBITS.bits() | UpdateTypes::ORIGIN1.bits() | 7)
+                .u8((UpdateTypes::ANGLE1 | UpdateTypes::MODEL | UpdateTypes::EFFECTS).bits() as u8)
+                .u8(1)
+                .u8(2)
+                .u16(100)
+                .u8(64)
+                .u16(UpdateTypes::FRAME.bits() | UpdateTypes::ORIGIN3.bits() | 8)
+                .u8(3)
+                .u16(200)
+                .u16(UpdateTypes::REMOVE.bits() | 9)
+                .u16(0)
+        };
+        let playerinfo = Bytes(vec![])
+            .u8(3)
+            .u16((DfTypes::ORIGIN2 | DfTypes::ANGLE | DfTypes::WEAPONFRAME).bits())
+            .u8(41)
+            .u16(24)
+            .u16(0x8000)
+            .u8(5);
+        let cases = [
+            (ServerClient::Playerinfo, playerinfo),
+            (ServerClient::Packetentities, entities(Bytes(vec![]))),
+            (
+                ServerClient::Deltapacketentities,
+                entities(Bytes(vec![]).u8(12)),
+            ),
+        ];
+        for (cmd, b) in cases {
+            let flags = MessageFlags::default();
+            let mut message = reader(&b.0, flags, MessageType::Mvd);
+            match cmd {
+                ServerClient::Playerinfo => Playerinfo::skip_demo(&mut message),
+                ServerClient::Packetentities => Packetentities::skip(&mut message),
+                _ => Deltapacketentities::skip(&mut message),
+            }
+            .unwrap();
+            assert_eq!(message.position, b.0.len());
+
+            let mut message = reader(&b.0, flags, MessageType::Mvd);
+            cmd.read_message(&mut message).unwrap();
+            assert_eq!(message.position, b.0.len());
+        }
+    }
+
+    #[test]
+    fn bad_write_fails() {
+        let mut message = Message::empty();
+        assert!(ServerMessage::Bad(Bad {}).write(&mut message).is_err());
+    }
+}
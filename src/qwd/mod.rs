@@ -0,0 +1,380 @@
+use crate::protocol::errors::QwdParseError;
+#[cfg(feature = "trace")]
+use crate::protocol::message::trace::{MessageTrace, ToTraceValue, TraceOptions, TraceValue};
+use crate::protocol::message::Message;
+use crate::protocol::message::MessageFlags;
+use crate::protocol::message::MessageType;
+use crate::protocol::types::*;
+use serde::Serialize;
+
+#[cfg(feature = "ascii_strings")]
+use crate::utils::ascii_converter::AsciiConverter;
+
+use crate::protocol::message::trace;
+
+/// usercmd the client sent, recorded with dem_cmd
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd, Default)]
+pub struct QwdUserCommand {
+    pub command: DeltaUserCommand,
+    pub view_angle: AngleVector,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub enum QwdData {
+    /// dem_cmd
+    Command(QwdUserCommand),
+    /// dem_read, a packet received from the server
+    Read(Packet),
+    /// dem_set
+    Set {
+        outgoing_sequence: u32,
+        incoming_sequence: u32,
+    },
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug, PartialOrd)]
+pub struct QwdFrame {
+    pub frame: u32,
+    pub time: f64,
+    pub time_diff: f64,
+    pub data: QwdData,
+}
+
+impl QwdFrame {
+    /// the [`ServerMessage`]s of a dem_read frame, can be fed to [`crate::state::State::apply_messages`]
+    pub fn messages(&self) -> &[ServerMessage] {
+        match &self.data {
+            QwdData::Read(Packet::Connected(c)) => &c.messages,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Qwd {
+    pub size: usize,
+    pub finished: bool,
+    pub message: Message,
+    pub frame: u32,
+    pub time: f64,
+    #[cfg(feature = "trace")]
+    pub trace_options: TraceOptions,
+}
+
+impl Qwd {
+    pub fn new(
+        buffer: Vec<u8>,
+        #[cfg(feature = "ascii_strings")] maybe_ascii_converter: Option<AsciiConverter>,
+        #[cfg(feature = "trace")] trace_options: TraceOptions,
+    ) -> Result<Qwd, QwdParseError> {
+        let size = buffer.len();
+        let flags = MessageFlags {
+            protocol: ProtocolVersion::Standard as u32,
+            ..Default::default()
+        };
+
+        #[allow(unused_mut)]
+        let mut message = Message::new(
+            Box::new(buffer),
+            0,
+            size,
+            false,
+            flags,
+            #[cfg(feature = "ascii_strings")]
+            maybe_ascii_converter,
+            MessageType::Connection,
+            #[cfg(feature = "trace")]
+            MessageTrace::default(),
+        );
+
+        #[cfg(feature = "trace")]
+        {
+            message.trace.enabled = trace_options.enabled;
+        }
+
+        Ok(Qwd {
+            size,
+            finished: size == 0,
+            message,
+            frame: 0,
+            time: 0.0,
+            #[cfg(feature = "trace")]
+            trace_options,
+        })
+    }
+
+    pub fn parse_frame(&mut self) -> Result<QwdFrame, QwdParseError> {
+        trace::trace_annotate!(self.message, format!("({:.4})({})", self.time, self.frame));
+        trace::trace_start!(self.message, false);
+        trace::trace_annotate!(self.message, "demo_time");
+        let time = self.message.read_f32(false)? as f64;
+        let time_diff = if self.frame == 0 {
+            0.0
+        } else {
+            time - self.time
+        };
+        self.time = time;
+
+        trace::trace_annotate!(self.message, "cmd");
+        let cmd = self.message.read_u8(false)?;
+        let data = match DemoCommand::try_from(cmd) {
+            Ok(DemoCommand::Command) => QwdData::Command(self.read_usercommand()?),
+            Ok(DemoCommand::Read) => QwdData::Read(self.read_packet()?),
+            Ok(DemoCommand::Set) => {
+                trace::trace_annotate!(self.message, "outgoing_sequence");
+                let outgoing_sequence = self.message.read_u32(false)?;
+                trace::trace_annotate!(self.message, "incoming_sequence");
+                let incoming_sequence = self.message.read_u32(false)?;
+                QwdData::Set {
+                    outgoing_sequence,
+                    incoming_sequence,
+                }
+            }
+            _ => return Err(QwdParseError::UnhandledCommand(cmd)),
+        };
+
+        let frame = QwdFrame {
+            frame: self.frame,
+            time,
+            time_diff,
+            data,
+        };
+        self.frame += 1;
+        if self.message.position >= self.message.length {
+            self.finished = true;
+        }
+        trace::trace_stop!(self.message);
+        Ok(frame)
+    }
+
+    // the usercmd_t is written as is, including the struct padding, followed by the view angles
+    fn read_usercommand(&mut self) -> Result<QwdUserCommand, QwdParseError> {
+        trace::trace_annotate!(self.message, "msec");
+        let msec = self.message.read_u8(false)?;
+        self.message.read_u8(false)?;
+        self.message.read_u16(false)?;
+        let mut angle = AngleVector::default();
+        for i in 0..3 {
+            trace::trace_annotate!(self.message, "angle");
+            angle[i] = self.message.read_f32(false)?;
+        }
+        trace::trace_annotate!(self.message, "forward");
+        let forward = self.message.read_i16(false)?;
+        trace::trace_annotate!(self.message, "side");
+        let side = self.message.read_i16(false)?;
+        trace::trace_annotate!(self.message, "up");
+        let up = self.message.read_i16(false)?;
+        trace::trace_annotate!(self.message, "buttons");
+        let buttons = self.message.read_u8(false)?;
+        trace::trace_annotate!(self.message, "impulse");
+        let impulse = self.message.read_u8(false)?;
+        let mut view_angle = AngleVector::default();
+        for i in 0..3 {
+            trace::trace_annotate!(self.message, "view_angle");
+            view_angle[i] = self.message.read_f32(false)?;
+        }
+
+        let command = DeltaUserCommand {
+            bits: UserCommandFlags::all(),
+            angle: AngleVectorOption {
+                x: Some(angle.x),
+                y: Some(angle.y),
+                z: Some(angle.z),
+            },
+            forward: Some(forward),
+            side: Some(side),
+            up: Some(up),
+            buttons: Some(buttons),
+            impulse: Some(impulse),
+            msec: Some(msec),
+        };
+        Ok(QwdUserCommand {
+            command,
+            view_angle,
+        })
+    }
+
+    fn read_packet(&mut self) -> Result<Packet, QwdParseError> {
+        trace::trace_annotate!(self.message, "size");
+        let size = self.message.read_u32(false)? as usize;
+        let start = self.message.position;
+        let stop = start + size;
+        if stop > self.message.length {
+            return Err(QwdParseError::PacketSize(size, self.size, start));
+        }
+
+        // limit the message to the packet so the reader stops at its end
+        let length = self.message.length;
+        self.message.length = stop;
+        let packet = self.read_packet_limited();
+        self.message.length = length;
+        self.message.position = stop;
+
+        let packet = packet?;
+        if let Packet::Connected(c) = &packet {
+            for m in &c.messages {
+                if let ServerMessage::Serverdata(s) = m {
+                    self.message.flags.fte_protocol_extensions = s.fte_protocol_extension;
+                    self.message.flags.fte_protocol_extensions_2 = s.fte_protocol_extension_2;
+                    self.message.flags.mvd_protocol_extension = s.mvd_protocol_extension;
+                }
+            }
+        }
+        Ok(packet)
+    }
+
+    fn read_packet_limited(&mut self) -> Result<Packet, QwdParseError> {
+        if !self.message.is_oob()? {
+            return Ok(self.message.read_connected_packet()?);
+        }
+
+        // the demo is ended with an out of band svc_disconnect "EndOfDemo"
+        self.message.position += 4;
+        let t = self.message.read_u8(true)?;
+        if t == ServerClient::Disconnect as u8 {
+            self.finished = true;
+            return Ok(Packet::Connected(Connected {
+                sequence: OOB_HEADER,
+                sequence_ack: 0,
+                messages: vec![ServerMessage::Disconnect(Disconnect {})],
+            }));
+        }
+        self.message.position -= 4;
+        Ok(self.message.read_oob_packet()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "state")]
+    use crate::state::State;
+
+    fn qwd(data: Vec<u8>) -> Qwd {
+        Qwd::new(
+            data,
+            #[cfg(feature = "ascii_strings")]
+            None,
+            #[cfg(feature = "trace")]
+            TraceOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn block(data: &mut Message, time: f32, cmd: DemoCommand) {
+        data.write_f32(time);
+        data.write_u8(cmd as u8);
+    }
+
+    fn packet(data: &mut Message, packet: &Message) {
+        data.write_u32(packet.buffer.len() as u32);
+        data.buffer.extend_from_slice(&packet.buffer);
+        data.position += packet.buffer.len();
+    }
+
+    #[test]
+    fn parse_qwd() {
+        let mut data = Message::empty();
+
+        let mut p = Message::empty();
+        p.write_u32(1_u32);
+        p.write_u32(0_u32);
+        p.write_u8(ServerClient::Serverdata as u8);
+        p.write_u32(ProtocolVersion::Standard as u32);
+        p.write_u32(2_u32);
+        p.write_stringbyte("qw");
+        p.write_u8(3);
+        p.write_stringbyte("dm4");
+        for _ in 0..10 {
+            p.write_f32(1.0);
+        }
+        p.write_u8(ServerClient::Updatefrags as u8);
+        p.write_u8(3);
+        p.write_i16(7_i16);
+        block(&mut data, 0.5, DemoCommand::Read);
+        packet(&mut data, &p);
+
+        // the 24 byte usercmd_t followed by the view angles
+        block(&mut data, 0.75, DemoCommand::Command);
+        data.write_u8(13);
+        data.write_u8(0);
+        data.write_u16(0_u16);
+        data.write_f32(10.0);
+        data.write_f32(90.0);
+        data.write_f32(0.0);
+        data.write_i16(400_i16);
+        data.write_i16(-200_i16);
+        data.write_i16(0_i16);
+        data.write_u8(1);
+        data.write_u8(2);
+        data.write_f32(11.0);
+        data.write_f32(91.0);
+        data.write_f32(0.0);
+
+        block(&mut data, 0.75, DemoCommand::Set);
+        data.write_u32(20_u32);
+        data.write_u32(19_u32);
+
+        let mut p = Message::empty();
+        p.write_i32(-1);
+        p.write_u8(ServerClient::Disconnect as u8);
+        p.write_stringbyte("EndOfDemo");
+        block(&mut data, 1.0, DemoCommand::Read);
+        packet(&mut data, &p);
+
+        let mut qwd = qwd(data.into());
+        let mut frames = vec![];
+        while !qwd.finished {
+            frames.push(qwd.parse_frame().unwrap());
+        }
+        assert_eq!(frames.len(), 4);
+
+        assert_eq!(frames[0].time, 0.5);
+        assert!(matches!(
+            frames[0].messages()[0],
+            ServerMessage::Serverdata(_)
+        ));
+        assert_eq!(
+            frames[0].messages()[1],
+            ServerMessage::Updatefrags(Updatefrags {
+                player_number: 3,
+                frags: 7
+            })
+        );
+
+        match &frames[1].data {
+            QwdData::Command(c) => {
+                assert_eq!(c.command.msec, Some(13));
+                assert_eq!(c.command.angle.y, Some(90.0));
+                assert_eq!(c.command.forward, Some(400));
+                assert_eq!(c.command.side, Some(-200));
+                assert_eq!(c.command.buttons, Some(1));
+                assert_eq!(c.command.impulse, Some(2));
+                assert_eq!(c.view_angle.y, 91.0);
+            }
+            d => panic!("expected a usercmd, got {:?}", d),
+        }
+        assert_eq!(frames[1].time_diff, 0.25);
+
+        assert_eq!(
+            frames[2].data,
+            QwdData::Set {
+                outgoing_sequence: 20,
+                incoming_sequence: 19
+            }
+        );
+        assert_eq!(
+            frames[3].messages(),
+            &[ServerMessage::Disconnect(Disconnect {})]
+        );
+
+        #[cfg(feature = "state")]
+        {
+            let mut state = State::new();
+            for frame in &frames {
+                state.apply_messages(frame.messages());
+            }
+            assert_eq!(state.players[&3].frags, 7);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use crate::protocol::types::{AngleVector, CoordinateVector};
+use crate::state::State;
+use serde::Serialize;
+
+/// a move further than this on any axis between two states is a teleport, like the client does
+/// for packet entities
+pub const TELEPORT_DISTANCE: f32 = 128.0;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct InterpolatedPosition {
+    pub origin: CoordinateVector,
+    pub angle: AngleVector,
+}
+
+/// two consecutive [`State`]s and their demo times, positions in between are interpolated
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct InterpolatedState {
+    pub from: State,
+    pub from_time: f64,
+    pub to: State,
+    pub to_time: f64,
+}
+
+fn lerp(from: f32, to: f32, fraction: f32) -> f32 {
+    from + (to - from) * fraction
+}
+
+// takes the shorter way around the circle
+fn lerp_angle(from: f32, to: f32, fraction: f32) -> f32 {
+    let delta = (to - from + 180.0).rem_euclid(360.0) - 180.0;
+    from + delta * fraction
+}
+
+fn teleported(from: &CoordinateVector, to: &CoordinateVector) -> bool {
+    (to.x - from.x).abs() > TELEPORT_DISTANCE
+        || (to.y - from.y).abs() > TELEPORT_DISTANCE
+        || (to.z - from.z).abs() > TELEPORT_DISTANCE
+}
+
+fn interpolate(
+    from: (&CoordinateVector, &AngleVector),
+    to: (&CoordinateVector, &AngleVector),
+    fraction: f32,
+) -> InterpolatedPosition {
+    if teleported(from.0, to.0) {
+        return InterpolatedPosition {
+            origin: *to.0,
+            angle: *to.1,
+        };
+    }
+    InterpolatedPosition {
+        origin: CoordinateVector {
+            x: lerp(from.0.x, to.0.x, fraction),
+            y: lerp(from.0.y, to.0.y, fraction),
+            z: lerp(from.0.z, to.0.z, fraction),
+        },
+        angle: AngleVector {
+            x: lerp_angle(from.1.x, to.1.x, fraction),
+            y: lerp_angle(from.1.y, to.1.y, fraction),
+            z: lerp_angle(from.1.z, to.1.z, fraction),
+        },
+    }
+}
+
+impl InterpolatedState {
+    pub fn new(from: State, from_time: f64, to: State, to_time: f64) -> InterpolatedState {
+        InterpolatedState {
+            from,
+            from_time,
+            to,
+            to_time,
+        }
+    }
+
+    /// the current `to` state becomes the `from` state
+    pub fn push(&mut self, state: State, time: f64) {
+        self.from = std::mem::replace(&mut self.to, state);
+        self.from_time = self.to_time;
+        self.to_time = time;
+    }
+
+    /// how far time is between the two states, clamped to 0..=1
+    pub fn fraction(&self, time: f64) -> f32 {
+        let span = self.to_time - self.from_time;
+        if span <= 0.0 {
+            return 1.0;
+        }
+        ((time - self.from_time) / span).clamp(0.0, 1.0) as f32
+    }
+
+    /// position of the player at time, players that just appeared are not interpolated
+    pub fn player(&self, index: u16, time: f64) -> Option<InterpolatedPosition> {
+        let to = self.to.players.get(&index)?;
+        let from = match self.from.players.get(&index) {
+            Some(from) => from,
+            None => {
+                return Some(InterpolatedPosition {
+                    origin: to.origin,
+                    angle: to.angle,
+                })
+            }
+        };
+        Some(interpolate(
+            (&from.origin, &from.angle),
+            (&to.origin, &to.angle),
+            self.fraction(time),
+        ))
+    }
+
+    /// position of the entity at time, entities that were removed are gone and entities that
+    /// appeared or changed their model are not interpolated
+    pub fn entity(&self, index: u16, time: f64) -> Option<InterpolatedPosition> {
+        let to = self.to.entities.get(&index)?;
+        let from = match self.from.entities.get(&index) {
+            Some(from) if from.model == to.model => from,
+            _ => {
+                return Some(InterpolatedPosition {
+                    origin: to.origin,
+                    angle: to.angle,
+                })
+            }
+        };
+        Some(interpolate(
+            (&from.origin, &from.angle),
+            (&to.origin, &to.angle),
+            self.fraction(time),
+        ))
+    }
+
+    pub fn players(&self, time: f64) -> HashMap<u16, InterpolatedPosition> {
+        self.to
+            .players
+            .keys()
+            .filter_map(|index| Some((*index, self.player(*index, time)?)))
+            .collect()
+    }
+
+    pub fn entities(&self, time: f64) -> HashMap<u16, InterpolatedPosition> {
+        self.to
+            .entities
+            .keys()
+            .filter_map(|index| Some((*index, self.entity(*index, time)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Entity, Player};
+
+    fn origin(x: f32, y: f32, z: f32) -> CoordinateVector {
+        CoordinateVector { x, y, z }
+    }
+
+    fn angle(x: f32, y: f32, z: f32) -> AngleVector {
+        AngleVector { x, y, z }
+    }
+
+    fn player(origin: CoordinateVector, angle: AngleVector) -> Player {
+        Player {
+            origin,
+            angle,
+            ..Default::default()
+        }
+    }
+
+    fn entity(model: u16, origin: CoordinateVector) -> Entity {
+        Entity {
+            model,
+            origin,
+            ..Default::default()
+        }
+    }
+
+    fn states() -> InterpolatedState {
+        let mut from = State::new();
+        from.players
+            .insert(0, player(origin(0.0, 0.0, 0.0), angle(0.0, 350.0, 0.0)));
+        from.players
+            .insert(1, player(origin(0.0, 0.0, 0.0), angle(0.0, 90.0, 0.0)));
+        from.entities.insert(10, entity(1, origin(10.0, 0.0, 0.0)));
+        from.entities.insert(11, entity(1, origin(0.0, 0.0, 0.0)));
+        from.entities.insert(12, entity(1, origin(0.0, 0.0, 0.0)));
+
+        let mut to = State::new();
+        to.players
+            .insert(0, player(origin(10.0, -20.0, 4.0), angle(10.0, 10.0, 0.0)));
+        // teleported
+        to.players
+            .insert(1, player(origin(500.0, 0.0, 0.0), angle(0.0, 180.0, 0.0)));
+        to.players
+            .insert(2, player(origin(1.0, 2.0, 3.0), angle(0.0, 0.0, 0.0)));
+        to.entities.insert(10, entity(1, origin(20.0, 0.0, 0.0)));
+        // the slot was reused
+        to.entities.insert(12, entity(2, origin(8.0, 0.0, 0.0)));
+
+        let mut state = InterpolatedState::default();
+        state.push(from, 1.0);
+        state.push(to, 1.1);
+        state
+    }
+
+    #[test]
+    fn interpolate_players() {
+        let state = states();
+        let p = state.player(0, 1.05).unwrap();
+        assert_eq!(p.origin, origin(5.0, -10.0, 2.0));
+        assert!((p.angle.x - 5.0).abs() < 0.001);
+        // 350 -> 10 goes through 0, not 180
+        assert!((p.angle.y - 360.0).abs() < 0.001, "{}", p.angle.y);
+
+        assert_eq!(state.player(0, 0.5).unwrap().origin, origin(0.0, 0.0, 0.0));
+        assert_eq!(
+            state.player(0, 2.0).unwrap().origin,
+            origin(10.0, -20.0, 4.0)
+        );
+
+        let p = state.player(1, 1.05).unwrap();
+        assert_eq!(p.origin, origin(500.0, 0.0, 0.0));
+        assert_eq!(p.angle, angle(0.0, 180.0, 0.0));
+        assert_eq!(state.player(2, 1.05).unwrap().origin, origin(1.0, 2.0, 3.0));
+        assert_eq!(state.players(1.05).len(), 3);
+    }
+
+    #[test]
+    fn interpolate_entities() {
+        let state = states();
+        let e = state.entity(10, 1.025).unwrap();
+        assert!((e.origin.x - 12.5).abs() < 0.001);
+        assert_eq!(state.entity(11, 1.05), None);
+        assert_eq!(
+            state.entity(12, 1.05).unwrap().origin,
+            origin(8.0, 0.0, 0.0)
+        );
+
+        let entities = state.entities(1.05);
+        assert_eq!(entities.len(), 2);
+        assert!(!entities.contains_key(&11));
+    }
+}
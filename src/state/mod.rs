@@ -3,17 +3,19 @@ use crate::protocol::types::*;
 #[cfg(feature = "ascii_strings")]
 use crate::utils::ascii_converter::AsciiConverter;
 use crate::utils::userinfo::Userinfo;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 pub mod interpolated;
 pub use interpolated::InterpolatedState;
+pub mod serverinfo;
+pub use serverinfo::ServerinfoMap;
 
 pub type Stat = [i32; 32];
 
 static mut PACKET_ENT_COUNT: u32 = 0;
 
-#[derive(Serialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Player {
     pub frags: i16,
     pub ping: u16,
@@ -28,33 +30,27 @@ pub struct Player {
     pub bottom_color: StringByte,
     pub origin: CoordinateVector,
     pub angle: AngleVector,
+    pub frame: u8,
     pub model: u8,
     pub skinnum: u8,
     pub effects: u8,
     pub weaponframe: u8,
+    pub dead: bool,
+    pub gib: bool,
     pub stats: Stat,
 }
 
 impl Player {
     /// updates the userinfo of this [`Player`].
-    #[cfg(feature = "ascii_strings")]
     fn update_userinfo(&mut self) {
-        for (k, v) in self.userinfo.values.iter() {
-            if k.string == "team" {
-                self.team = v.clone();
-            }
-            if k.string == "name" {
-                self.name = v.clone();
-            }
-        }
+        self.team = self.userinfo.get(b"team").cloned().unwrap_or_default();
+        self.name = self.userinfo.get(b"name").cloned().unwrap_or_default();
+        let spectator = self.userinfo.get(b"*spectator").cloned().unwrap_or_default();
+        self.spectator = !spectator.bytes.is_empty() && spectator.bytes != b"0";
     }
-
-    /// the userinfo isnt read into the [`Player`] when ascii_strings is disabled.
-    #[cfg(not(feature = "ascii_strings"))]
-    fn update_userinfo(&mut self) {}
 }
 
-#[derive(Serialize, Copy, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default)]
 pub struct Entity {
     pub index: u16,
     pub model: u16,
@@ -67,52 +63,85 @@ pub struct Entity {
 }
 
 impl Entity {
-    pub fn reverse_delta(&mut self, delta: &Packetentity) -> Packetentity {
-        let mut p = Packetentity::default();
+    /// the values of this [`Entity`] for the fields set in delta, applying it after delta
+    /// reverts delta
+    pub fn reverse_delta(&self, delta: &Packetentity) -> Packetentity {
+        let mut p = Packetentity {
+            entity_index: delta.entity_index,
+            ..Default::default()
+        };
 
-        if let Some(v) = delta.model {
-            p.model = Some(self.model.clone());
+        if delta.model.is_some() {
+            p.model = Some(self.model);
         }
-        if let Some(v) = delta.frame {
-            p.frame = Some(self.frame.clone());
+        if delta.frame.is_some() {
+            p.frame = Some(self.frame);
         }
-        if let Some(v) = delta.colormap {
-            p.colormap = Some(self.colormap.clone());
+        if delta.colormap.is_some() {
+            p.colormap = Some(self.colormap);
         }
-        if let Some(v) = delta.skin {
-            p.skin = Some(self.skinnum.clone());
+        if delta.skin.is_some() {
+            p.skin = Some(self.skinnum);
         }
-        if let Some(v) = delta.effects {
-            p.effects = Some(self.effects.clone());
+        if delta.effects.is_some() {
+            p.effects = Some(self.effects);
         }
         if let Some(v) = delta.origin {
             let mut r = CoordinateVectorOption::default();
             if v.x.is_some() {
-                r.x = Some(self.origin.x.clone());
+                r.x = Some(self.origin.x);
             }
             if v.y.is_some() {
-                r.y = Some(self.origin.y.clone());
+                r.y = Some(self.origin.y);
             }
             if v.z.is_some() {
-                r.z = Some(self.origin.z.clone());
+                r.z = Some(self.origin.z);
             }
             p.origin = Some(r);
         }
         if let Some(v) = delta.angle {
             let mut r = AngleVectorOption::default();
             if v.x.is_some() {
-                r.x = Some(self.angle.x.clone());
+                r.x = Some(self.angle.x);
             }
             if v.y.is_some() {
-                r.y = Some(self.angle.y.clone());
+                r.y = Some(self.angle.y);
             }
             if v.z.is_some() {
-                r.z = Some(self.angle.z.clone());
+                r.z = Some(self.angle.z);
             }
             p.angle = Some(r);
         }
         p
     }
+
+    /// the delta that turns from into this [`Entity`]
+    pub fn delta_from(&self, from: &Entity) -> Packetentity {
+        // only mark the fields that differ, reverse_delta fills in our values
+        let origin = CoordinateVectorOption {
+            x: (self.origin.x != from.origin.x).then_some(0.0),
+            y: (self.origin.y != from.origin.y).then_some(0.0),
+            z: (self.origin.z != from.origin.z).then_some(0.0),
+        };
+        let angle = AngleVectorOption {
+            x: (self.angle.x != from.angle.x).then_some(0.0),
+            y: (self.angle.y != from.angle.y).then_some(0.0),
+            z: (self.angle.z != from.angle.z).then_some(0.0),
+        };
+        let mask = Packetentity {
+            entity_index: self.index,
+            model: (self.model != from.model).then_some(0),
+            frame: (self.frame != from.frame).then_some(0),
+            colormap: (self.colormap != from.colormap).then_some(0),
+            skin: (self.skinnum != from.skinnum).then_some(0),
+            effects: (self.effects != from.effects).then_some(0),
+            origin: (origin != CoordinateVectorOption::default()).then_some(origin),
+            angle: (angle != AngleVectorOption::default()).then_some(angle),
+            ..Default::default()
+        };
+        self.reverse_delta(&mask)
+    }
+
     /// apply [`ServerMessage::Packetentity`] as a deltapacket_entity to this [`Entity`]
     pub fn apply_delta(&mut self, delta: &Packetentity) {
         if let Some(v) = delta.model {
@@ -122,13 +151,13 @@ impl Entity {
             self.frame = v;
         }
         if let Some(v) = delta.colormap {
-            self.frame = v;
+            self.colormap = v;
         }
         if let Some(v) = delta.skin {
-            self.frame = v;
+            self.skinnum = v;
         }
         if let Some(v) = delta.effects {
-            self.frame = v;
+            self.effects = v;
         }
         if let Some(v) = delta.origin {
             v.apply_to(&mut self.origin);
@@ -210,11 +239,12 @@ impl Sound {
 }
 */
 
-#[derive(Serialize, Clone, Default, Debug)]
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct State {
     #[cfg(feature = "ascii_strings")]
     ascii_converter: AsciiConverter,
     pub serverdata: Serverdata,
+    pub serverinfo: ServerinfoMap,
     pub players: HashMap<u16, Player>,
     pub sounds: Vec<StringByte>,
     pub models: Vec<StringByte>,
@@ -243,6 +273,7 @@ impl State {
                 bytes: vec![0],
                 string: "".to_string(),
             }],
+            serverinfo: ServerinfoMap::new_with_ascii_converter(ascii_converter.clone()),
             ascii_converter,
             ..Default::default()
         }
@@ -277,19 +308,52 @@ impl State {
             }
             ServerMessage::Updateuserinfo(data) => {
                 player.uid = data.uid;
-                player.userinfo.update(&data.userinfo);
+                player.userinfo.replace(&data.userinfo);
                 player.update_userinfo();
             }
             ServerMessage::Playerinfo(data) => match data {
                 Playerinfo::PlayerinfoMvdT(playerinfo_mvd) => {
+                    player.frame = playerinfo_mvd.frame;
+                    player.dead = playerinfo_mvd.flags.contains(DfTypes::DEAD);
+                    player.gib = playerinfo_mvd.flags.contains(DfTypes::GIB);
                     if let Some(origin) = playerinfo_mvd.origin {
                         origin.apply_to(&mut player.origin);
                     };
                     if let Some(angle) = playerinfo_mvd.angle {
                         angle.apply_to(&mut player.angle);
                     };
+                    if let Some(model) = playerinfo_mvd.model {
+                        player.model = model;
+                    }
+                    if let Some(skinnum) = playerinfo_mvd.skinnum {
+                        player.skinnum = skinnum;
+                    }
+                    if let Some(effects) = playerinfo_mvd.effects {
+                        player.effects = effects;
+                    }
+                    if let Some(weaponframe) = playerinfo_mvd.weaponframe {
+                        player.weaponframe = weaponframe;
+                    }
+                }
+                Playerinfo::PlayerinfoConnectionT(playerinfo_connection) => {
+                    player.origin = playerinfo_connection.origin;
+                    player.frame = playerinfo_connection.frame;
+                    if let Some(command) = &playerinfo_connection.command {
+                        command.angle.apply_to(&mut player.angle);
+                    }
+                    if let Some(model) = playerinfo_connection.model {
+                        player.model = model;
+                    }
+                    if let Some(skinnum) = playerinfo_connection.skinnum {
+                        player.skinnum = skinnum;
+                    }
+                    if let Some(effects) = playerinfo_connection.effects {
+                        player.effects = effects;
+                    }
+                    if let Some(weaponframe) = playerinfo_connection.weaponframe {
+                        player.weaponframe = weaponframe;
+                    }
                 }
-                Playerinfo::PlayerinfoConnectionT(playerinfo_connection) => todo!(),
             },
             ServerMessage::Updatestatlong(data) => {
                 player.stats[data.stat as usize] = data.value;
@@ -321,6 +385,7 @@ impl State {
                 None => {
                     let mut e = Entity::default();
                     e.apply_delta(ent);
+                    e.index = ent.entity_index;
                     self.entities.insert(ent.entity_index as u16, e);
                     // println!(
                     //     "packet_entities: we failed to get the baseline! {} -> {:?}",
@@ -329,8 +394,9 @@ impl State {
                     continue;
                 }
             };
-            let mut e = baseline.clone();
+            let mut e = *baseline;
             e.apply_delta(ent);
+            e.index = ent.entity_index;
             // println!(
             //     "packet_entities: inserting ({}) model({}) ({})",
             //     ent.entity_index, e.model, baseline.model
@@ -386,7 +452,7 @@ impl State {
             .insert(temp_entity.entity, temp_entity.clone());
     }
 
-    pub fn apply_messages_mvd(&mut self, messages: &'_ Vec<ServerMessage>, last: &MvdTarget) {
+    pub fn apply_messages_mvd(&mut self, messages: &[ServerMessage], last: &MvdTarget) {
         for message in messages {
             match message {
                 ServerMessage::Serverdata(data) => {
@@ -400,7 +466,9 @@ impl State {
                 }
                 ServerMessage::Spawnstatic(data) => self.static_entities.push(*data),
                 ServerMessage::Cdtrack(_) => continue,
-                ServerMessage::Stufftext(_) => continue,
+                ServerMessage::Stufftext(data) => {
+                    self.serverinfo.apply_stufftext(&data.text.bytes);
+                }
                 ServerMessage::Spawnstaticsound(data) => {
                     self.static_sounds.push(data.clone());
                 }
@@ -423,7 +491,9 @@ impl State {
                     Playerinfo::PlayerinfoMvdT(playerinfo_mvd) => {
                         self.update_player(playerinfo_mvd.player_number as u16, message);
                     }
-                    Playerinfo::PlayerinfoConnectionT(playerinfo_connection) => todo!(),
+                    Playerinfo::PlayerinfoConnectionT(playerinfo_connection) => {
+                        self.update_player(playerinfo_connection.player_number as u16, message);
+                    }
                 },
                 ServerMessage::Updatestatlong(_) => {
                     self.update_player(last.to as u16, message);
@@ -437,8 +507,8 @@ impl State {
                 ServerMessage::Lightstyle(_) => {
                     // ignore
                 }
-                ServerMessage::Serverinfo(_) => {
-                    // ignore, but probably shouldnt be
+                ServerMessage::Serverinfo(data) => {
+                    self.serverinfo.update(&data.key, &data.value);
                 }
                 ServerMessage::Centerprint(_) => {
                     // ignore
@@ -498,7 +568,7 @@ impl State {
         }
     }
 
-    pub fn apply_messages(&mut self, messages: &'_ Vec<ServerMessage>) {
+    pub fn apply_messages(&mut self, messages: &[ServerMessage]) {
         for message in messages {
             match message {
                 ServerMessage::Serverdata(data) => {
@@ -516,7 +586,9 @@ impl State {
                 }
                 ServerMessage::Spawnstatic(data) => self.static_entities.push(*data),
                 ServerMessage::Cdtrack(_) => continue,
-                ServerMessage::Stufftext(_) => continue,
+                ServerMessage::Stufftext(data) => {
+                    self.serverinfo.apply_stufftext(&data.text.bytes);
+                }
                 ServerMessage::Spawnstaticsound(data) => {
                     self.static_sounds.push(data.clone());
                 }
@@ -539,13 +611,16 @@ impl State {
                     Playerinfo::PlayerinfoMvdT(playerinfo_mvd) => {
                         self.update_player(playerinfo_mvd.player_number as u16, message);
                     }
-                    Playerinfo::PlayerinfoConnectionT(playerinfo_connection) => todo!(),
+                    Playerinfo::PlayerinfoConnectionT(playerinfo_connection) => {
+                        self.update_player(playerinfo_connection.player_number as u16, message);
+                    }
                 },
+                // stats are only sent for the connected player, the high bit marks spectators
                 ServerMessage::Updatestatlong(_) => {
-                    //    self.update_player(last_to as u16, message);
+                    self.update_player((self.serverdata.player_number & 127) as u16, message);
                 }
                 ServerMessage::Updatestat(_) => {
-                    //    self.update_player(last_to as u16, message);
+                    self.update_player((self.serverdata.player_number & 127) as u16, message);
                 }
                 ServerMessage::Setinfo(data) => {
                     self.update_player(data.player_number as u16, message);
@@ -553,8 +628,8 @@ impl State {
                 ServerMessage::Lightstyle(_) => {
                     // ignore
                 }
-                ServerMessage::Serverinfo(_) => {
-                    // ignore, but probably shouldnt be
+                ServerMessage::Serverinfo(data) => {
+                    self.serverinfo.update(&data.key, &data.value);
                 }
                 ServerMessage::Centerprint(_) => {
                     // ignore
@@ -0,0 +1,153 @@
+use std::str::FromStr;
+
+use crate::protocol::types::StringByte;
+#[cfg(feature = "ascii_strings")]
+use crate::utils::ascii_converter::AsciiConverter;
+use crate::utils::infostring::MAX_SERVERINFO_STRING;
+use crate::utils::userinfo::{fullserverinfo, Userinfo};
+use serde::{Deserialize, Serialize};
+
+/// the serverinfo as sent with the fullserverinfo stufftext and updated by
+/// [`crate::protocol::types::Serverinfo`] messages
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServerinfoMap {
+    pub values: Userinfo,
+}
+
+impl Default for ServerinfoMap {
+    fn default() -> Self {
+        ServerinfoMap::new()
+    }
+}
+
+impl ServerinfoMap {
+    pub fn new() -> ServerinfoMap {
+        ServerinfoMap {
+            values: Userinfo::new().with_max_size(MAX_SERVERINFO_STRING),
+        }
+    }
+
+    #[cfg(feature = "ascii_strings")]
+    pub fn new_with_ascii_converter(ascii_converter: AsciiConverter) -> ServerinfoMap {
+        ServerinfoMap {
+            values: Userinfo::new_with_ascii_converter(ascii_converter)
+                .with_max_size(MAX_SERVERINFO_STRING),
+        }
+    }
+
+    /// replaces all values if text is a fullserverinfo stufftext, returns if it was one
+    pub fn apply_stufftext(&mut self, text: &[u8]) -> bool {
+        let info = match fullserverinfo(text) {
+            Some(info) => info,
+            None => return false,
+        };
+        self.values.replace(&StringByte {
+            bytes: info.to_vec(),
+            ..Default::default()
+        });
+        true
+    }
+
+    pub fn update(&mut self, key: &StringByte, value: &StringByte) {
+        self.values.update_key_value(key, value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&StringByte> {
+        self.values.get(key.as_bytes())
+    }
+
+    /// the value of key parsed as T, None if it is missing or does not parse
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        let value = self.values.info().get(key.as_bytes())?;
+        std::str::from_utf8(value).ok()?.trim().parse().ok()
+    }
+
+    pub fn hostname(&self) -> Option<&StringByte> {
+        self.get("hostname")
+    }
+
+    pub fn deathmatch(&self) -> Option<u32> {
+        self.get_parsed("deathmatch")
+    }
+
+    pub fn teamplay(&self) -> Option<u32> {
+        self.get_parsed("teamplay")
+    }
+
+    /// in minutes
+    pub fn timelimit(&self) -> Option<f32> {
+        self.get_parsed("timelimit")
+    }
+
+    pub fn fraglimit(&self) -> Option<u32> {
+        self.get_parsed("fraglimit")
+    }
+
+    pub fn maxclients(&self) -> Option<u32> {
+        self.get_parsed("maxclients")
+    }
+
+    /// the server version (*version)
+    pub fn version(&self) -> Option<&StringByte> {
+        self.get("*version")
+    }
+
+    pub fn ktxver(&self) -> Option<&StringByte> {
+        self.get("ktxver")
+    }
+
+    /// the ktx game mode, like "2on2" or "ffa"
+    pub fn mode(&self) -> Option<&StringByte> {
+        self.get("mode")
+    }
+
+    /// the ktx match status, like "Standby", "Countdown" or "5 min left"
+    pub fn status(&self) -> Option<&StringByte> {
+        self.get("status")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "ascii_strings")]
+    fn string(s: &str) -> StringByte {
+        StringByte::new(s, &AsciiConverter::new())
+    }
+
+    #[cfg(not(feature = "ascii_strings"))]
+    fn string(s: &str) -> StringByte {
+        StringByte::new(s)
+    }
+
+    #[test]
+    fn serverinfo_values() {
+        let mut serverinfo = ServerinfoMap::new();
+        assert!(!serverinfo.apply_stufftext(b"skins\n"));
+        assert!(serverinfo.apply_stufftext(
+            b"fullserverinfo \"\\hostname\\qw.server\\deathmatch\\3\\teamplay\\2\\timelimit\\20\\fraglimit\\0\\maxclients\\8\\*version\\MVDSV 0.36\\ktxver\\1.42\\mode\\2on2\\status\\Standby\"\n"
+        ));
+        assert_eq!(serverinfo.hostname().unwrap().bytes, b"qw.server");
+        assert_eq!(serverinfo.deathmatch(), Some(3));
+        assert_eq!(serverinfo.teamplay(), Some(2));
+        assert_eq!(serverinfo.timelimit(), Some(20.0));
+        assert_eq!(serverinfo.fraglimit(), Some(0));
+        assert_eq!(serverinfo.maxclients(), Some(8));
+        assert_eq!(serverinfo.version().unwrap().bytes, b"MVDSV 0.36");
+        assert_eq!(serverinfo.ktxver().unwrap().bytes, b"1.42");
+        assert_eq!(serverinfo.mode().unwrap().bytes, b"2on2");
+        assert_eq!(serverinfo.status().unwrap().bytes, b"Standby");
+
+        serverinfo.update(&string("status"), &string("19 min left"));
+        serverinfo.update(&string("timelimit"), &string("none"));
+        assert_eq!(serverinfo.status().unwrap().bytes, b"19 min left");
+        assert_eq!(serverinfo.timelimit(), None);
+        assert_eq!(serverinfo.values.len(), 10);
+
+        // a new fullserverinfo replaces everything
+        assert!(serverinfo.apply_stufftext(b"fullserverinfo \"\\deathmatch\\1\"\n"));
+        assert_eq!(serverinfo.deathmatch(), Some(1));
+        assert_eq!(serverinfo.status(), None);
+    }
+}
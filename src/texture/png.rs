@@ -30,3 +30,29 @@ pub fn from_palette_data(
     }
     Ok(pngbuf)
 }
+
+/// one byte per pixel, e.g. lightmaps
+pub fn from_greyscale_data(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    let mut pngbuf = vec![];
+    {
+        let mut encoder = png::Encoder::new(std::io::Cursor::new(&mut pngbuf), width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(data)?;
+    }
+    Ok(pngbuf)
+}
+
+/// three bytes per pixel
+pub fn from_rgb_data(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    let mut pngbuf = vec![];
+    {
+        let mut encoder = png::Encoder::new(std::io::Cursor::new(&mut pngbuf), width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(data)?;
+    }
+    Ok(pngbuf)
+}
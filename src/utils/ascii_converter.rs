@@ -1,11 +1,11 @@
 use std::error::Error;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use simple_error::bail;
 use crate::protocol::types::StringByte;
 
 
 
-#[derive(Debug, Serialize,Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AsciiConverter {
     table: Vec<u8>,
 }
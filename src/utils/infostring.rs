@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::errors::InfostringError;
+
+/// keys and values have to be shorter than this
+pub const MAX_INFO_KEY: usize = 64;
+/// size limit of a userinfo
+pub const MAX_INFO_STRING: usize = 196;
+/// size limit of a serverinfo
+pub const MAX_SERVERINFO_STRING: usize = 512;
+
+/// a `\key\value` string as used for userinfo and serverinfo
+///
+/// keys are unique, setting a key again replaces its value in place and an empty value removes
+/// the key, like the engine does
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Infostring {
+    values: Vec<(Vec<u8>, Vec<u8>)>,
+    max_size: usize,
+}
+
+impl Default for Infostring {
+    fn default() -> Self {
+        Infostring::new(MAX_INFO_STRING)
+    }
+}
+
+fn check(string: &[u8], max_length: usize) -> Result<(), InfostringError> {
+    if string.iter().any(|c| *c == b'\\' || *c == b'"') {
+        return Err(InfostringError::InvalidCharacter(
+            String::from_utf8_lossy(string).to_string(),
+        ));
+    }
+    if string.len() >= max_length {
+        return Err(InfostringError::TooLong(
+            String::from_utf8_lossy(string).to_string(),
+            max_length,
+        ));
+    }
+    Ok(())
+}
+
+impl Infostring {
+    pub fn new(max_size: usize) -> Infostring {
+        Infostring {
+            values: vec![],
+            max_size,
+        }
+    }
+
+    /// parses `\k\v\k\v`, nothing is validated as this is what we got sent.
+    /// a missing last value is empty and later values of a key replace earlier ones
+    pub fn parse(bytes: &[u8], max_size: usize) -> Infostring {
+        let mut info = Infostring::new(max_size);
+        info.update(bytes);
+        info
+    }
+
+    /// adds all values of a `\k\v\k\v` string without validating them
+    pub fn update(&mut self, bytes: &[u8]) {
+        let bytes = bytes.strip_prefix(b"\\").unwrap_or(bytes);
+        if bytes.is_empty() {
+            return;
+        }
+        let mut parts = bytes.split(|c| *c == b'\\');
+        while let Some(key) = parts.next() {
+            let value = parts.next().unwrap_or_default();
+            self.insert(key, value);
+        }
+    }
+
+    /// sets a key without validating it, used for values coming from the server
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) {
+        if value.is_empty() {
+            self.remove(key);
+            return;
+        }
+        match self.values.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_vec(),
+            None => self.values.push((key.to_vec(), value.to_vec())),
+        }
+    }
+
+    /// sets a key like `setinfo` does, `*` keys are read only
+    pub fn set(&mut self, key: &[u8], value: &[u8]) -> Result<(), InfostringError> {
+        if key.starts_with(b"*") {
+            return Err(InfostringError::ReadOnlyKey(
+                String::from_utf8_lossy(key).to_string(),
+            ));
+        }
+        self.set_star(key, value)
+    }
+
+    /// sets a key, `*` keys included
+    pub fn set_star(&mut self, key: &[u8], value: &[u8]) -> Result<(), InfostringError> {
+        if key.is_empty() {
+            return Err(InfostringError::EmptyKey);
+        }
+        check(key, MAX_INFO_KEY)?;
+        check(value, MAX_INFO_KEY)?;
+        if !value.is_empty() {
+            let old = self.get(key).map_or(0, |v| v.len() + key.len() + 2);
+            let size = self.size() - old + key.len() + value.len() + 2;
+            if size > self.max_size {
+                return Err(InfostringError::SizeExceeded(self.max_size));
+            }
+        }
+        self.insert(key, value);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// removes key and returns its value
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let index = self.values.iter().position(|(k, _)| k == key)?;
+        Some(self.values.remove(index).1)
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.values
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// length of the serialized string
+    pub fn size(&self) -> usize {
+        self.values.iter().map(|(k, v)| k.len() + v.len() + 2).sum()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size());
+        for (k, v) in &self.values {
+            bytes.push(b'\\');
+            bytes.extend_from_slice(k);
+            bytes.push(b'\\');
+            bytes.extend_from_slice(v);
+        }
+        bytes
+    }
+}
+
+/// quotes a value for a console command, `"` can't be escaped so it is rejected
+pub fn quote(value: &[u8]) -> Result<Vec<u8>, InfostringError> {
+    if value.contains(&b'"') {
+        return Err(InfostringError::InvalidCharacter(
+            String::from_utf8_lossy(value).to_string(),
+        ));
+    }
+    let mut quoted = Vec::with_capacity(value.len() + 2);
+    quoted.push(b'"');
+    quoted.extend_from_slice(value);
+    quoted.push(b'"');
+    Ok(quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_serialize() {
+        let info = Infostring::parse(b"\\name\\bob\\team\\red\\name\\alice\\rate", 512);
+        assert_eq!(info.len(), 2);
+        assert_eq!(info.get(b"name"), Some(&b"alice"[..]));
+        assert_eq!(info.get(b"team"), Some(&b"red"[..]));
+        assert_eq!(info.get(b"rate"), None);
+        assert_eq!(info.to_bytes(), b"\\name\\alice\\team\\red");
+        assert_eq!(info.size(), info.to_bytes().len());
+        assert_eq!(
+            info.iter().collect::<Vec<_>>(),
+            vec![(&b"name"[..], &b"alice"[..]), (&b"team"[..], &b"red"[..])]
+        );
+        assert!(Infostring::parse(b"", 512).is_empty());
+        assert!(Infostring::parse(b"\\", 512).is_empty());
+    }
+
+    #[test]
+    fn set_and_remove() {
+        let mut info = Infostring::new(24);
+        info.set(b"name", b"bob").unwrap();
+        info.set(b"team", b"red").unwrap();
+        info.set(b"name", b"alice").unwrap();
+        assert_eq!(info.to_bytes(), b"\\name\\alice\\team\\red");
+
+        assert!(matches!(
+            info.set(b"*spectator", b"1"),
+            Err(InfostringError::ReadOnlyKey(_))
+        ));
+        assert!(matches!(
+            info.set(b"na\\me", b"1"),
+            Err(InfostringError::InvalidCharacter(_))
+        ));
+        assert!(matches!(
+            info.set(b"name", b"\"bob\""),
+            Err(InfostringError::InvalidCharacter(_))
+        ));
+        assert!(matches!(
+            info.set(b"name", &[b'a'; MAX_INFO_KEY]),
+            Err(InfostringError::TooLong(_, MAX_INFO_KEY))
+        ));
+        assert!(matches!(
+            info.set(b"", b"1"),
+            Err(InfostringError::EmptyKey)
+        ));
+        // 20 bytes used, replacing a value only counts the difference
+        assert!(matches!(
+            info.set(b"rate", b"1"),
+            Err(InfostringError::SizeExceeded(24))
+        ));
+        info.set(b"name", b"al").unwrap();
+        info.set_star(b"*s", b"1").unwrap();
+        assert_eq!(info.size(), 22);
+
+        // an empty value removes the key
+        info.set(b"team", b"").unwrap();
+        assert_eq!(info.get(b"team"), None);
+        assert_eq!(info.remove(b"name"), Some(b"al".to_vec()));
+        assert_eq!(info.remove(b"name"), None);
+        assert_eq!(info.to_bytes(), b"\\*s\\1");
+    }
+
+    #[test]
+    fn quote_values() {
+        assert_eq!(quote(b"some name").unwrap(), b"\"some name\"");
+        assert!(quote(b"a\"b").is_err());
+    }
+}
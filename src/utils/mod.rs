@@ -1,5 +1,7 @@
 pub mod ascii_converter;
+pub mod infostring;
 pub mod perf;
+pub mod qwstring;
 pub mod userinfo;
 
 #[cfg(feature = "trace")]
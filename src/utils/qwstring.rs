@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::types::StringByte;
+
+/// start of the private use area ezQuake and FTE map the quake charset to
+pub const UNICODE_PRIVATE_USE: u32 = 0xe000;
+
+/// how a character of the quake charset is drawn
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum QwColor {
+    #[default]
+    White,
+    /// characters with the high bit set
+    Red,
+    /// the yellow numbers and brackets
+    Gold,
+}
+
+/// a run of characters drawn in the same color
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct QwSegment {
+    pub color: QwColor,
+    pub text: String,
+}
+
+// closest unicode character for the glyphs below 32, the upper half repeats the lower one
+const SPECIAL_CHARS: [char; 32] = [
+    '•', '•', '•', '•', '•', '•', '•', '•', '•', '•', '\n', '•', '•', '•', '•', '•', '[', ']', '0',
+    '1', '2', '3', '4', '5', '6', '7', '8', '9', '•', '<', '=', '>',
+];
+
+fn char_of(byte: u8) -> char {
+    match byte & 0x7f {
+        c @ 0..=31 => SPECIAL_CHARS[c as usize],
+        0x7f => '←',
+        c => c as char,
+    }
+}
+
+fn color_of(byte: u8) -> QwColor {
+    match byte & 0x7f {
+        0x10..=0x1b => QwColor::Gold,
+        _ if byte & 0x80 != 0 => QwColor::Red,
+        _ => QwColor::White,
+    }
+}
+
+/// splits the string into runs of the same color with readable characters
+pub fn segments(bytes: &[u8]) -> Vec<QwSegment> {
+    let mut segments: Vec<QwSegment> = vec![];
+    for byte in bytes {
+        let color = color_of(*byte);
+        match segments.last_mut() {
+            Some(segment) if segment.color == color => segment.text.push(char_of(*byte)),
+            _ => segments.push(QwSegment {
+                color,
+                text: char_of(*byte).to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+/// the string with terminal color escapes
+pub fn to_ansi(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for segment in segments(bytes) {
+        match segment.color {
+            QwColor::White => out.push_str(&segment.text),
+            QwColor::Red => out.push_str(&format!("\x1b[31m{}\x1b[0m", segment.text)),
+            QwColor::Gold => out.push_str(&format!("\x1b[33m{}\x1b[0m", segment.text)),
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// the string as html, colored runs are wrapped in `<span class="qw-red">` or
+/// `<span class="qw-gold">`
+pub fn to_html(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for segment in segments(bytes) {
+        let class = match segment.color {
+            QwColor::White => {
+                escape_html(&segment.text, &mut out);
+                continue;
+            }
+            QwColor::Red => "qw-red",
+            QwColor::Gold => "qw-gold",
+        };
+        out.push_str(&format!("<span class=\"{}\">", class));
+        escape_html(&segment.text, &mut out);
+        out.push_str("</span>");
+    }
+    out
+}
+
+/// the string as ezQuake maps it to unicode, printable ascii stays as is and every other
+/// character goes to the private use area at [`UNICODE_PRIVATE_USE`], so nothing is lost
+pub fn to_unicode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            b'\n' | 0x20..=0x7e => *byte as char,
+            _ => char::from_u32(UNICODE_PRIVATE_USE + *byte as u32).unwrap_or('?'),
+        })
+        .collect()
+}
+
+/// the string as readable unicode without colors
+pub fn to_plain(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| char_of(*byte)).collect()
+}
+
+fn byte_of(c: char) -> u8 {
+    match c {
+        '\n' | ' '..='~' => c as u8,
+        '•' => 0x1c,
+        '←' => 0x7f,
+        c => match (c as u32).checked_sub(UNICODE_PRIVATE_USE) {
+            Some(byte @ 0..=0xff) => byte as u8,
+            _ => b'?',
+        },
+    }
+}
+
+/// the reverse of [`to_unicode`] and [`to_plain`], characters that have no quake equivalent
+/// become `?`
+pub fn from_unicode(text: &str) -> Vec<u8> {
+    text.chars().map(byte_of).collect()
+}
+
+/// like [`from_unicode`] but with the markup of the ezQuake fun chars:
+/// * `{text}` is red
+/// * `$[` and `$]` are the gold brackets, `$0` to `$9` the gold numbers
+/// * `$xHH` is the byte HH and `$$` a dollar sign
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut red = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let high = if red { 0x80 } else { 0 };
+        match (c, chars.peek().copied()) {
+            ('{', _) if !red => red = true,
+            ('}', _) if red => red = false,
+            ('$', Some('[')) => bytes.push(0x10 | high),
+            ('$', Some(']')) => bytes.push(0x11 | high),
+            ('$', Some(d @ '0'..='9')) => bytes.push((0x12 + d as u8 - b'0') | high),
+            ('$', Some('$')) => bytes.push(b'$' | high),
+            ('$', Some('x')) => {
+                let hex: String = chars.clone().skip(1).take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => {
+                        bytes.push(byte);
+                        chars.nth(1);
+                    }
+                    _ => {
+                        bytes.push(b'$' | high);
+                        continue;
+                    }
+                }
+            }
+            (c, _) => {
+                // the private use area already says which half it is
+                if c == '\n' || c as u32 >= UNICODE_PRIVATE_USE {
+                    bytes.push(byte_of(c));
+                } else {
+                    bytes.push(byte_of(c) | high);
+                }
+                continue;
+            }
+        }
+        // the second character of a $ sequence
+        if c == '$' {
+            chars.next();
+        }
+    }
+    bytes
+}
+
+impl StringByte {
+    pub fn segments(&self) -> Vec<QwSegment> {
+        segments(&self.bytes)
+    }
+
+    pub fn to_ansi(&self) -> String {
+        to_ansi(&self.bytes)
+    }
+
+    pub fn to_html(&self) -> String {
+        to_html(&self.bytes)
+    }
+
+    pub fn to_unicode(&self) -> String {
+        to_unicode(&self.bytes)
+    }
+
+    pub fn to_plain(&self) -> String {
+        to_plain(&self.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "[" gold, "a" white, "bc" red, "1" gold, "d<" white
+    const NAME: &[u8] = &[0x10, b'a', b'b' | 0x80, b'c' | 0x80, 0x13, b'd', b'<'];
+
+    #[test]
+    fn render() {
+        assert_eq!(
+            segments(NAME),
+            vec![
+                QwSegment {
+                    color: QwColor::Gold,
+                    text: "[".to_string()
+                },
+                QwSegment {
+                    color: QwColor::White,
+                    text: "a".to_string()
+                },
+                QwSegment {
+                    color: QwColor::Red,
+                    text: "bc".to_string()
+                },
+                QwSegment {
+                    color: QwColor::Gold,
+                    text: "1".to_string()
+                },
+                QwSegment {
+                    color: QwColor::White,
+                    text: "d<".to_string()
+                },
+            ]
+        );
+        assert_eq!(to_plain(NAME), "[abc1d<");
+        assert_eq!(
+            to_ansi(NAME),
+            "\x1b[33m[\x1b[0ma\x1b[31mbc\x1b[0m\x1b[33m1\x1b[0md<"
+        );
+        assert_eq!(
+            to_html(NAME),
+            "<span class=\"qw-gold\">[</span>a<span class=\"qw-red\">bc</span>\
+             <span class=\"qw-gold\">1</span>d&lt;"
+        );
+        assert_eq!(to_unicode(NAME), "\u{e010}a\u{e0e2}\u{e0e3}\u{e013}d<");
+        assert_eq!(to_plain(&[0x05, 0x7f, 0x9d, 0x9e, 0x9f]), "•←<=>");
+    }
+
+    #[test]
+    fn encode_markup() {
+        assert_eq!(encode("$[a{bc}$1$]d<"), {
+            let mut name = NAME.to_vec();
+            name.insert(5, 0x11);
+            name
+        });
+        assert_eq!(
+            encode("{$$}$$ $x05$xzz"),
+            vec![b'$' | 0x80, b'$', b' ', 0x05, b'$', b'x', b'z', b'z']
+        );
+        assert_eq!(encode("ä"), b"?");
+        assert_eq!(encode("{a\u{e061}}"), vec![b'a' | 0x80, b'a']);
+    }
+
+    #[test]
+    fn unicode_round_trip() {
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(from_unicode(&to_unicode(&all)), all);
+        assert_eq!(from_unicode(&to_plain(b"a{$}")), b"a{$}");
+        assert_eq!(from_unicode("ä"), b"?");
+    }
+}
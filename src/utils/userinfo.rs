@@ -1,14 +1,24 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::protocol::errors::InfostringError;
 use crate::protocol::types::StringByte;
 #[cfg(feature = "ascii_strings")]
 use crate::utils::ascii_converter::AsciiConverter;
+use crate::utils::infostring::Infostring;
 
-#[derive(Clone, Debug, Serialize, Default)]
+/// the infostring of a `fullserverinfo "\k\v"` stufftext
+pub fn fullserverinfo(text: &[u8]) -> Option<&[u8]> {
+    let text = text.strip_prefix(b"fullserverinfo ")?.trim_ascii();
+    text.strip_prefix(b"\"")?.strip_suffix(b"\"")
+}
+
+/// an [`Infostring`] with its keys and values converted to [`StringByte`]s
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Userinfo {
     #[cfg(feature = "ascii_strings")]
     ascii_converter: AsciiConverter,
-    pub values: Vec<(StringByte, StringByte)>,
+    info: Infostring,
+    values: Vec<(StringByte, StringByte)>,
 }
 
 impl Userinfo {
@@ -17,6 +27,7 @@ impl Userinfo {
             ..Default::default()
         }
     }
+
     #[cfg(feature = "ascii_strings")]
     pub fn new_with_ascii_converter(ascii_converter: AsciiConverter) -> Userinfo {
         Userinfo {
@@ -25,135 +36,133 @@ impl Userinfo {
         }
     }
 
-    #[cfg(feature = "ascii_strings")]
-    pub fn update_key_value(&mut self, key: &StringByte, value: &StringByte) {
-        for i in 0..self.values.len() {
-            let (k, _) = &self.values[i];
-            if k.string == key.string {
-                self.values[i] = (key.clone(), value.clone());
-                return;
-            }
+    /// sets the size limit that [`Userinfo::set`] enforces, [`MAX_INFO_STRING`](crate::utils::infostring::MAX_INFO_STRING) by default
+    pub fn with_max_size(mut self, max_size: usize) -> Userinfo {
+        let mut info = Infostring::new(max_size);
+        for (k, v) in self.info.iter() {
+            info.insert(k, v);
         }
-        self.values.push((key.clone(), value.clone()));
+        self.info = info;
+        self
     }
 
-    #[cfg(not(feature = "ascii_strings"))]
-    pub fn update_key_value(&mut self, key: &StringByte, value: &StringByte) {
-        for i in 0..self.values.len() {
-            let (k, _) = &self.values[i];
-            if *k == *key {
-                self.values[i] = (key.clone(), value.clone());
-                return;
-            }
-        }
-        self.values.push((key.clone(), value.clone()));
+    // rebuilds the converted values after the infostring changed
+    fn sync(&mut self) {
+        self.values = self
+            .info
+            .iter()
+            .map(|(k, v)| (self.string(k), self.string(v)))
+            .collect();
     }
 
     #[cfg(feature = "ascii_strings")]
-    pub fn update(&mut self, userinfo: &StringByte) {
-        let mut start = true;
-        let mut key_vec: Vec<u8> = vec![];
-        let mut v: Vec<u8> = vec![];
-        let mut key = true;
-        for i in 0..userinfo.bytes.len() {
-            if userinfo.bytes[i] == 92 || i == userinfo.bytes.len() - 1 {
-                if i == userinfo.bytes.len() - 1 {
-                    v.push(userinfo.bytes[i]);
-                }
-                if start {
-                    start = false;
-                    continue;
-                } else {
-                    if key {
-                        key_vec = v.clone();
-                    } else {
-                        let sb_k = StringByte {
-                            string: self.ascii_converter.convert(key_vec.clone()),
-                            bytes: key_vec.clone(),
-                        };
-                        let sb_v = StringByte {
-                            string: self.ascii_converter.convert(v.clone()),
-                            bytes: v.clone(),
-                        };
-                        self.values.push((sb_k, sb_v))
-                    }
-                    key = !key;
-                    v.clear();
-                    continue;
-                }
-            }
-            v.push(userinfo.bytes[i]);
-        }
+    fn string(&self, bytes: &[u8]) -> StringByte {
+        StringByte::new(bytes, &self.ascii_converter)
     }
 
     #[cfg(not(feature = "ascii_strings"))]
+    fn string(&self, bytes: &[u8]) -> StringByte {
+        StringByte::new(bytes)
+    }
+
+    /// sets a key as sent by the server, an empty value removes it
+    pub fn update_key_value(&mut self, key: &StringByte, value: &StringByte) {
+        self.info.insert(&key.bytes, &value.bytes);
+        self.sync();
+    }
+
+    /// adds all values of the infostring, existing keys are replaced
     pub fn update(&mut self, userinfo: &StringByte) {
-        let mut start = true;
-        let mut key_vec: Vec<u8> = vec![];
-        let mut v: Vec<u8> = vec![];
-        let mut key = true;
-        for i in 0..userinfo.bytes.len() {
-            if userinfo.bytes[i] == 92 {
-                if start {
-                    start = false;
-                    continue;
-                } else {
-                    if key {
-                        key_vec = v.clone();
-                    } else {
-                        self.values.push((
-                            StringByte {
-                                bytes: key_vec.clone(),
-                            },
-                            StringByte { bytes: v.clone() },
-                        ))
-                    }
-                    key = !key;
-                    v.clear();
-                    continue;
-                }
-            }
-            v.push(userinfo.bytes[i]);
-        }
+        self.info.update(&userinfo.bytes);
+        self.sync();
     }
 
-    #[cfg(not(feature = "ascii_strings"))]
-    pub fn update_from_string(&mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
-        let key = key.into();
-        let value = value.into();
-        let sb_k = StringByte::new(key);
-        let sb_v = StringByte::new(value);
-        self.update_key_value(&sb_k, &sb_v);
+    /// replaces all values with the ones of the infostring
+    pub fn replace(&mut self, userinfo: &StringByte) {
+        self.info.clear();
+        self.info.update(&userinfo.bytes);
+        self.sync();
     }
 
-    #[cfg(feature = "ascii_strings")]
+    /// sets a key without validating it
     pub fn update_from_string(&mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
-        let key = key.into();
-        let value = value.into();
-        let sb_k = StringByte::new(key, &self.ascii_converter);
-        let sb_v = StringByte::new(value, &self.ascii_converter);
-        self.update_key_value(&sb_k, &sb_v);
-    }
-
-    pub fn as_bytes(&mut self) -> Vec<u8> {
-        let mut rb: Vec<u8> = Vec::new();
-        for i in 0..self.values.len() {
-            let (k, v) = &self.values[i];
-            #[cfg(feature = "ascii_strings")]
-            {
-                rb.push(b'\\');
-                rb.extend(k.bytes.clone());
-                rb.push(b'\\');
-                rb.extend(v.bytes.clone());
-            }
-            #[cfg(not(feature = "ascii_strings"))]
-            {
-                rb.push(b'\\');
-                rb.extend(k.bytes.clone());
-                rb.push(b'\\');
-                rb.extend(v.bytes.clone());
-            }
-        }
-        rb
+        self.info.insert(&key.into(), &value.into());
+        self.sync();
+    }
+
+    /// sets a key like `setinfo`, see [`Infostring::set`]
+    pub fn set(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<(), InfostringError> {
+        self.info.set(key.as_ref(), value.as_ref())?;
+        self.sync();
+        Ok(())
+    }
+
+    /// like [`Userinfo::set`] but allows `*` keys
+    pub fn set_star(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<(), InfostringError> {
+        self.info.set_star(key.as_ref(), value.as_ref())?;
+        self.sync();
+        Ok(())
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&StringByte> {
+        self.values
+            .iter()
+            .find(|(k, _)| k.bytes == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<StringByte> {
+        let value = self.info.remove(key)?;
+        self.sync();
+        Some(self.string(&value))
+    }
+
+    /// the keys and values in the order they were set
+    pub fn values(&self) -> &[(StringByte, StringByte)] {
+        &self.values
+    }
+
+    /// the raw infostring
+    pub fn info(&self) -> &Infostring {
+        &self.info
+    }
+
+    pub fn len(&self) -> usize {
+        self.info.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.info.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.info.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_follow_the_infostring() {
+        let mut userinfo = Userinfo::new();
+        userinfo.update_from_string("name", "bob");
+        userinfo.set("team", "red").unwrap();
+        assert_eq!(userinfo.get(b"name").unwrap().bytes, b"bob");
+        assert!(userinfo.set("*spectator", "1").is_err());
+        userinfo.set("name", "").unwrap();
+        assert_eq!(userinfo.get(b"name"), None);
+        assert_eq!(userinfo.values().len(), 1);
+        assert_eq!(userinfo.values()[0].1.bytes, b"red");
+        assert_eq!(userinfo.as_bytes(), userinfo.info().to_bytes());
     }
 }
@@ -1,4 +1,4 @@
-#[cfg(target_os = "unix")]
+#[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 #[cfg(target_os = "windows")]
 use std::os::windows::fs::MetadataExt;
@@ -265,3 +265,124 @@ pub fn environment_directory_entry_size_offset_complex(
     assert_eq!(directory_entry.data2, original_data_2);
     Ok(())
 }
+
+#[derive(Serialize, Clone, Debug, DataTypeRead)]
+#[datatyperead(datatype = Throwaway)]
+pub struct TaggedPayload {
+    pub value: u16,
+}
+
+#[derive(Serialize, Clone, Debug, DataTypeRead)]
+#[datatyperead(tag = u8, datatype = Throwaway)]
+pub enum Tagged {
+    #[datatyperead(tag = 0)]
+    Empty,
+    #[datatyperead(tag = 1)]
+    Payload(TaggedPayload),
+    #[datatyperead(tag = 2)]
+    Value(i32),
+}
+
+#[test]
+pub fn tagged_enum() -> Result<(), quakeworld::datatypes::reader::Error> {
+    use quakeworld::datatypes::reader::{DataTypeSize, Error};
+
+    let raw_data = generate_data!(vec![0, 1, 0x34, 0x12, 2], (-2_i32).to_le_bytes().to_vec());
+    let mut datatypereader = DataTypeReader::new(
+        raw_data,
+        #[cfg(feature = "trace")]
+        None,
+    );
+    let mut tagged: Vec<Tagged> = Vec::with_capacity(3);
+    datatypereader.read_exact_generic(&mut tagged)?;
+    assert!(matches!(tagged[0], Tagged::Empty));
+    assert!(matches!(tagged[1], Tagged::Payload(TaggedPayload { value: 0x1234 })));
+    assert!(matches!(tagged[2], Tagged::Value(-2)));
+    // the tag and the largest payload
+    assert_eq!(<Tagged as DataTypeSize>::datatype_size(), 5);
+
+    let mut datatypereader = DataTypeReader::new(
+        vec![3, 0, 0, 0, 0],
+        #[cfg(feature = "trace")]
+        None,
+    );
+    match <Tagged as DataTypeRead>::read(&mut datatypereader) {
+        Err(Error::UnknownTag(name, 3)) => assert_eq!(name, "Tagged"),
+        r => panic!("expected an unknown tag, got {:?}", r),
+    }
+    Ok(())
+}
+
+#[test]
+pub fn tagged_enum_fallback() -> Result<(), quakeworld::datatypes::reader::Error> {
+    use quakeworld::datatypes::mdl::SkinType;
+
+    let raw_data = generate_data!(
+        vec![0, 0, 0, 0, 1, 2, 3, 4],
+        // any tag other than 0 is a group
+        vec![5, 0, 0, 0, 2, 0, 0, 0],
+        0.1_f32.to_le_bytes().to_vec(),
+        0.2_f32.to_le_bytes().to_vec(),
+        vec![5, 6, 7, 8, 9, 10, 11, 12]
+    );
+    let mut datatypereader = DataTypeReader::new(
+        raw_data,
+        #[cfg(feature = "trace")]
+        None,
+    );
+    datatypereader.set_env("skin_size", 4_u32);
+    let mut skins: Vec<SkinType> = Vec::with_capacity(2);
+    datatypereader.read_exact_generic(&mut skins)?;
+    match &skins[0] {
+        SkinType::Single(skin) => assert_eq!(skin.data, vec![1, 2, 3, 4]),
+        s => panic!("expected a single skin, got {:?}", s),
+    }
+    match &skins[1] {
+        SkinType::Group(group) => {
+            assert_eq!(group.times, vec![0.1, 0.2]);
+            assert_eq!(group.skins.len(), 2);
+            assert_eq!(group.skins[1].data, vec![9, 10, 11, 12]);
+        }
+        s => panic!("expected a skin group, got {:?}", s),
+    }
+    Ok(())
+}
+
+#[test]
+pub fn reader_sources() -> Result<(), quakeworld::datatypes::reader::Error> {
+    use quakeworld::datatypes::reader::Error;
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    let raw_data = generate_data!(vec![8, 0, 0, 0, 4, 0, 0, 0], b"DEADBEEF".to_vec());
+
+    // borrowed
+    let mut datatypereader = DataTypeReader::from_slice(
+        &raw_data,
+        #[cfg(feature = "trace")]
+        None,
+    );
+    let entry = <EnvironmentDirectoryEntryBoth as DataTypeRead>::read(&mut datatypereader)?;
+    assert_eq!(entry.data, b"DEAD");
+    // the position is restored after reading from the offset
+    assert_eq!(datatypereader.position(), 8);
+    assert_eq!(datatypereader.len(), 16);
+
+    // any Read + Seek, offsets are absolute to the source
+    let mut cursor = Cursor::new(generate_data!(b"skip".to_vec(), raw_data.clone()));
+    cursor.seek(SeekFrom::Start(4))?;
+    let mut datatypereader = DataTypeReader::from_reader(
+        cursor,
+        #[cfg(feature = "trace")]
+        None,
+    )?;
+    assert_eq!(datatypereader.position(), 4);
+    let size = <u32 as DataTypeRead>::read(&mut datatypereader)?;
+    assert_eq!(size, 8);
+    datatypereader.set_position(16);
+    assert_eq!(<u32 as DataTypeRead>::read(&mut datatypereader)?, 0x46454542);
+    match <u8 as DataTypeRead>::read(&mut datatypereader) {
+        Err(Error::ReadSizeError(20, 20, 1)) => {}
+        r => panic!("expected a read size error, got {:?}", r),
+    }
+    Ok(())
+}
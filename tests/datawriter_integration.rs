@@ -0,0 +1,140 @@
+//! datatypes::writer tests
+
+use protocol_macros::{DataTypeRead, DataTypeWrite};
+use quakeworld::datatypes::bsp::Face29;
+use quakeworld::datatypes::common::{BoundingBox, DirectoryEntry, Vector3};
+use quakeworld::datatypes::reader::{DataTypeRead, DataTypeReader, Error};
+use quakeworld::datatypes::writer::{DataTypeWrite, DataTypeWriter};
+use serde::Serialize;
+
+fn reader(data: Vec<u8>) -> DataTypeReader {
+    DataTypeReader::new(
+        data,
+        #[cfg(feature = "trace")]
+        None,
+    )
+}
+
+#[derive(Serialize, Clone, Debug, DataTypeRead, DataTypeWrite)]
+#[datatyperead(datatype = Throwaway)]
+pub struct Sized {
+    #[datatyperead(environment)]
+    pub count: u16,
+    #[datatyperead(size_from = 8, string)]
+    pub name: Vec<u8>,
+    #[datatyperead(size_from = "count")]
+    pub values: Vec<i32>,
+    pub position: Vector3<f32>,
+}
+
+#[derive(Serialize, Clone, Debug, DataTypeRead, DataTypeWrite)]
+#[datatyperead(datatype = Throwaway)]
+pub struct Offset {
+    #[datatyperead(environment)]
+    pub entry: DirectoryEntry,
+    #[datatyperead(size_offset_from = "entry")]
+    pub data: Vec<u8>,
+}
+
+#[test]
+pub fn write_sized() -> Result<(), Error> {
+    let sized = Sized {
+        count: 2,
+        name: b"quake".to_vec(),
+        values: vec![-1, 256],
+        position: Vector3::new(1.0, -2.0, 0.5),
+    };
+    let mut datawriter = DataTypeWriter::new();
+    sized.write(&mut datawriter)?;
+    let data = datawriter.into_data();
+
+    let mut expected = vec![2, 0];
+    expected.extend(b"quake\0\0\0");
+    expected.extend((-1_i32).to_le_bytes());
+    expected.extend(256_i32.to_le_bytes());
+    expected.extend(1.0_f32.to_le_bytes());
+    expected.extend((-2.0_f32).to_le_bytes());
+    expected.extend(0.5_f32.to_le_bytes());
+    assert_eq!(data, expected);
+
+    let read = <Sized as DataTypeRead>::read(&mut reader(data))?;
+    assert_eq!(read.count, 2);
+    assert_eq!(read.name, b"quake");
+    assert_eq!(read.values, vec![-1, 256]);
+    assert_eq!(read.position.as_array(), [1.0, -2.0, 0.5]);
+    Ok(())
+}
+
+#[test]
+pub fn write_size_mismatch() {
+    let mut sized = Sized {
+        count: 3,
+        name: b"quake".to_vec(),
+        values: vec![-1, 256],
+        position: Vector3::new(0.0, 0.0, 0.0),
+    };
+    match sized.write(&mut DataTypeWriter::new()) {
+        Err(Error::WriteSizeError(3, 2)) => {}
+        r => panic!("expected a size error, got {:?}", r),
+    }
+    sized.count = 2;
+    sized.name = b"too long name".to_vec();
+    match sized.write(&mut DataTypeWriter::new()) {
+        Err(Error::WriteSizeError(8, 13)) => {}
+        r => panic!("expected a size error, got {:?}", r),
+    }
+}
+
+#[test]
+pub fn write_offset() -> Result<(), Error> {
+    let offset = Offset {
+        entry: DirectoryEntry {
+            offset: 12,
+            size: 4,
+        },
+        data: vec![1, 2, 3, 4],
+    };
+    let mut datawriter = DataTypeWriter::new();
+    offset.write(&mut datawriter)?;
+    // the gap between the entry and the data is filled
+    assert_eq!(datawriter.position(), 8);
+    let data = datawriter.into_data();
+    assert_eq!(data, vec![12, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4]);
+
+    let read = <Offset as DataTypeRead>::read(&mut reader(data))?;
+    assert_eq!(read.data, vec![1, 2, 3, 4]);
+    Ok(())
+}
+
+#[test]
+pub fn write_face() -> Result<(), Error> {
+    let face = Face29 {
+        plane_index: 3,
+        side: 1,
+        edge_index: 1024,
+        edge_count: 5,
+        texture_index: 2,
+        light_type: 0,
+        light_base: 255,
+        light_additional: vec![1, 2],
+        lightmap_index: -1,
+    };
+    let mut datawriter = DataTypeWriter::new();
+    face.write(&mut datawriter)?;
+    let data = datawriter.into_data();
+    assert_eq!(data.len(), 20);
+
+    let read = <Face29 as DataTypeRead>::read(&mut reader(data.clone()))?;
+    let mut datawriter = DataTypeWriter::new();
+    read.write(&mut datawriter)?;
+    assert_eq!(datawriter.into_data(), data);
+
+    let bounding_box = BoundingBox {
+        min: 1_i16,
+        max: -1_i16,
+    };
+    let mut datawriter = DataTypeWriter::new();
+    bounding_box.write(&mut datawriter)?;
+    assert_eq!(datawriter.into_data(), vec![1, 0, 0xff, 0xff]);
+    Ok(())
+}
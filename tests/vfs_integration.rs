@@ -267,3 +267,30 @@ pub fn conversion_integration() -> Result<(), quakeworld::vfs::Error> {
     assert!(path.equals_string("this/is/a/test"));
     Ok(())
 }
+
+#[test]
+pub fn bsp_lit_integration() -> Result<(), quakeworld::bsp::Error> {
+    let mut lit = b"QLIT\x01\x00\x00\x00".to_vec();
+    lit.extend([1, 2, 3, 4, 5, 6]);
+    let mut vfs = Vfs::default();
+    let pak_node = create_pak_node!(
+        "lit.pak",
+        ("maps/dm2.lit", lit),
+        ("maps/e1m1.lit", b"QLIT\x01\x00\x00\x00\x01")
+    );
+    vfs.insert_node(pak_node, "");
+
+    let mut bsp = quakeworld::bsp::Bsp {
+        light_maps: vec![10, 20],
+        ..Default::default()
+    };
+    assert!(!bsp.load_lit(&vfs, "maps/dm4.bsp")?);
+    assert!(bsp.lit.is_none());
+    assert!(matches!(
+        bsp.load_lit(&vfs, "maps/e1m1.bsp"),
+        Err(quakeworld::bsp::Error::LitSize(1, 6))
+    ));
+    assert!(bsp.load_lit(&vfs, "maps/dm2.bsp")?);
+    assert_eq!(bsp.lit.unwrap().data, vec![1, 2, 3, 4, 5, 6]);
+    Ok(())
+}